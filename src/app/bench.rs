@@ -0,0 +1 @@
+pub use crate::serial::bench::run_bench;
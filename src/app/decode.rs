@@ -0,0 +1,265 @@
+// `sergw decode`: print a capture with the inspector's formats, without the
+// TUI. Input is either raw bytes (e.g. a `--split-logs` file) or a pcap
+// written by the inspector's export, whose packets keep their timestamp and
+// direction.
+
+use std::io::{self, BufWriter, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use anyhow::{bail, Context, Result};
+
+use crate::cli::{Decode, DecodeFormat};
+use crate::ui::inspector::{dump_bytes, hex_ascii_rows, DumpFormat};
+
+// Raw input without --frame-delimiter is shown in rows of this many bytes
+const ROW: usize = 16;
+const PCAP_HEADER_LEN: usize = 24;
+const PCAP_RECORD_HEADER_LEN: usize = 16;
+// See `ui::pcap` for the export's link type and pseudo header
+const LINKTYPE_USER0: u32 = 147;
+const PSEUDO_HEADER_LEN: usize = 20;
+
+/// One frame to print: where it came from and its bytes
+#[derive(Debug, PartialEq, Eq)]
+struct Frame<'a> {
+    label: String,
+    data: &'a [u8],
+}
+
+pub fn run_decode(decode: Decode) -> Result<()> {
+    let mut data = Vec::new();
+    if decode.file.as_os_str() == "-" {
+        io::stdin()
+            .read_to_end(&mut data)
+            .context("Reading capture from stdin")?;
+    } else {
+        data = std::fs::read(&decode.file)
+            .with_context(|| format!("Reading capture {}", decode.file.display()))?;
+    }
+    let delimiter = decode.frame_delimiter.as_deref();
+    let frames = if pcap_byte_order(&data).is_some() {
+        pcap_frames(&data, delimiter)?
+    } else {
+        raw_frames(&data, delimiter)
+    };
+    let mut out = BufWriter::new(io::stdout().lock());
+    let written = frames
+        .iter()
+        .flat_map(|frame| render(frame, decode.format))
+        .try_for_each(|line| writeln!(out, "{line}"))
+        .and_then(|()| out.flush());
+    match written {
+        // `sergw decode big.bin | head` is fine
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        other => other.context("Writing to stdout"),
+    }
+}
+
+fn dump_format(format: DecodeFormat) -> DumpFormat {
+    match format {
+        DecodeFormat::HexAscii => DumpFormat::HexAscii,
+        DecodeFormat::Hex => DumpFormat::Hex,
+        DecodeFormat::Ascii => DumpFormat::Ascii,
+        DecodeFormat::AsciiEscaped => DumpFormat::AsciiEscaped,
+        DecodeFormat::Dec => DumpFormat::Dec,
+    }
+}
+
+/// The frame's label, then its bytes; continuation rows are indented under it
+fn render(frame: &Frame, format: DecodeFormat) -> Vec<String> {
+    let rows = match dump_format(format) {
+        DumpFormat::HexAscii => hex_ascii_rows(frame.data, ROW),
+        fmt => vec![dump_bytes(frame.data, fmt, usize::MAX)],
+    };
+    let indent = " ".repeat(frame.label.chars().count());
+    rows.into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let label = if i == 0 { &frame.label } else { &indent };
+            format!("{label}  {}", row.trim_end())
+        })
+        .collect()
+}
+
+/// `data` cut after every `delimiter`, or into fixed rows without one
+fn split<'a>(data: &'a [u8], delimiter: Option<&[u8]>) -> Vec<(usize, &'a [u8])> {
+    let Some(delim) = delimiter.filter(|d| !d.is_empty()) else {
+        return data
+            .chunks(ROW)
+            .enumerate()
+            .map(|(i, row)| (i * ROW, row))
+            .collect();
+    };
+    let mut frames = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i + delim.len() <= data.len() {
+        if &data[i..i + delim.len()] == delim {
+            i += delim.len();
+            frames.push((start, &data[start..i]));
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+    if start < data.len() {
+        frames.push((start, &data[start..]));
+    }
+    frames
+}
+
+fn raw_frames<'a>(data: &'a [u8], delimiter: Option<&[u8]>) -> Vec<Frame<'a>> {
+    split(data, delimiter)
+        .into_iter()
+        .map(|(offset, data)| Frame {
+            label: format!("{offset:08x}"),
+            data,
+        })
+        .collect()
+}
+
+/// pcap magic in either byte order; true means big-endian fields
+fn pcap_byte_order(data: &[u8]) -> Option<bool> {
+    match data.get(..4)? {
+        [0xd4, 0xc3, 0xb2, 0xa1] => Some(false),
+        [0xa1, 0xb2, 0xc3, 0xd4] => Some(true),
+        _ => None,
+    }
+}
+
+fn pcap_frames<'a>(data: &'a [u8], delimiter: Option<&[u8]>) -> Result<Vec<Frame<'a>>> {
+    let big_endian = pcap_byte_order(data).unwrap_or_default();
+    let u32_at = |at: usize| -> Option<u32> {
+        let bytes: [u8; 4] = data.get(at..at + 4)?.try_into().ok()?;
+        Some(match big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    };
+    let Some(link_type) = u32_at(20) else {
+        bail!("pcap header is truncated");
+    };
+    let mut frames = Vec::new();
+    let mut at = PCAP_HEADER_LEN;
+    while at < data.len() {
+        let (Some(secs), Some(micros), Some(incl)) = (u32_at(at), u32_at(at + 4), u32_at(at + 8))
+        else {
+            bail!("pcap record header at byte {at} is truncated");
+        };
+        let body_at = at + PCAP_RECORD_HEADER_LEN;
+        let Some(body) = data.get(body_at..body_at + incl as usize) else {
+            bail!("pcap record at byte {at} is truncated");
+        };
+        at = body_at + incl as usize;
+        let (source, payload) = match link_type {
+            LINKTYPE_USER0 if body.len() >= PSEUDO_HEADER_LEN => {
+                let (header, payload) = body.split_at(PSEUDO_HEADER_LEN);
+                (direction(header), payload)
+            }
+            _ => (String::new(), body),
+        };
+        // Each packet is already one read; a delimiter can only cut it further
+        let parts = match delimiter {
+            Some(_) => split(payload, delimiter),
+            None => vec![(0, payload)],
+        };
+        for (_, part) in parts {
+            frames.push(Frame {
+                label: format!("{secs}.{micros:06}{source}"),
+                data: part,
+            });
+        }
+    }
+    Ok(frames)
+}
+
+/// " in" for device output, " out 10.0.0.2:4000" for a client's writes
+fn direction(header: &[u8]) -> String {
+    if header[0] == 0 {
+        return " in".to_string();
+    }
+    let port = u16::from_be_bytes([header[2], header[3]]);
+    let ip = match header[1] {
+        4 => IpAddr::V4(Ipv4Addr::new(header[4], header[5], header[6], header[7])),
+        6 => {
+            let octets: [u8; 16] = header[4..20].try_into().unwrap_or_default();
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return " out".to_string(),
+    };
+    format!(" out {}", SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_rows_and_delimited_frames() {
+        let data = b"OK\r\nERROR\r\npartial";
+        let rows = raw_frames(data, None);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].label, "00000010");
+
+        let frames = raw_frames(data, Some(b"\r\n"));
+        let parts: Vec<&[u8]> = frames.iter().map(|f| f.data).collect();
+        assert_eq!(parts, [&b"OK\r\n"[..], b"ERROR\r\n", b"partial"]);
+        assert_eq!(frames[1].label, "00000004");
+        assert_eq!(
+            render(&frames[0], DecodeFormat::AsciiEscaped),
+            ["00000000  OK\\r\\n"]
+        );
+        assert_eq!(
+            render(&frames[0], DecodeFormat::Hex),
+            ["00000000  4f 4b 0d 0a"]
+        );
+    }
+
+    #[test]
+    fn hex_ascii_continuation_rows_are_indented() {
+        let frame = Frame {
+            label: "00000000".into(),
+            data: b"0123456789abcdefXY",
+        };
+        let lines = render(&frame, DecodeFormat::HexAscii);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("00000000  30 31 "));
+        assert!(lines[1].starts_with("          58 59 "));
+        assert!(lines[1].ends_with("|XY|"));
+    }
+
+    #[test]
+    fn pcap_export_keeps_time_and_direction() {
+        let mut pcap = Vec::new();
+        pcap.extend_from_slice(&0xa1b2_c3d4u32.to_le_bytes());
+        pcap.extend_from_slice(&[2, 0, 4, 0]);
+        pcap.extend_from_slice(&[0; 8]);
+        pcap.extend_from_slice(&65_535u32.to_le_bytes());
+        pcap.extend_from_slice(&LINKTYPE_USER0.to_le_bytes());
+        let mut record = |micros: u32, header: [u8; PSEUDO_HEADER_LEN], payload: &[u8]| {
+            let len = (PSEUDO_HEADER_LEN + payload.len()) as u32;
+            for field in [1_700_000_000, micros, len, len] {
+                pcap.extend_from_slice(&u32::to_le_bytes(field));
+            }
+            pcap.extend_from_slice(&header);
+            pcap.extend_from_slice(payload);
+        };
+        record(250_000, [0; PSEUDO_HEADER_LEN], b"hi");
+        let mut client = [0; PSEUDO_HEADER_LEN];
+        client[..8].copy_from_slice(&[1, 4, 0x0f, 0xa0, 10, 0, 0, 2]);
+        record(7, client, b"AT\rAT\r");
+
+        let frames = pcap_frames(&pcap, None).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].label, "1700000000.250000 in");
+        assert_eq!(frames[0].data, b"hi");
+        assert_eq!(frames[1].label, "1700000000.000007 out 10.0.0.2:4000");
+
+        let split = pcap_frames(&pcap, Some(b"\r")).unwrap();
+        assert_eq!(split.len(), 3);
+        assert_eq!(split[2].data, b"AT\r");
+
+        pcap.truncate(pcap.len() - 1);
+        assert!(pcap_frames(&pcap, None).is_err());
+    }
+}
@@ -1,4 +1,6 @@
 pub mod pty;
+#[cfg(target_os = "linux")]
+pub mod replay;
 pub mod serial;
 pub mod ui; // orchestrator
 pub use serial::run_mock_serial;
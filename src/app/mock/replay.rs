@@ -0,0 +1,176 @@
+// Scripted device output for the mock serial: a file (or stdin) is written to
+// the PTY master so clients of the bridged device see deterministic data.
+
+use std::fs::File;
+use std::io::{IsTerminal, Read, Write};
+use std::os::unix::io::OwnedFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+use crate::cli::ReplayArgs;
+
+pub struct ReplayScript {
+    data: Vec<u8>,
+    interval: Duration,
+    looping: bool,
+}
+
+impl ReplayScript {
+    /// Reads the whole script up front so `--loop` can replay stdin too
+    pub fn load(args: &ReplayArgs) -> Result<Option<Self>> {
+        let Some(path) = &args.replay else {
+            return Ok(None);
+        };
+        let mut data = Vec::new();
+        if path == "-" {
+            std::io::stdin()
+                .read_to_end(&mut data)
+                .context("Reading replay script from stdin")?;
+        } else {
+            data = std::fs::read(path).with_context(|| format!("Reading replay script {path}"))?;
+        }
+        Ok(Some(Self {
+            data,
+            interval: Duration::from_millis(args.replay_interval_ms),
+            looping: args.loop_replay,
+        }))
+    }
+
+    /// With an interval the script goes out a line at a time, otherwise in one write
+    fn chunks(&self) -> Vec<&[u8]> {
+        if self.interval.is_zero() {
+            vec![&self.data[..]]
+        } else {
+            self.data.split_inclusive(|&b| b == b'\n').collect()
+        }
+    }
+
+    /// Write the script (repeatedly with `--loop`) until done or `stop` is set.
+    /// `on_pass` gets the 1-based pass number after each complete pass.
+    pub fn run(
+        &self,
+        out: &mut impl Write,
+        stop: &AtomicBool,
+        mut on_pass: impl FnMut(u64),
+    ) -> std::io::Result<()> {
+        if self.data.is_empty() {
+            return Ok(());
+        }
+        let mut pass = 0u64;
+        loop {
+            for (i, chunk) in self.chunks().into_iter().enumerate() {
+                if stop.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                if i > 0 && !sleep_unless_stopped(self.interval, stop) {
+                    return Ok(());
+                }
+                out.write_all(chunk)?;
+                out.flush()?;
+            }
+            pass += 1;
+            on_pass(pass);
+            if !self.looping || !sleep_unless_stopped(self.interval, stop) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Sleeps in short slices so Ctrl+C isn't held up by a long interval; false if stopped
+fn sleep_unless_stopped(total: Duration, stop: &AtomicBool) -> bool {
+    let end = Instant::now() + total;
+    while !stop.load(Ordering::Relaxed) {
+        let now = Instant::now();
+        if now >= end {
+            return true;
+        }
+        std::thread::sleep((end - now).min(Duration::from_millis(50)));
+    }
+    false
+}
+
+/// No terminal (CI, test harness): replay without the chat UI. Whatever
+/// clients send to the device is copied to stdout; Ctrl+C exits.
+pub fn run_headless(master: OwnedFd, device: &str, script: ReplayScript) -> Result<()> {
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let stop = stop.clone();
+        let _ = ctrlc::set_handler(move || stop.store(true, Ordering::Relaxed));
+    }
+    let mut master_file: File = master.into();
+    let mut reader = master_file.try_clone()?;
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        let mut stdout = std::io::stdout();
+        while let Ok(n) = reader.read(&mut buf) {
+            if n == 0
+                || stdout
+                    .write_all(&buf[..n])
+                    .and_then(|_| stdout.flush())
+                    .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    eprintln!("mock serial: replaying to {device} (Ctrl+C to quit)");
+    script
+        .run(&mut master_file, &stop, |pass| {
+            eprintln!("* replay pass {pass} done ({} bytes)", script.data.len());
+        })
+        .context("Writing replay script to the PTY")?;
+    // Keep the device alive after the script so clients can finish reading
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    Ok(())
+}
+
+pub fn wants_headless() -> bool {
+    !std::io::stdout().is_terminal()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(data: &[u8], interval_ms: u64, looping: bool) -> ReplayScript {
+        ReplayScript {
+            data: data.to_vec(),
+            interval: Duration::from_millis(interval_ms),
+            looping,
+        }
+    }
+
+    #[test]
+    fn chunks_by_line_only_with_interval() {
+        let s = script(b"a\nbb\nc", 0, false);
+        assert_eq!(s.chunks(), vec![&b"a\nbb\nc"[..]]);
+        let s = script(b"a\nbb\nc", 1, false);
+        assert_eq!(s.chunks(), vec![&b"a\n"[..], b"bb\n", b"c"]);
+    }
+
+    #[test]
+    fn loops_until_stopped() {
+        let s = script(b"x\n", 0, true);
+        let stop = AtomicBool::new(false);
+        let mut out = Vec::new();
+        s.run(&mut out, &stop, |pass| {
+            if pass == 3 {
+                stop.store(true, Ordering::Relaxed);
+            }
+        })
+        .unwrap();
+        assert_eq!(out, b"x\nx\nx\n");
+
+        let once = script(b"x\n", 0, false);
+        let mut out = Vec::new();
+        once.run(&mut out, &AtomicBool::new(false), |_| {}).unwrap();
+        assert_eq!(out, b"x\n");
+    }
+}
@@ -4,10 +4,13 @@
 use anyhow::Result;
 
 #[cfg(target_os = "linux")]
-pub fn run_mock_serial() -> Result<()> {
+pub fn run_mock_serial(eol: crate::cli::EolOpt, replay: crate::cli::ReplayArgs) -> Result<()> {
     use super::pty::create_pty_pair;
+    use super::replay::{self, ReplayScript};
     use super::ui::run_mock_chat_with_title;
 
+    // Load before the TUI takes over the terminal (the script may be stdin)
+    let script = ReplayScript::load(&replay)?;
     let (master, _slave_fd, slave_path) = create_pty_pair()?;
 
     // Create a default temporary alias symlink for the slave path for the program duration
@@ -24,6 +27,13 @@ pub fn run_mock_serial() -> Result<()> {
     }
     let _guard = SymlinkGuard(alias_path);
 
-    run_mock_chat_with_title(master, format!("mock serial | {alias_path}"))?;
+    match script {
+        Some(script) if replay::wants_headless() => {
+            replay::run_headless(master, alias_path, script)?
+        }
+        script => {
+            run_mock_chat_with_title(master, format!("mock serial | {alias_path}"), eol, script)?
+        }
+    }
     Ok(())
 }
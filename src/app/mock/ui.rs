@@ -12,17 +12,23 @@ use std::sync::{
 #[cfg(target_os = "linux")]
 use std::time::{Duration, Instant};
 
+#[cfg(target_os = "linux")]
+use super::replay::ReplayScript;
+#[cfg(target_os = "linux")]
+use crate::cli::EolOpt;
 #[cfg(target_os = "linux")]
 use crate::metrics::ThroughputAverager;
 #[cfg(target_os = "linux")]
+use crate::ui::input::InputLine;
+#[cfg(target_os = "linux")]
 use anyhow::Result;
 #[cfg(target_os = "linux")]
 use crossbeam_channel as channel;
 #[cfg(target_os = "linux")]
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, DisableBracketedPaste, Event, KeyCode},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{disable_raw_mode, LeaveAlternateScreen},
 };
 #[cfg(target_os = "linux")]
 use ratatui::{
@@ -33,17 +39,107 @@ use ratatui::{
     Terminal,
 };
 
+// Line settings the mock claims to use. A PTY ignores them, but showing the
+// intended values lets client-side autobaud and config tests be scripted.
+#[cfg(target_os = "linux")]
+const BAUDS: [u32; 11] = [
+    1200, 2400, 4800, 9600, 19_200, 38_400, 57_600, 115_200, 230_400, 460_800, 921_600,
+];
+#[cfg(target_os = "linux")]
+const PARITIES: [char; 5] = ['N', 'O', 'E', 'M', 'S'];
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, PartialEq, Eq)]
+struct MockLine {
+    baud: usize,
+    parity: usize,
+    data_bits: u8,
+    stop_bits: u8,
+}
+
+#[cfg(target_os = "linux")]
+impl Default for MockLine {
+    fn default() -> Self {
+        Self {
+            baud: 7, // 115200
+            parity: 0,
+            data_bits: 8,
+            stop_bits: 1,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl MockLine {
+    fn next_baud(&mut self) {
+        self.baud = (self.baud + 1) % BAUDS.len();
+    }
+
+    fn next_parity(&mut self) {
+        self.parity = (self.parity + 1) % PARITIES.len();
+    }
+
+    fn next_data_bits(&mut self) {
+        self.data_bits = if self.data_bits >= 8 {
+            5
+        } else {
+            self.data_bits + 1
+        };
+    }
+
+    fn next_stop_bits(&mut self) {
+        self.stop_bits = if self.stop_bits == 1 { 2 } else { 1 };
+    }
+
+    /// Conventional short form, e.g. `115200 8N1`
+    fn summary(&self) -> String {
+        format!(
+            "{} {}{}{}",
+            BAUDS[self.baud], self.data_bits, PARITIES[self.parity], self.stop_bits
+        )
+    }
+}
+
+// Feeds the Out throughput figure for bytes the replay thread writes
+#[cfg(target_os = "linux")]
+struct CountingWriter {
+    inner: File,
+    count: Arc<AtomicU64>,
+}
+
 #[cfg(target_os = "linux")]
-pub fn run_mock_chat_with_title(master: OwnedFd, title: String) -> Result<()> {
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn run_mock_chat_with_title(
+    master: OwnedFd,
+    title: String,
+    eol: EolOpt,
+    replay: Option<ReplayScript>,
+) -> Result<()> {
     let mut master_file: File = master.into();
 
-    enable_raw_mode()?;
-    let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
+    crate::ui::terminal::enter(true)?;
+    let backend = CrosstermBackend::new(std::io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
     let stop = Arc::new(AtomicBool::new(false));
+    // Raw mode turns Ctrl+C into a key; this catches SIGTERM/SIGHUP so the
+    // terminal is restored on the way out
+    {
+        let stop = stop.clone();
+        let _ = ctrlc::set_handler(move || stop.store(true, Ordering::Relaxed));
+    }
     let rx_bytes = Arc::new(AtomicU64::new(0));
     let tx_bytes = Arc::new(AtomicU64::new(0));
     let (log_tx, log_rx) = channel::unbounded::<String>();
@@ -74,15 +170,34 @@ pub fn run_mock_chat_with_title(master: OwnedFd, title: String) -> Result<()> {
         }
     });
 
+    // Scripted output runs alongside typed input
+    if let Some(script) = replay {
+        let stop_s = stop.clone();
+        let log_tx_replay = log_tx.clone();
+        let mut writer = CountingWriter {
+            inner: master_file.try_clone()?,
+            count: tx_bytes.clone(),
+        };
+        std::thread::spawn(move || {
+            let result = script.run(&mut writer, &stop_s, |pass| {
+                let _ = log_tx_replay.send(format!("* replay pass {pass} done"));
+            });
+            if let Err(e) = result {
+                let _ = log_tx_replay.send(format!("* replay failed: {e}"));
+            }
+        });
+    }
+
     let mut logs: Vec<String> = Vec::new();
-    let mut input = String::new();
+    let mut input = InputLine::default();
+    let mut line = MockLine::default();
     let mut last_rx = 0u64;
     let mut last_tx = 0u64;
     let mut avg_in = ThroughputAverager::new(5.0);
     let mut avg_out = ThroughputAverager::new(5.0);
     let mut last_time = Instant::now();
 
-    loop {
+    while !stop.load(Ordering::Relaxed) {
         while let Ok(line) = log_rx.try_recv() {
             logs.push(line);
             if logs.len() > 200 {
@@ -111,7 +226,10 @@ pub fn run_mock_chat_with_title(master: OwnedFd, title: String) -> Result<()> {
                 ])
                 .split(f.size());
 
-            let header = Paragraph::new(format!("{title} | In: {inbound} B/s Out: {outbound} B/s"));
+            let header = Paragraph::new(format!(
+                "{title} | {} | In: {inbound} B/s Out: {outbound} B/s",
+                line.summary()
+            ));
             f.render_widget(header, chunks[0]);
 
             // Auto-scroll: render only the last lines that fit
@@ -127,46 +245,92 @@ pub fn run_mock_chat_with_title(master: OwnedFd, title: String) -> Result<()> {
                 .block(Block::default().title("Messages").borders(Borders::ALL));
             f.render_widget(para, chunks[1]);
 
-            let input_box = Paragraph::new(input.clone()).block(
+            let (shown, cursor) = input.view(chunks[2].width.saturating_sub(2) as usize);
+            let input_box = Paragraph::new(shown).block(
                 Block::default()
-                    .title("Input (Enter to send, Ctrl+C to quit)")
+                    .title("Input (Enter to send, F2 baud, F3 parity, F4 data bits, F5 stop bits, Ctrl+C to quit)")
                     .borders(Borders::ALL),
             );
             f.render_widget(input_box, chunks[2]);
+            f.set_cursor(chunks[2].x + 1 + cursor as u16, chunks[2].y + 1);
         })?;
 
         if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(k) = event::read()? {
-                match k.code {
-                    KeyCode::Char('c')
-                        if k.modifiers
-                            .contains(crossterm::event::KeyModifiers::CONTROL) =>
-                    {
-                        break
-                    }
-                    KeyCode::Char(c) => input.push(c),
-                    KeyCode::Backspace => {
-                        input.pop();
-                    }
-                    KeyCode::Enter => {
-                        if !input.is_empty() {
-                            let mut to_send = input.clone();
-                            to_send.push('\n');
-                            let _ = master_file.write_all(to_send.as_bytes());
-                            tx_bytes.fetch_add(to_send.len() as u64, Ordering::Relaxed);
-                            let _ = log_tx.send(format!("> {input}"));
-                            input.clear();
-                        }
+            let k = match event::read()? {
+                Event::Key(k) => k,
+                Event::Paste(text) => {
+                    input.insert_str(&text);
+                    continue;
+                }
+                _ => continue,
+            };
+            match k.code {
+                KeyCode::Char('c')
+                    if k.modifiers
+                        .contains(crossterm::event::KeyModifiers::CONTROL) =>
+                {
+                    break
+                }
+                KeyCode::Char(c) => input.insert(c),
+                KeyCode::Backspace => input.backspace(),
+                KeyCode::Delete => input.delete(),
+                KeyCode::Left => input.left(),
+                KeyCode::Right => input.right(),
+                KeyCode::Home => input.home(),
+                KeyCode::End => input.end(),
+                KeyCode::Enter if !input.is_empty() => {
+                    let mut to_send = input.as_str().to_string();
+                    to_send.push_str(eol.as_str());
+                    let _ = master_file.write_all(to_send.as_bytes());
+                    tx_bytes.fetch_add(to_send.len() as u64, Ordering::Relaxed);
+                    let _ = log_tx.send(format!("> {}", input.as_str()));
+                    input.clear();
+                }
+                KeyCode::Esc => input.clear(),
+                KeyCode::F(n @ 2..=5) => {
+                    match n {
+                        2 => line.next_baud(),
+                        3 => line.next_parity(),
+                        4 => line.next_data_bits(),
+                        _ => line.next_stop_bits(),
                     }
-                    KeyCode::Esc => input.clear(),
-                    _ => {}
+                    let _ = log_tx.send(format!("* line settings: {}", line.summary()));
                 }
+                _ => {}
             }
         }
     }
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    )?;
     terminal.show_cursor()?;
     Ok(())
 }
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_settings_cycle_and_wrap() {
+        let mut line = MockLine::default();
+        assert_eq!(line.summary(), "115200 8N1");
+        line.next_parity();
+        line.next_stop_bits();
+        assert_eq!(line.summary(), "115200 8O2");
+        for _ in 0..4 {
+            line.next_baud();
+        }
+        assert_eq!(line.summary(), "1200 8O2");
+        line.next_data_bits();
+        assert_eq!(line.data_bits, 5);
+        for _ in 0..4 {
+            line.next_parity();
+        }
+        assert_eq!(PARITIES[line.parity], 'N');
+    }
+}
@@ -1,4 +1,8 @@
 // High-level app modules
+pub mod bench;
+pub mod decode;
 pub mod listen;
 pub mod listener;
 pub mod mock;
+pub mod ping;
+pub mod selftest;
@@ -0,0 +1 @@
+pub use crate::serial::ping::run_ping;
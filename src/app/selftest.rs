@@ -0,0 +1 @@
+pub use crate::serial::selftest::run_selftest;
@@ -1,16 +1,47 @@
+use std::ffi::OsString;
+use std::io::IsTerminal;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 
-use clap::{Parser, Subcommand, ValueEnum};
+use anyhow::Context;
+use bytes::Bytes;
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
 use serialport::{DataBits, Parity, StopBits};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 #[command(propagate_version = true)]
 pub struct Cli {
+    /// TOML file with defaults for listen and ports options ([listen]/[ports] sections)
+    #[arg(long, global = true, env = "SERGW_CONFIG", value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+impl Cli {
+    /// Parse `args`, then fill options they (and the environment) left unset
+    /// from `--config`. Command-line mistakes come back as `clap::Error`.
+    pub fn parse_with_config(mut args: Vec<OsString>) -> anyhow::Result<Self> {
+        let cmd = Self::command();
+        let matches = cmd.clone().try_get_matches_from(&args)?;
+        if let Some(path) = matches.get_one::<PathBuf>("config") {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("Reading config {}", path.display()))?;
+            let extra = crate::config::config_args(&text, &cmd, &matches)
+                .with_context(|| format!("Parsing config {}", path.display()))?;
+            if !extra.is_empty() {
+                args.extend(extra);
+                return Ok(Self::from_arg_matches(&cmd.try_get_matches_from(&args)?)?);
+            }
+        }
+        Ok(Self::from_arg_matches(&matches)?)
+    }
+}
+
+// Parsed once at startup, so the size of `Listen` doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 pub enum Commands {
     /// List available serial ports
@@ -24,9 +55,29 @@ pub enum Commands {
         /// Output format
         #[arg(long, value_enum, default_value_t = PortsFormat::Text)]
         format: PortsFormat,
+        /// Only list ports whose name matches this glob (e.g. '/dev/ttyACM*')
+        #[arg(id = "match", long = "match", value_name = "GLOB")]
+        port_match: Option<String>,
+        /// Open each port briefly and list the baud rates its driver accepts
+        #[arg(long)]
+        baud_list: bool,
     },
     /// Bridge a serial port to TCP
     Listen(Listen),
+    /// Measure serial round-trip latency against an echoing device or loopback
+    Ping(Ping),
+    /// Measure sustained serial read throughput
+    Bench(Bench),
+    /// Verify TX/RX wiring by reading back a known pattern through a loopback
+    Selftest(Selftest),
+    /// Print a capture file (raw bytes or an inspector pcap export) as hex/text
+    Decode(Decode),
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to generate for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
 
     #[cfg(target_os = "linux")]
     /// Mock utilities
@@ -38,33 +89,402 @@ pub enum Commands {
 
 #[derive(Parser, Clone, Debug)]
 pub struct Listen {
+    /// Serial port to open (auto-select if exactly one is found and this is omitted)
+    #[arg(long, env = "SERGW_SERIAL")]
+    pub serial: Option<String>,
+
+    /// Also consider built-in (non-USB) ports such as /dev/ttyS0 or /dev/ttyAMA0 when auto-selecting
+    #[arg(long)]
+    pub include_builtin: bool,
+
+    /// Only auto-select among ports whose name matches this glob (e.g. '/dev/ttyACM*')
+    #[arg(
+        id = "match",
+        long = "match",
+        value_name = "GLOB",
+        conflicts_with = "serial"
+    )]
+    pub port_match: Option<String>,
+
+    /// Bridge to a remote TCP serial server (e.g. another sergw) instead of a local port
+    #[arg(long, env = "SERGW_UPSTREAM", value_name = "HOST:PORT", conflicts_with_all = ["serial", "include_builtin", "match"])]
+    pub upstream: Option<String>,
+
+    /// Bridge to a serial port on an RFC 2217 (Telnet COM-PORT-OPTION) device server, applying --baud and line settings remotely
+    #[cfg(feature = "rfc2217")]
+    #[arg(long, env = "SERGW_UPSTREAM_RFC2217", value_name = "HOST:PORT", conflicts_with_all = ["serial", "include_builtin", "match", "upstream", "dtr", "rts"])]
+    pub upstream_rfc2217: Option<String>,
+
+    /// Baud rate. Given a comma-separated list, each is tried at startup and the
+    /// first giving readable output is used (the first if none does)
+    #[arg(
+        id = "baud",
+        long = "baud",
+        env = "SERGW_BAUD",
+        value_name = "RATE[,RATE...]",
+        value_delimiter = ',',
+        default_value = "115200"
+    )]
+    pub baud_rates: Vec<u32>,
+
+    /// TCP listen address (HOST:PORT; hostnames and [v6]:port accepted).
+    /// Repeat to serve the same device on several ports
+    #[arg(
+        long,
+        env = "SERGW_HOST",
+        value_delimiter = ',',
+        default_value = "127.0.0.1:5656"
+    )]
+    pub host: Vec<String>,
+
+    /// Address family to prefer when --host resolves to several addresses
+    #[arg(long, env = "SERGW_PREFER_IP", value_enum, default_value_t = IpFamily::V4)]
+    pub prefer_ip: IpFamily,
+
+    #[command(flatten)]
+    pub line: LineSettings,
+
+    /// Buffer capacity (messages) for internal channels
+    #[arg(long, env = "SERGW_BUFFER", default_value_t = 4096)]
+    pub buffer: usize,
+
+    /// Bytes of recent serial output replayed to each newly connected client (0 disables)
+    #[arg(long, env = "SERGW_SCROLLBACK", default_value_t = 0)]
+    pub scrollback: usize,
+
+    /// Run without the TUI, logging events to stderr (implied when stdout is not a terminal)
+    #[arg(long, env = "SERGW_NO_TUI")]
+    pub no_tui: bool,
+
+    /// Cap serial -> TCP throughput; frames over the budget are dropped, not queued
+    #[arg(long, env = "SERGW_TCP_RATE", value_name = "BYTES_PER_SEC", value_parser = clap::value_parser!(u64).range(1..))]
+    pub tcp_rate: Option<u64>,
+
+    /// Disconnect a client that sends more than this toward the device for 3 seconds in a row
+    #[arg(long, value_name = "BYTES_PER_SEC")]
+    pub client_ingress_rate: Option<u64>,
+
+    /// Log throughput every N seconds (0 disables; default 5 headless, off with the TUI)
+    #[arg(long, env = "SERGW_METRICS_INTERVAL", value_name = "SECS")]
+    pub metrics_interval: Option<u64>,
+
+    /// Stop after running for this many seconds
+    #[arg(long, value_name = "SECS")]
+    pub duration: Option<u64>,
+
+    /// Stop once this many bytes have been read from serial
+    #[arg(long, value_name = "BYTES")]
+    pub stop_after_bytes: Option<u64>,
+
+    /// Keep retrying the initial serial open for up to this many seconds (0 fails immediately)
+    #[arg(
+        long,
+        env = "SERGW_WAIT_FOR_DEVICE",
+        value_name = "SECS",
+        default_value_t = 0
+    )]
+    pub wait_for_device: u64,
+
+    /// TCP message framing; `length-prefix` wraps each message in a little-endian u32 length
+    #[arg(long, env = "SERGW_FRAME", value_enum, default_value_t = FrameMode::Raw)]
+    pub frame: FrameMode,
+
+    /// Serial-side packet framing; `cobs` decodes 0x00-delimited COBS packets from the device and encodes what is sent to it (needs `--frame length-prefix`)
+    #[arg(long, env = "SERGW_FRAMING", value_enum, default_value_t = SerialFraming::Raw)]
+    pub framing: SerialFraming,
+
+    /// Retry binding the TCP port this many times while the address is still in use
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub bind_retries: u32,
+
+    /// Set SO_REUSEADDR on the listening socket
+    #[arg(long)]
+    pub reuse_addr: bool,
+
+    /// Copy at most this many bytes of each read into the inspector (default: whole read)
+    #[arg(long, value_name = "BYTES")]
+    pub inspector_sample_max: Option<usize>,
+
+    /// Lay out the inspector's hex view in rows of this many bytes, padded so frames line up
+    #[arg(long, value_name = "BYTES", value_parser = clap::value_parser!(u16).range(1..))]
+    pub inspector_hex_columns: Option<u16>,
+
+    /// Samples queued between the I/O threads and the inspector; when full, new ones are dropped
+    #[arg(long, value_name = "SAMPLES", default_value_t = 1024, value_parser = clap::value_parser!(u32).range(1..))]
+    pub inspector_buffer: u32,
+
+    /// How queued client writes reach the device; `latest` discards stale frames when backed up
+    #[arg(long, value_enum, default_value_t = SerialWritePolicy::Fifo)]
+    pub serial_write_policy: SerialWritePolicy,
+
+    /// Flush the port after every write so each command goes out at once (lower throughput)
+    #[arg(long)]
+    pub flush_writes: bool,
+
+    /// Check the device exists and the address is bindable, print a summary and exit
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Headless: log a failing reconnect on the first attempt and then every N attempts (0: first only)
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub reconnect_log_every: u32,
+
+    /// Serve an HTTP health check on its own address (200 while the device is up, 503 otherwise)
+    #[arg(long, env = "SERGW_HEALTH_ADDR", value_name = "ADDR")]
+    pub health_addr: Option<SocketAddr>,
+
+    /// Stream the events log, one line per event, to every client of this address
+    #[arg(long, env = "SERGW_EVENT_ADDR", value_name = "ADDR")]
+    pub event_addr: Option<SocketAddr>,
+
+    /// Event lines for --event-addr and headless output; json prints them to stdout
+    #[arg(long, env = "SERGW_EVENT_FORMAT", value_enum, default_value_t = EventFormat::Text)]
+    pub event_format: EventFormat,
+
+    /// Poll the serial port with a 0 ms read timeout instead of blocking up to 200 ms
+    #[arg(long)]
+    pub nonblocking_serial: bool,
+
+    /// Don't warn when the device output looks like random noise (a sign of a wrong --baud)
+    #[arg(long)]
+    pub no_baud_check: bool,
+
+    /// Print what the serial port reports (settings, modem lines, probed baud rates) before starting
+    #[arg(long)]
+    pub show_caps: bool,
+
+    /// Drive DTR to this level whenever the port is opened
+    #[arg(long, value_enum, value_name = "LEVEL", conflicts_with = "upstream")]
+    pub dtr: Option<LineLevel>,
+
+    /// Drive RTS to this level whenever the port is opened
+    #[arg(long, value_enum, value_name = "LEVEL", conflicts_with = "upstream")]
+    pub rts: Option<LineLevel>,
+
+    /// TUI key bindings file (default: ~/.config/sergw/keys.conf if present)
+    #[arg(long, env = "SERGW_KEYMAP", value_name = "PATH")]
+    pub keymap: Option<PathBuf>,
+
+    /// Gather serial reads for up to N ms (or a full 4 KiB buffer) before broadcasting them as one frame
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub coalesce_ms: u64,
+
+    /// Close the device while no clients are connected and reopen it when one arrives
+    #[arg(long, conflicts_with = "poll_bytes")]
+    pub close_when_idle: bool,
+
+    /// Exit (code 6) the first time the device disconnects instead of reconnecting
+    #[arg(long, visible_alias = "no-reconnect")]
+    pub exit_on_disconnect: bool,
+
+    /// Drop all clients once the device has been gone this long (default: keep them while reconnecting)
+    #[arg(long, value_name = "SECS", conflicts_with = "exit_on_disconnect")]
+    pub disconnect_clients_after: Option<u64>,
+
+    /// Send these bytes to the device every --poll-interval ms, e.g. "0x01 03 00 00"; replies are broadcast as usual
+    #[arg(long, value_name = "HEX", value_parser = parse_hex_bytes)]
+    pub poll_bytes: Option<Bytes>,
+
+    /// Milliseconds between --poll-bytes writes
+    #[arg(long, value_name = "MS", default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub poll_interval: u64,
+
+    /// Remove these leading bytes (hex, e.g. "02") from each device envelope before broadcasting it
+    #[arg(long, value_name = "HEX", value_parser = parse_hex_bytes)]
+    pub strip_prefix: Option<Bytes>,
+
+    /// Remove these trailing bytes (hex, e.g. "03") from each device envelope before broadcasting it; it also marks where an envelope ends
+    #[arg(long, value_name = "HEX", value_parser = parse_hex_bytes)]
+    pub strip_suffix: Option<Bytes>,
+
+    /// Put these bytes (hex) in front of every client write to the device
+    #[arg(long, value_name = "HEX", value_parser = parse_hex_bytes)]
+    pub add_prefix: Option<Bytes>,
+
+    /// Put these bytes (hex) after every client write to the device
+    #[arg(long, value_name = "HEX", value_parser = parse_hex_bytes)]
+    pub add_suffix: Option<Bytes>,
+
+    /// Write this file's bytes to the device when the first client connects
+    #[arg(long, value_name = "FILE")]
+    pub on_connect_script: Option<PathBuf>,
+
+    /// Write --on-connect-script for every client that connects, not just the first
+    #[arg(long, requires = "on_connect_script")]
+    pub on_connect_every: bool,
+
+    /// Append the device's output to <DIR>/<device>.log, opened on the first byte
+    #[arg(long, value_name = "DIR")]
+    pub split_logs: Option<PathBuf>,
+
+    /// With --split-logs, also append each client's writes to <DIR>/client-<ip>_<port>.log
+    #[arg(long, requires = "split_logs")]
+    pub split_logs_clients: bool,
+
+    /// Also copy the device's output to stdout as it is read (headless only)
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub tee: Option<TeeFormat>,
+
+    /// Dial HOST:PORT and send it a read-only copy of everything broadcast to clients, redialling when it drops
+    #[arg(long, value_name = "HOST:PORT")]
+    pub tap: Option<String>,
+
+    /// Only the first client's writes reach the device; the rest observe until it leaves and the next is promoted
+    #[arg(long)]
+    pub single_controller: bool,
+
+    /// Refuse a new client when its IP address already has this many connections
+    #[arg(long, value_name = "N")]
+    pub max_per_ip: Option<usize>,
+
+    /// Accept at most this many new connections per second (bursts up to the same number); the excess is closed
+    #[arg(long, value_name = "N_PER_SEC", value_parser = clap::value_parser!(u64).range(1..))]
+    pub accept_rate: Option<u64>,
+
+    /// Look up each client's host name (reverse DNS) after accepting it and report it as an event
+    #[arg(long)]
+    pub reverse_dns: bool,
+
+    /// Close a client whose socket accepts no data for this long (0: wait forever)
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    pub tcp_write_timeout: u64,
+
+    /// Read commands (send <HEX>, pause, resume, baud <RATE>) from stdin, one per line; runs headless
+    #[arg(long)]
+    pub stdin_commands: bool,
+
+    /// Feed generated samples into the inspector (for working on the TUI without hardware)
+    #[arg(long, hide = true)]
+    pub inspector_demo: bool,
+
+    /// Show the device and client count in the terminal title while the TUI runs
+    #[arg(long)]
+    pub terminal_title: bool,
+
+    /// Which side "inbound" is relative to in the TUI throughput panel
+    #[arg(long, value_enum, default_value_t = ThroughputPerspective::Tcp)]
+    pub throughput_perspective: ThroughputPerspective,
+}
+
+impl Listen {
+    /// The rate the device is opened at: the first of --baud, or the one
+    /// startup settled on
+    pub fn baud(&self) -> u32 {
+        self.baud_rates.first().copied().unwrap_or(115_200)
+    }
+
+    pub fn set_baud(&mut self, baud: u32) {
+        self.baud_rates = vec![baud];
+    }
+
+    pub fn wants_tui(&self) -> bool {
+        // stdin belongs to the command reader, so the TUI could not read keys
+        !self.no_tui && !self.stdin_commands && std::io::stdout().is_terminal()
+    }
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct Decode {
+    /// Capture to read: raw bytes, or a pcap saved from the inspector ("-" for stdin)
+    pub file: PathBuf,
+
+    /// How bytes are shown
+    #[arg(long, value_enum, default_value_t = DecodeFormat::HexAscii)]
+    pub format: DecodeFormat,
+
+    /// End a frame after each occurrence of these bytes (hex, e.g. "0d 0a"); raw input is otherwise shown 16 bytes per row
+    #[arg(long, value_name = "HEX", value_parser = parse_hex_bytes)]
+    pub frame_delimiter: Option<Bytes>,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct Ping {
     /// Serial port to open (auto-select if exactly one is found and this is omitted)
     #[arg(long)]
     pub serial: Option<String>,
 
+    /// Also consider built-in (non-USB) ports when auto-selecting
+    #[arg(long)]
+    pub include_builtin: bool,
+
     /// Baud rate
     #[arg(long, default_value_t = 115_200)]
     pub baud: u32,
 
-    /// TCP listen address
-    #[arg(long, default_value = "127.0.0.1:5656")]
-    pub host: SocketAddr,
+    #[command(flatten)]
+    pub line: LineSettings,
+
+    /// Number of round trips to measure
+    #[arg(long, short = 'n', default_value_t = 10)]
+    pub count: u32,
+
+    /// Time to wait for each echo before counting it as lost (milliseconds)
+    #[arg(long, default_value_t = 1000)]
+    pub timeout_ms: u64,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct Bench {
+    /// Serial port to open (auto-select if exactly one is found and this is omitted)
+    #[arg(long)]
+    pub serial: Option<String>,
+
+    /// Also consider built-in (non-USB) ports when auto-selecting
+    #[arg(long)]
+    pub include_builtin: bool,
+
+    /// Baud rate
+    #[arg(long, default_value_t = 115_200)]
+    pub baud: u32,
+
+    #[command(flatten)]
+    pub line: LineSettings,
+
+    /// How long to read for (seconds)
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    pub duration: u64,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+    pub format: ReportFormat,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct Selftest {
+    /// Serial port to open (auto-select if exactly one is found and this is omitted)
+    #[arg(long)]
+    pub serial: Option<String>,
+
+    /// Also consider built-in (non-USB) ports when auto-selecting
+    #[arg(long)]
+    pub include_builtin: bool,
+
+    /// Baud rate
+    #[arg(long, default_value_t = 115_200)]
+    pub baud: u32,
+
+    #[command(flatten)]
+    pub line: LineSettings,
 
+    /// Time to wait for the full pattern to come back (milliseconds)
+    #[arg(long, default_value_t = 1000)]
+    pub timeout_ms: u64,
+}
+
+/// Serial line framing shared by every command that opens a port
+#[derive(Args, Clone, Debug)]
+pub struct LineSettings {
     /// Data bits
-    #[arg(long, value_enum, default_value_t = DataBitsOpt::Eight)]
+    #[arg(long, env = "SERGW_DATA_BITS", value_enum, default_value_t = DataBitsOpt::Eight)]
     pub data_bits: DataBitsOpt,
 
     /// Parity
-    #[arg(long, value_enum, default_value_t = ParityOpt::None)]
+    #[arg(long, env = "SERGW_PARITY", value_enum, default_value_t = ParityOpt::None)]
     pub parity: ParityOpt,
 
     /// Stop bits
-    #[arg(long, value_enum, default_value_t = StopBitsOpt::One)]
+    #[arg(long, env = "SERGW_STOP_BITS", value_enum, default_value_t = StopBitsOpt::One)]
     pub stop_bits: StopBitsOpt,
-
-    /// Buffer capacity (messages) for internal channels
-    #[arg(long, default_value_t = 4096)]
-    pub buffer: usize,
 }
 
 #[cfg(target_os = "linux")]
@@ -75,6 +495,11 @@ pub enum MockCmd {
         /// Optionally create a symlink to the slave PTY at this path (cannot force /dev/pts/N)
         #[arg(long)]
         alias: Option<String>,
+        /// Line ending appended when pressing Enter, to emulate the device
+        #[arg(long, value_enum, default_value_t = EolOpt::Lf)]
+        eol: EolOpt,
+        #[command(flatten)]
+        replay: ReplayArgs,
     },
     /// Open a chat UI connected to a TCP server (replaces `socat - TCP:host:port`)
     Listener {
@@ -83,11 +508,50 @@ pub enum MockCmd {
     },
 }
 
+#[cfg(target_os = "linux")]
+#[derive(Args, Clone, Debug)]
+pub struct ReplayArgs {
+    /// Write this file's contents to the device, as if the device sent it (`-` reads stdin)
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<String>,
+
+    /// Pause between script lines; 0 writes the whole script at once
+    #[arg(long, value_name = "MS", default_value_t = 0, requires = "replay")]
+    pub replay_interval_ms: u64,
+
+    /// Start the script over when it ends
+    #[arg(long = "loop", requires = "replay")]
+    pub loop_replay: bool,
+}
+
 #[derive(Parser, Clone, Debug)]
 pub struct Chat {
-    /// TCP server to connect to (e.g. 127.0.0.1:5656)
+    /// TCP server to connect to (e.g. 127.0.0.1:5656, localhost:5656, [::1]:5656)
     #[arg(long, default_value = "127.0.0.1:5656")]
-    pub host: std::net::SocketAddr,
+    pub host: String,
+
+    /// Address family to prefer when --host resolves to several addresses
+    #[arg(long, value_enum, default_value_t = IpFamily::V4)]
+    pub prefer_ip: IpFamily,
+
+    /// Pace `/send <path>` uploads to this many bytes per second (default: as fast as the socket allows)
+    #[arg(long, value_name = "BYTES_PER_SEC", value_parser = clap::value_parser!(u64).range(1..))]
+    pub send_rate: Option<u64>,
+
+    /// Key bindings file (default: ~/.config/sergw/keys.conf if present)
+    #[arg(long, value_name = "PATH")]
+    pub keymap: Option<PathBuf>,
+
+    /// Talk to this serial port directly instead of a TCP server
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["host", "prefer_ip"])]
+    pub serial: Option<String>,
+
+    /// Baud rate (with --serial)
+    #[arg(long, default_value_t = 115_200, requires = "serial")]
+    pub baud: u32,
+
+    #[command(flatten)]
+    pub line: LineSettings,
 }
 
 #[derive(ValueEnum, Clone, Debug)]
@@ -109,11 +573,41 @@ impl From<DataBitsOpt> for DataBits {
     }
 }
 
+/// Level for an output handshake line (`--dtr`, `--rts`)
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineLevel {
+    On,
+    Off,
+}
+
+impl LineLevel {
+    pub fn is_on(self) -> bool {
+        self == LineLevel::On
+    }
+}
+
+impl std::fmt::Display for LineLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(if self.is_on() { "on" } else { "off" })
+    }
+}
+
 #[derive(ValueEnum, Clone, Debug)]
 pub enum ParityOpt {
     None,
     Odd,
     Even,
+    /// Parity bit always 1 (Linux only)
+    Mark,
+    /// Parity bit always 0 (Linux only)
+    Space,
+}
+
+impl ParityOpt {
+    /// Mark/space ("stick") parity needs CMSPAR on top of the odd/even setting
+    pub fn is_stick(&self) -> bool {
+        matches!(self, ParityOpt::Mark | ParityOpt::Space)
+    }
 }
 
 impl From<ParityOpt> for Parity {
@@ -122,6 +616,10 @@ impl From<ParityOpt> for Parity {
             ParityOpt::None => Parity::None,
             ParityOpt::Odd => Parity::Odd,
             ParityOpt::Even => Parity::Even,
+            // termios: PARENB|PARODD|CMSPAR is mark, PARENB|CMSPAR is space;
+            // configure_serial adds CMSPAR after opening
+            ParityOpt::Mark => Parity::Odd,
+            ParityOpt::Space => Parity::Even,
         }
     }
 }
@@ -141,28 +639,193 @@ impl From<StopBitsOpt> for StopBits {
     }
 }
 
+#[cfg(target_os = "linux")]
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EolOpt {
+    Lf,
+    Cr,
+    Crlf,
+}
+
+#[cfg(target_os = "linux")]
+impl EolOpt {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EolOpt::Lf => "\n",
+            EolOpt::Cr => "\r",
+            EolOpt::Crlf => "\r\n",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IpFamily {
+    V4,
+    V6,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameMode {
+    /// Plain byte stream (no boundaries)
+    Raw,
+    /// u32 little-endian length before every message, both directions
+    LengthPrefix,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerialFraming {
+    /// Bytes pass through as they are
+    Raw,
+    /// Consistent Overhead Byte Stuffing, each packet ended by 0x00
+    Cobs,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerialWritePolicy {
+    /// Write every frame in arrival order
+    Fifo,
+    /// Write only the newest pending frame, dropping older queued ones
+    Latest,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventFormat {
+    /// The events pane's plain lines
+    Text,
+    /// One JSON object per line: event, addr (client events), ts, message
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TeeFormat {
+    /// The bytes as read
+    Raw,
+    /// Hex byte pairs, one line per read
+    Hex,
+}
+
+/// Which side of the bridge the TUI throughput panel calls "inbound"
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThroughputPerspective {
+    /// Inbound is data arriving at the serial device (tcp→serial)
+    Serial,
+    /// Inbound is data arriving at TCP clients (serial→tcp)
+    Tcp,
+}
+
 #[derive(ValueEnum, Clone, Debug)]
 pub enum PortsFormat {
     Text,
     Json,
+    /// One device path per line and nothing else; empty output (exit 0) when none
+    Plain,
+}
+
+/// Inspector byte formats, for `decode`
+#[derive(ValueEnum, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DecodeFormat {
+    /// hexdump rows: hex bytes followed by their ASCII
+    HexAscii,
+    Hex,
+    /// printable ASCII, other bytes as '.'
+    Ascii,
+    /// ASCII with control bytes as C-style escapes
+    AsciiEscaped,
+    Dec,
+}
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum ReportFormat {
+    Text,
+    Json,
+}
+
+/// `--poll-bytes`: hex byte pairs, written like the overview injection palette's hex mode
+fn parse_hex_bytes(s: &str) -> Result<Bytes, String> {
+    match crate::ui::inject::parse_hex(s) {
+        Ok(bytes) if bytes.is_empty() => Err("no bytes given".to_string()),
+        Ok(bytes) => Ok(Bytes::from(bytes)),
+        Err(e) => Err(e.to_string()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
+
+    // Tests that set SERGW_* variables or rely on them being unset
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
 
     #[test]
     fn parse_listen_defaults() {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
         let cli = Cli::parse_from(["sergw", "listen"]);
         match cli.command.unwrap() {
             Commands::Listen(l) => {
                 assert_eq!(l.serial, None);
-                assert_eq!(l.baud, 115_200);
-                assert_eq!(l.host, "127.0.0.1:5656".parse().unwrap());
-                assert!(matches!(l.data_bits, DataBitsOpt::Eight));
-                assert!(matches!(l.parity, ParityOpt::None));
-                assert!(matches!(l.stop_bits, StopBitsOpt::One));
+                assert!(!l.include_builtin);
+                assert_eq!(l.port_match, None);
+                assert_eq!(l.upstream, None);
+                assert_eq!(l.baud_rates, [115_200]);
+                assert_eq!(l.host, ["127.0.0.1:5656"]);
+                assert_eq!(l.prefer_ip, IpFamily::V4);
+                assert!(matches!(l.line.data_bits, DataBitsOpt::Eight));
+                assert!(matches!(l.line.parity, ParityOpt::None));
+                assert!(matches!(l.line.stop_bits, StopBitsOpt::One));
                 assert_eq!(l.buffer, 4096);
+                assert_eq!(l.scrollback, 0);
+                assert_eq!(l.wait_for_device, 0);
+                assert!(!l.no_tui);
+                assert_eq!(l.tcp_rate, None);
+                assert_eq!(l.client_ingress_rate, None);
+                assert_eq!(l.metrics_interval, None);
+                assert_eq!(l.duration, None);
+                assert_eq!(l.stop_after_bytes, None);
+                assert_eq!(l.frame, FrameMode::Raw);
+                assert_eq!(l.framing, SerialFraming::Raw);
+                assert_eq!(l.bind_retries, 0);
+                assert!(!l.reuse_addr);
+                assert_eq!(l.inspector_sample_max, None);
+                assert_eq!(l.inspector_hex_columns, None);
+                assert_eq!(l.inspector_buffer, 1024);
+                assert_eq!(l.serial_write_policy, SerialWritePolicy::Fifo);
+                assert!(!l.flush_writes);
+                assert!(!l.dry_run);
+                assert_eq!(l.reconnect_log_every, 10);
+                assert_eq!(l.health_addr, None);
+                assert_eq!(l.event_addr, None);
+                assert!(!l.nonblocking_serial);
+                assert!(!l.show_caps);
+                assert_eq!((l.dtr, l.rts), (None, None));
+                assert!(!l.no_baud_check);
+                assert_eq!(l.keymap, None);
+                assert_eq!(l.throughput_perspective, ThroughputPerspective::Tcp);
+                assert_eq!(l.coalesce_ms, 0);
+                assert_eq!(l.tcp_write_timeout, 10);
+                assert_eq!(l.max_per_ip, None);
+                assert_eq!(l.accept_rate, None);
+                assert!(!l.single_controller);
+                assert_eq!(l.on_connect_script, None);
+                assert!(!l.on_connect_every);
+                assert_eq!(l.split_logs, None);
+                assert!(!l.split_logs_clients);
+                assert_eq!(l.tee, None);
+                assert_eq!(l.tap, None);
+                assert!(!l.reverse_dns);
+                assert!(!l.terminal_title);
+                assert!(!l.stdin_commands);
+                assert!(!l.inspector_demo);
+                assert!(!l.exit_on_disconnect);
+                assert!(!l.close_when_idle);
+                assert_eq!(l.disconnect_clients_after, None);
+                assert_eq!(l.poll_bytes, None);
+                assert_eq!(l.poll_interval, 1000);
+                assert_eq!(l.strip_prefix, None);
+                assert_eq!(l.strip_suffix, None);
+                assert_eq!(l.add_prefix, None);
+                assert_eq!(l.add_suffix, None);
+                assert_eq!(l.event_format, EventFormat::Text);
             }
             _ => panic!("expected listen"),
         }
@@ -187,23 +850,376 @@ mod tests {
             "two",
             "--buffer",
             "123",
+            "--frame",
+            "length-prefix",
+            "--bind-retries",
+            "3",
+            "--reuse-addr",
+            "--serial-write-policy",
+            "latest",
+            "--health-addr",
+            "127.0.0.1:8080",
+            "--event-addr",
+            "[::1]:8081",
+            "--throughput-perspective",
+            "serial",
+            "--coalesce-ms",
+            "4",
+            "--max-per-ip",
+            "2",
+            "--event-format",
+            "json",
+            "--poll-bytes",
+            "0x01,0x03 0d0a",
+            "--poll-interval",
+            "250",
         ]);
         match cli.command.unwrap() {
             Commands::Listen(l) => {
                 assert_eq!(l.serial.as_deref(), Some("/dev/ttyUSB9"));
-                assert_eq!(l.baud, 57_600);
-                assert_eq!(l.host, "0.0.0.0:9000".parse().unwrap());
-                assert!(matches!(l.data_bits, DataBitsOpt::Seven));
-                assert!(matches!(l.parity, ParityOpt::Even));
-                assert!(matches!(l.stop_bits, StopBitsOpt::Two));
+                assert_eq!(l.baud_rates, [57_600]);
+                assert_eq!(l.host, ["0.0.0.0:9000"]);
+                assert!(matches!(l.line.data_bits, DataBitsOpt::Seven));
+                assert!(matches!(l.line.parity, ParityOpt::Even));
+                assert!(matches!(l.line.stop_bits, StopBitsOpt::Two));
                 assert_eq!(l.buffer, 123);
+                assert_eq!(l.frame, FrameMode::LengthPrefix);
+                assert_eq!(l.bind_retries, 3);
+                assert!(l.reuse_addr);
+                assert_eq!(l.serial_write_policy, SerialWritePolicy::Latest);
+                assert_eq!(l.health_addr, Some("127.0.0.1:8080".parse().unwrap()));
+                assert_eq!(l.event_addr, Some("[::1]:8081".parse().unwrap()));
+                assert_eq!(l.throughput_perspective, ThroughputPerspective::Serial);
+                assert_eq!(l.coalesce_ms, 4);
+                assert_eq!(l.max_per_ip, Some(2));
+                assert_eq!(l.event_format, EventFormat::Json);
+                assert_eq!(l.poll_bytes.as_deref(), Some(&[1, 3, 0x0d, 0x0a][..]));
+                assert_eq!(l.poll_interval, 250);
+            }
+            _ => panic!("expected listen"),
+        }
+    }
+
+    #[test]
+    fn baud_takes_a_list_of_candidates() {
+        let cli = Cli::try_parse_from(["sergw", "listen", "--baud", "9600,115200"]).unwrap();
+        let Some(Commands::Listen(mut l)) = cli.command else {
+            panic!("expected listen");
+        };
+        assert_eq!(l.baud_rates, [9600, 115_200]);
+        assert_eq!(l.baud(), 9600);
+        l.set_baud(115_200);
+        assert_eq!((l.baud(), l.baud_rates.len()), (115_200, 1));
+    }
+
+    #[test]
+    fn listen_flags_fall_back_to_env() {
+        let _env = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let vars = [
+            ("SERGW_BAUD", "9600"),
+            ("SERGW_HOST", "0.0.0.0:7000"),
+            ("SERGW_NO_TUI", "true"),
+            ("SERGW_FRAME", "length-prefix"),
+        ];
+        for (k, v) in vars {
+            std::env::set_var(k, v);
+        }
+        let from_env = Cli::try_parse_from(["sergw", "listen"]);
+        let flag_wins = Cli::try_parse_from(["sergw", "listen", "--baud", "57600"]);
+        for (k, _) in vars {
+            std::env::remove_var(k);
+        }
+
+        let Some(Commands::Listen(l)) = from_env.unwrap().command else {
+            panic!("expected listen");
+        };
+        assert_eq!(l.baud_rates, [9600]);
+        assert_eq!(l.host, ["0.0.0.0:7000"]);
+        assert!(l.no_tui);
+        assert_eq!(l.frame, FrameMode::LengthPrefix);
+        let Some(Commands::Listen(l)) = flag_wins.unwrap().command else {
+            panic!("expected listen");
+        };
+        assert_eq!(l.baud_rates, [57_600]);
+        assert_eq!(l.host, ["0.0.0.0:7000"]);
+    }
+
+    #[cfg(feature = "rfc2217")]
+    #[test]
+    fn parse_listen_rfc2217() {
+        let cli = Cli::parse_from(["sergw", "listen", "--upstream-rfc2217", "10.0.0.5:4001"]);
+        match cli.command.unwrap() {
+            Commands::Listen(l) => assert_eq!(l.upstream_rfc2217.as_deref(), Some("10.0.0.5:4001")),
+            _ => panic!("expected listen"),
+        }
+        assert!(Cli::try_parse_from([
+            "sergw",
+            "listen",
+            "--upstream-rfc2217",
+            "a:1",
+            "--serial",
+            "/dev/ttyUSB0"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn parse_listen_include_builtin() {
+        let cli = Cli::parse_from(["sergw", "listen", "--include-builtin"]);
+        match cli.command.unwrap() {
+            Commands::Listen(l) => assert!(l.include_builtin),
+            _ => panic!("expected listen"),
+        }
+    }
+
+    #[test]
+    fn parse_ping_values() {
+        let cli = Cli::parse_from([
+            "sergw",
+            "ping",
+            "--serial",
+            "/dev/ttyUSB0",
+            "-n",
+            "3",
+            "--timeout-ms",
+            "250",
+            "--parity",
+            "odd",
+        ]);
+        match cli.command.unwrap() {
+            Commands::Ping(p) => {
+                assert_eq!(p.serial.as_deref(), Some("/dev/ttyUSB0"));
+                assert_eq!(p.count, 3);
+                assert_eq!(p.timeout_ms, 250);
+                assert!(matches!(p.line.parity, ParityOpt::Odd));
+            }
+            _ => panic!("expected ping"),
+        }
+    }
+
+    #[test]
+    fn parse_bench_json() {
+        let cli = Cli::parse_from(["sergw", "bench", "--duration", "3", "--format", "json"]);
+        match cli.command.unwrap() {
+            Commands::Bench(b) => {
+                assert_eq!(b.duration, 3);
+                assert!(matches!(b.format, ReportFormat::Json));
+            }
+            _ => panic!("expected bench"),
+        }
+    }
+
+    #[test]
+    fn parse_listen_upstream() {
+        let cli = Cli::parse_from(["sergw", "listen", "--upstream", "gw.local:5656"]);
+        match cli.command.unwrap() {
+            Commands::Listen(l) => assert_eq!(l.upstream.as_deref(), Some("gw.local:5656")),
+            _ => panic!("expected listen"),
+        }
+        assert!(Cli::try_parse_from([
+            "sergw",
+            "listen",
+            "--upstream",
+            "gw.local:5656",
+            "--serial",
+            "/dev/ttyUSB0"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn parse_selftest() {
+        let cli = Cli::parse_from(["sergw", "selftest", "--baud", "9600"]);
+        match cli.command.unwrap() {
+            Commands::Selftest(t) => {
+                assert_eq!(t.baud, 9600);
+                assert_eq!(t.timeout_ms, 1000);
+            }
+            _ => panic!("expected selftest"),
+        }
+    }
+
+    #[test]
+    fn parse_listen_hostname_and_family() {
+        let cli = Cli::parse_from([
+            "sergw",
+            "listen",
+            "--host",
+            "localhost:5656",
+            "--prefer-ip",
+            "v6",
+        ]);
+        match cli.command.unwrap() {
+            Commands::Listen(l) => {
+                assert_eq!(l.host, ["localhost:5656"]);
+                assert_eq!(l.prefer_ip, IpFamily::V6);
+            }
+            _ => panic!("expected listen"),
+        }
+    }
+
+    #[test]
+    fn parse_listen_several_hosts() {
+        let cli = Cli::parse_from([
+            "sergw",
+            "listen",
+            "--host",
+            "0.0.0.0:5656",
+            "--host",
+            "[::1]:7000,127.0.0.1:7001",
+        ]);
+        let Some(Commands::Listen(l)) = cli.command else {
+            panic!("expected listen");
+        };
+        assert_eq!(l.host, ["0.0.0.0:5656", "[::1]:7000", "127.0.0.1:7001"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_mock_serial_eol() {
+        let cli = Cli::parse_from(["sergw", "mock", "serial", "--eol", "crlf"]);
+        match cli.command.unwrap() {
+            Commands::Mock {
+                cmd: MockCmd::Serial { eol, .. },
+            } => assert_eq!(eol.as_str(), "\r\n"),
+            _ => panic!("expected mock serial"),
+        }
+        let cli = Cli::parse_from(["sergw", "mock", "serial"]);
+        match cli.command.unwrap() {
+            Commands::Mock {
+                cmd: MockCmd::Serial { eol, .. },
+            } => assert_eq!(eol, EolOpt::Lf),
+            _ => panic!("expected mock serial"),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_mock_serial_replay() {
+        let cli = Cli::parse_from([
+            "sergw",
+            "mock",
+            "serial",
+            "--replay",
+            "-",
+            "--replay-interval-ms",
+            "250",
+            "--loop",
+        ]);
+        match cli.command.unwrap() {
+            Commands::Mock {
+                cmd: MockCmd::Serial { replay, .. },
+            } => {
+                assert_eq!(replay.replay.as_deref(), Some("-"));
+                assert_eq!(replay.replay_interval_ms, 250);
+                assert!(replay.loop_replay);
             }
+            _ => panic!("expected mock serial"),
+        }
+        // --loop without a script is rejected
+        assert!(Cli::try_parse_from(["sergw", "mock", "serial", "--loop"]).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_mock_listener_serial() {
+        let cli = Cli::parse_from([
+            "sergw",
+            "mock",
+            "listener",
+            "--serial",
+            "/dev/ttyUSB0",
+            "--baud",
+            "9600",
+            "--parity",
+            "even",
+        ]);
+        match cli.command.unwrap() {
+            Commands::Mock {
+                cmd: MockCmd::Listener { chat },
+            } => {
+                assert_eq!(chat.serial.as_deref(), Some("/dev/ttyUSB0"));
+                assert_eq!(chat.baud, 9600);
+                assert!(matches!(chat.line.parity, ParityOpt::Even));
+            }
+            _ => panic!("expected mock listener"),
+        }
+        let both = [
+            "sergw",
+            "mock",
+            "listener",
+            "--serial",
+            "/dev/ttyUSB0",
+            "--host",
+            "x:1",
+        ];
+        assert!(Cli::try_parse_from(both).is_err());
+        assert!(Cli::try_parse_from(["sergw", "mock", "listener", "--baud", "9600"]).is_err());
+        assert!(Cli::try_parse_from(["sergw", "mock", "listener", "--send-rate", "0"]).is_err());
+    }
+
+    #[test]
+    fn parse_completions_shell() {
+        let cli = Cli::parse_from(["sergw", "completions", "zsh"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Completions {
+                shell: clap_complete::Shell::Zsh
+            })
+        ));
+        assert!(Cli::try_parse_from(["sergw", "completions", "tcsh"]).is_err());
+    }
+
+    #[test]
+    fn no_reconnect_is_exit_on_disconnect() {
+        let cli = Cli::parse_from(["sergw", "listen", "--no-reconnect"]);
+        match cli.command.unwrap() {
+            Commands::Listen(l) => assert!(l.exit_on_disconnect),
             _ => panic!("expected listen"),
         }
+        assert!(Cli::try_parse_from([
+            "sergw",
+            "listen",
+            "--no-reconnect",
+            "--disconnect-clients-after",
+            "5"
+        ])
+        .is_err());
     }
 
     #[test]
-    fn parse_ports_json() {
+    fn parse_decode_options() {
+        let cli = Cli::parse_from([
+            "sergw",
+            "decode",
+            "cap.bin",
+            "--format",
+            "ascii-escaped",
+            "--frame-delimiter",
+            "0d0a",
+        ]);
+        match cli.command.unwrap() {
+            Commands::Decode(d) => {
+                assert_eq!(d.file, PathBuf::from("cap.bin"));
+                assert_eq!(d.format, DecodeFormat::AsciiEscaped);
+                assert_eq!(d.frame_delimiter.as_deref(), Some(&b"\r\n"[..]));
+            }
+            _ => panic!("expected decode"),
+        }
+    }
+
+    #[test]
+    fn tcp_rate_must_let_something_through() {
+        assert!(Cli::try_parse_from(["sergw", "listen", "--tcp-rate", "0"]).is_err());
+        let cli = Cli::try_parse_from(["sergw", "listen", "--tcp-rate", "1"]).unwrap();
+        let Some(Commands::Listen(l)) = cli.command else {
+            panic!("expected listen");
+        };
+        assert_eq!(l.tcp_rate, Some(1));
+    }
+
+    #[test]
+    fn parse_ports_formats() {
         let cli = Cli::parse_from(["sergw", "ports", "--format", "json"]);
         match cli.command.unwrap() {
             Commands::Ports { format, .. } => {
@@ -211,5 +1227,27 @@ mod tests {
             }
             _ => panic!("expected ports"),
         }
+        let cli = Cli::parse_from(["sergw", "ports", "--format", "plain", "--all"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Ports {
+                format: PortsFormat::Plain,
+                all: true,
+                ..
+            })
+        ));
+        let cli = Cli::parse_from(["sergw", "ports", "--match", "/dev/ttyACM*"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Ports { port_match: Some(ref p), .. }) if p == "/dev/ttyACM*"
+        ));
+        let cli = Cli::parse_from(["sergw", "listen", "--match", "/dev/ttyUSB[01]"]);
+        match cli.command.unwrap() {
+            Commands::Listen(l) => assert_eq!(l.port_match.as_deref(), Some("/dev/ttyUSB[01]")),
+            _ => panic!("expected listen"),
+        }
+        assert!(
+            Cli::try_parse_from(["sergw", "listen", "--match", "*", "--serial", "/dev/x"]).is_err()
+        );
     }
 }
@@ -0,0 +1,216 @@
+// `--config <FILE>`: TOML defaults for the `listen` and `ports` subcommands.
+//
+//   [listen]
+//   serial = "/dev/ttyUSB0"
+//   baud = 921600
+//   host = ["0.0.0.0:5656", "127.0.0.1:7000"]
+//   no-tui = true
+//   [ports]
+//   all = true
+//
+// Keys are the long flag names (dashes or underscores). A key only applies
+// when neither the command line nor a SERGW_* variable set that option or one
+// it conflicts with (a file `serial` gives way to `--upstream`), so the order
+// is flag > environment > file > built-in default. The file is
+// turned into extra flags and parsed by clap, so values are validated exactly
+// like their command-line form.
+
+use std::ffi::OsString;
+
+use clap::parser::ValueSource;
+use clap::{Arg, ArgMatches, Command};
+use thiserror::Error;
+
+// Subcommands a config file may configure
+const SECTIONS: [&str; 2] = ["listen", "ports"];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("{0}")]
+    Syntax(String),
+    #[error("unknown section [{0}] (expected listen or ports)")]
+    UnknownSection(String),
+    #[error("top-level key '{0}' must be inside a [listen] or [ports] section")]
+    StrayKey(String),
+    #[error("unknown key '{key}' in [{section}]")]
+    UnknownKey { section: String, key: String },
+    #[error("'{key}' in [{section}] must be a string, number, boolean or list of those")]
+    BadValue { section: String, key: String },
+}
+
+/// Flags for the options of the invoked subcommand that `matches` left unset.
+/// Every section is checked, even ones for another subcommand.
+pub fn config_args(
+    text: &str,
+    cmd: &Command,
+    matches: &ArgMatches,
+) -> Result<Vec<OsString>, ConfigError> {
+    let doc: toml::Table = text
+        .parse()
+        .map_err(|e: toml::de::Error| ConfigError::Syntax(e.message().to_string()))?;
+    let mut args = Vec::new();
+    for (section, table) in &doc {
+        let toml::Value::Table(table) = table else {
+            return Err(ConfigError::StrayKey(section.clone()));
+        };
+        if !SECTIONS.contains(&section.as_str()) {
+            return Err(ConfigError::UnknownSection(section.clone()));
+        }
+        let Some(sub) = cmd.find_subcommand(section) else {
+            continue;
+        };
+        let invoked = matches
+            .subcommand()
+            .filter(|(name, _)| name == section)
+            .map(|(_, m)| m);
+        for (key, value) in table {
+            let unknown = || ConfigError::UnknownKey {
+                section: section.clone(),
+                key: key.clone(),
+            };
+            let id = key.replace('-', "_");
+            let arg = sub
+                .get_arguments()
+                .find(|a| a.get_id() == id.as_str())
+                .filter(|a| !matches!(a.get_id().as_str(), "help" | "version" | "config"))
+                .ok_or_else(unknown)?;
+            let long = format!("--{}", arg.get_long().ok_or_else(unknown)?);
+            let Some(invoked) = invoked else {
+                continue;
+            };
+            if explicit(invoked, &id) || clashes_with_explicit(sub, invoked, arg) {
+                continue;
+            }
+            let bad = || ConfigError::BadValue {
+                section: section.clone(),
+                key: key.clone(),
+            };
+            let values = match value {
+                toml::Value::Array(items) => items.iter().collect(),
+                single => vec![single],
+            };
+            for value in values {
+                let text = match value {
+                    toml::Value::String(s) => s.clone(),
+                    toml::Value::Integer(n) => n.to_string(),
+                    toml::Value::Float(f) => f.to_string(),
+                    // Switches take no value: true passes the flag, false leaves it off
+                    toml::Value::Boolean(b) if !arg.get_action().takes_values() => {
+                        if *b {
+                            args.push(OsString::from(&long));
+                        }
+                        continue;
+                    }
+                    toml::Value::Boolean(b) => b.to_string(),
+                    _ => return Err(bad()),
+                };
+                args.push(OsString::from(&long));
+                args.push(OsString::from(text));
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// Set on the command line or from the environment, not by a default
+fn explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+/// Whether `arg` conflicts, either way round, with an explicitly set option
+fn clashes_with_explicit(sub: &Command, matches: &ArgMatches, arg: &Arg) -> bool {
+    sub.get_arguments()
+        .filter(|other| matches.ids().any(|id| id == other.get_id()))
+        .filter(|other| explicit(matches, other.get_id().as_str()))
+        .any(|other| {
+            let names = |a: &Arg, b: &Arg| {
+                sub.get_arg_conflicts_with(a)
+                    .iter()
+                    .any(|c| c.get_id() == b.get_id())
+            };
+            names(arg, other) || names(other, arg)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Cli;
+    use clap::CommandFactory;
+
+    fn args_for(text: &str, argv: &[&str]) -> Result<Vec<String>, ConfigError> {
+        let cmd = Cli::command();
+        let matches = cmd.clone().try_get_matches_from(argv).unwrap();
+        config_args(text, &cmd, &matches)
+            .map(|args| args.into_iter().map(|a| a.into_string().unwrap()).collect())
+    }
+
+    #[test]
+    fn file_fills_what_the_command_line_left_unset() {
+        let text = r#"
+            [listen]
+            baud = 9600
+            host = ["0.0.0.0:1", "0.0.0.0:2"]
+            no-tui = true
+            reuse_addr = false
+            [ports]
+            all = true
+        "#;
+        assert_eq!(
+            args_for(text, &["sergw", "listen", "--baud", "57600"]).unwrap(),
+            ["--host", "0.0.0.0:1", "--host", "0.0.0.0:2", "--no-tui"]
+        );
+        assert_eq!(args_for(text, &["sergw", "ports"]).unwrap(), ["--all"]);
+    }
+
+    #[test]
+    fn command_line_beats_conflicting_file_keys() {
+        let text = "[listen]\nserial = \"/dev/ttyUSB0\"\ninclude-builtin = true\nbaud = 9600\n";
+        assert_eq!(
+            args_for(text, &["sergw", "listen", "--upstream", "10.0.0.5:5656"]).unwrap(),
+            ["--baud", "9600"]
+        );
+        let mut args: Vec<OsString> = ["sergw", "listen", "--upstream", "10.0.0.5:5656"]
+            .map(OsString::from)
+            .to_vec();
+        let cmd = Cli::command();
+        let matches = cmd.clone().try_get_matches_from(&args).unwrap();
+        args.extend(config_args(text, &cmd, &matches).unwrap());
+        assert!(cmd.try_get_matches_from(&args).is_ok());
+    }
+
+    #[test]
+    fn mistakes_are_reported() {
+        assert_eq!(
+            args_for("[listen]\nbuad = 1\n", &["sergw", "listen"]),
+            Err(ConfigError::UnknownKey {
+                section: "listen".into(),
+                key: "buad".into()
+            })
+        );
+        // checked even when another subcommand runs
+        assert!(matches!(
+            args_for("[ports]\nfast = true\n", &["sergw", "listen"]),
+            Err(ConfigError::UnknownKey { .. })
+        ));
+        assert_eq!(
+            args_for("[mock]\n", &["sergw", "listen"]),
+            Err(ConfigError::UnknownSection("mock".into()))
+        );
+        assert_eq!(
+            args_for("baud = 1\n", &["sergw", "listen"]),
+            Err(ConfigError::StrayKey("baud".into()))
+        );
+        assert!(matches!(
+            args_for("[listen]\nbaud = {}\n", &["sergw", "listen"]),
+            Err(ConfigError::BadValue { .. })
+        ));
+        assert!(matches!(
+            args_for("[listen\n", &["sergw", "listen"]),
+            Err(ConfigError::Syntax(_))
+        ));
+    }
+}
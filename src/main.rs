@@ -1,22 +1,39 @@
 mod app;
 mod cli;
+mod config;
 mod metrics;
 mod net;
+mod rate;
 mod serial;
 mod state;
 mod ui;
 
+use std::io::IsTerminal;
+
 use anyhow::Result;
-use clap::{CommandFactory, Parser};
+use clap::CommandFactory;
 use tracing_subscriber::EnvFilter;
 
 use crate::app::listen::run_listen;
 use crate::cli::{Cli, Commands, PortsFormat};
-use crate::serial::list_available_ports;
+use crate::serial::{caps, glob_match, list_available_ports};
 use serialport::SerialPortType;
 
-fn print_ports(all: bool, verbose: bool, format: PortsFormat) {
-    let ports = list_available_ports(all);
+fn print_ports(
+    all: bool,
+    verbose: bool,
+    format: PortsFormat,
+    pattern: Option<&str>,
+    baud_list: bool,
+) {
+    let mut ports = list_available_ports(all);
+    if let Some(pattern) = pattern {
+        ports.retain(|p| glob_match(pattern, &p.port_name));
+    }
+    // --baud-list opens each port in turn; the plain contract stays paths only
+    let probe = |name: &str| {
+        (baud_list && !matches!(format, PortsFormat::Plain)).then(|| caps::port_bauds(name))
+    };
     match format {
         PortsFormat::Text => {
             if ports.is_empty() {
@@ -24,23 +41,32 @@ fn print_ports(all: bool, verbose: bool, format: PortsFormat) {
                 std::process::exit(2);
             }
             for p in ports {
+                let bauds = probe(&p.port_name)
+                    .map(|probed| format!("\tbaud: {}", caps::baud_summary(&probed)))
+                    .unwrap_or_default();
                 if verbose {
                     match p.port_type {
                         SerialPortType::UsbPort(info) => {
                             println!(
-                                "{}\tUSB vid:pid {:04x}:{:04x}\t{:?}\t{:?}",
+                                "{}\tUSB vid:pid {:04x}:{:04x}\t{:?}\t{:?}{bauds}",
                                 p.port_name, info.vid, info.pid, info.product, info.manufacturer,
                             );
                         }
                         other => {
-                            println!("{}\t{:?}", p.port_name, other);
+                            println!("{}\t{:?}{bauds}", p.port_name, other);
                         }
                     }
                 } else {
-                    println!("{}", p.port_name);
+                    println!("{}{bauds}", p.port_name);
                 }
             }
         }
+        // Script contract: one path per line, nothing else, exit 0 even when empty
+        PortsFormat::Plain => {
+            for p in ports {
+                println!("{}", p.port_name);
+            }
+        }
         PortsFormat::Json => {
             #[derive(serde::Serialize)]
             struct PortOut {
@@ -50,27 +76,45 @@ fn print_ports(all: bool, verbose: bool, format: PortsFormat) {
                 pid: Option<u16>,
                 product: Option<String>,
                 manufacturer: Option<String>,
+                // --baud-list only
+                #[serde(skip_serializing_if = "Option::is_none")]
+                bauds: Option<Vec<u32>>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                bauds_note: Option<String>,
             }
 
             let out: Vec<PortOut> = ports
                 .into_iter()
-                .map(|p| match p.port_type {
-                    SerialPortType::UsbPort(info) => PortOut {
-                        name: p.port_name,
-                        kind: "usb".into(),
-                        vid: Some(info.vid),
-                        pid: Some(info.pid),
-                        product: info.product,
-                        manufacturer: info.manufacturer,
-                    },
-                    other => PortOut {
-                        name: p.port_name,
-                        kind: format!("{other:?}"),
-                        vid: None,
-                        pid: None,
-                        product: None,
-                        manufacturer: None,
-                    },
+                .map(|p| {
+                    let (bauds, bauds_note) = match probe(&p.port_name) {
+                        Some(probed) => {
+                            let (rates, note) = caps::supported_bauds(&probed);
+                            (Some(rates), note)
+                        }
+                        None => (None, None),
+                    };
+                    match p.port_type {
+                        SerialPortType::UsbPort(info) => PortOut {
+                            name: p.port_name,
+                            kind: "usb".into(),
+                            vid: Some(info.vid),
+                            pid: Some(info.pid),
+                            product: info.product,
+                            manufacturer: info.manufacturer,
+                            bauds,
+                            bauds_note,
+                        },
+                        other => PortOut {
+                            name: p.port_name,
+                            kind: format!("{other:?}"),
+                            vid: None,
+                            pid: None,
+                            product: None,
+                            manufacturer: None,
+                            bauds,
+                            bauds_note,
+                        },
+                    }
                 })
                 .collect();
             println!("{}", serde_json::to_string_pretty(&out).unwrap());
@@ -78,31 +122,68 @@ fn print_ports(all: bool, verbose: bool, format: PortsFormat) {
     }
 }
 
+fn init_tracing(headless: bool) {
+    if headless {
+        // No TUI to show events, so log to stderr (info by default)
+        tracing_subscriber::fmt()
+            .with_env_filter(
+                EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+            )
+            .with_target(false)
+            .with_ansi(std::io::stderr().is_terminal())
+            .with_writer(std::io::stderr)
+            .try_init()
+            .ok();
+    } else {
+        // Silence external logging to keep TUI clean; route important status via the UI event log.
+        tracing_subscriber::fmt()
+            .with_env_filter(EnvFilter::from_default_env())
+            .with_target(false)
+            .with_writer(std::io::sink)
+            .try_init()
+            .ok();
+    }
+}
+
 fn main() {
-    // Silence external logging to keep TUI clean; route important status via the UI event log.
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .with_target(false)
-        .with_writer(std::io::sink)
-        .try_init()
-        .ok();
-
-    let cli = Cli::parse();
+    let cli = match Cli::parse_with_config(std::env::args_os().collect()) {
+        Ok(cli) => cli,
+        Err(err) => match err.downcast::<clap::Error>() {
+            // usage errors, --help and --version print and exit the usual clap way
+            Ok(usage) => usage.exit(),
+            Err(err) => {
+                eprintln!("error: {err:?}");
+                std::process::exit(1);
+            }
+        },
+    };
+    init_tracing(matches!(&cli.command, Some(Commands::Listen(l)) if !l.wants_tui()));
+
     let result: Result<()> = match cli.command {
+        Some(Commands::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Cli::command(), "sergw", &mut std::io::stdout());
+            Ok(())
+        }
         Some(Commands::Ports {
             all,
             verbose,
             format,
+            port_match,
+            baud_list,
         }) => {
-            print_ports(all, verbose, format);
+            print_ports(all, verbose, format, port_match.as_deref(), baud_list);
             Ok(())
         }
         Some(Commands::Listen(listen)) => run_listen(listen),
+        Some(Commands::Ping(ping)) => crate::app::ping::run_ping(ping),
+        Some(Commands::Bench(bench)) => crate::app::bench::run_bench(bench),
+        Some(Commands::Selftest(test)) => crate::app::selftest::run_selftest(test),
+        Some(Commands::Decode(decode)) => crate::app::decode::run_decode(decode),
         #[cfg(target_os = "linux")]
         Some(Commands::Mock { cmd: sub }) => match sub {
-            crate::cli::MockCmd::Serial { alias } => {
+            crate::cli::MockCmd::Serial { alias, eol, replay } => {
                 let _ = alias;
-                crate::app::mock::run_mock_serial()
+                crate::app::mock::run_mock_serial(eol, replay)
             }
             crate::cli::MockCmd::Listener { chat } => crate::app::listener::run_chat(chat),
         },
@@ -122,11 +203,16 @@ fn main() {
 }
 
 pub(crate) fn exit_code_for_error(err: &anyhow::Error) -> i32 {
-    // 2: no ports, 3: multiple ports, 4: bind failure, 5: serial open failure, 1: other
+    // 2: no ports, 3: multiple ports, 4: bind failure, 5: serial open failure,
+    // 6: device disconnected with --exit-on-disconnect, 1: other
     for cause in err.chain() {
+        if cause.is::<crate::net::server::DeviceDisconnected>() {
+            return 6;
+        }
         if let Some(sel) = cause.downcast_ref::<crate::serial::SerialSelectError>() {
             return match sel {
-                crate::serial::SerialSelectError::NoPorts => 2,
+                crate::serial::SerialSelectError::NoPorts
+                | crate::serial::SerialSelectError::NoMatch { .. } => 2,
                 crate::serial::SerialSelectError::MultiplePorts { .. } => 3,
             };
         }
@@ -1,3 +1,13 @@
+/// Smoothing windows (tau, in seconds) the TUIs cycle through
+pub const SMOOTHING_PRESETS: [f64; 3] = [1.0, 5.0, 15.0];
+pub const DEFAULT_SMOOTHING: f64 = 5.0;
+
+/// The preset after `tau`, wrapping around
+pub fn next_smoothing(tau_secs: f64) -> f64 {
+    let pos = SMOOTHING_PRESETS.iter().position(|&p| p == tau_secs);
+    SMOOTHING_PRESETS[pos.map_or(0, |i| (i + 1) % SMOOTHING_PRESETS.len())]
+}
+
 pub struct ThroughputAverager {
     tau_secs: f64,
     smoothed_bps: f64,
@@ -11,6 +21,10 @@ impl ThroughputAverager {
         }
     }
 
+    pub fn tau_secs(&self) -> f64 {
+        self.tau_secs
+    }
+
     pub fn update(&mut self, bytes_delta: u64, dt_secs: f64) -> f64 {
         let dt = dt_secs.max(1e-3);
         let alpha = 1.0 - (-dt / self.tau_secs).exp();
@@ -34,4 +48,13 @@ mod tests {
         assert!(r1 > r2);
         assert!(r2 > 0.0);
     }
+
+    #[test]
+    fn smoothing_presets_cycle() {
+        assert_eq!(next_smoothing(DEFAULT_SMOOTHING), 15.0);
+        assert_eq!(next_smoothing(15.0), 1.0);
+        assert_eq!(next_smoothing(1.0), 5.0);
+        // anything off the list starts over
+        assert_eq!(next_smoothing(2.5), 1.0);
+    }
 }
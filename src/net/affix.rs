@@ -0,0 +1,174 @@
+// Fixed byte wrappers around frames: `--strip-prefix/--strip-suffix` remove a
+// device's envelope (STX ... ETX) before its output is broadcast,
+// `--add-prefix/--add-suffix` put one around every client write. A serial read
+// can end anywhere, so the envelope itself is the framing: the stream is split
+// at each suffix (or, with only a prefix, at the next prefix) and a partial
+// envelope waits for the rest. Under `--framing cobs` each decoded packet is
+// one envelope. Adding is exact per message with `--frame length-prefix`.
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::net::frame::MAX_FRAME;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Affixes {
+    pub prefix: Option<Bytes>,
+    pub suffix: Option<Bytes>,
+}
+
+impl Affixes {
+    pub fn new(prefix: Option<Bytes>, suffix: Option<Bytes>) -> Self {
+        Self { prefix, suffix }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prefix.is_none() && self.suffix.is_none()
+    }
+
+    /// `frame` without the prefix and suffix; either is only removed when it matches
+    pub fn strip<'a>(&self, frame: &'a [u8]) -> &'a [u8] {
+        let mut rest = frame;
+        if let Some(prefix) = &self.prefix {
+            rest = rest.strip_prefix(prefix.as_ref()).unwrap_or(rest);
+        }
+        if let Some(suffix) = &self.suffix {
+            rest = rest.strip_suffix(suffix.as_ref()).unwrap_or(rest);
+        }
+        rest
+    }
+
+    /// `data` between the prefix and suffix
+    pub fn wrap(&self, data: Bytes) -> Bytes {
+        if self.is_empty() {
+            return data;
+        }
+        let prefix = self.prefix.as_deref().unwrap_or_default();
+        let suffix = self.suffix.as_deref().unwrap_or_default();
+        let mut out = BytesMut::with_capacity(prefix.len() + data.len() + suffix.len());
+        out.put_slice(prefix);
+        out.put_slice(&data);
+        out.put_slice(suffix);
+        out.freeze()
+    }
+}
+
+/// Cuts the device's byte stream into whole envelopes, ready for
+/// [`Affixes::strip`]; whatever follows the last complete one is kept for the
+/// next read
+#[derive(Debug, Default)]
+pub struct Envelopes {
+    affixes: Affixes,
+    pending: BytesMut,
+}
+
+impl Envelopes {
+    pub fn new(affixes: Affixes) -> Self {
+        Self {
+            affixes,
+            pending: BytesMut::new(),
+        }
+    }
+
+    pub fn feed(&mut self, data: &[u8]) -> Vec<Bytes> {
+        self.pending.extend_from_slice(data);
+        let mut whole = Vec::new();
+        while let Some(end) = self.next_end() {
+            whole.push(self.pending.split_to(end).freeze());
+        }
+        // an envelope that never closes is passed on rather than kept growing
+        if self.pending.len() > MAX_FRAME {
+            whole.push(self.pending.split().freeze());
+        }
+        whole
+    }
+
+    /// With only a prefix nothing marks the end of the last envelope, so the
+    /// device going quiet does
+    pub fn flush(&mut self) -> Option<Bytes> {
+        (self.affixes.suffix.is_none() && !self.pending.is_empty())
+            .then(|| self.pending.split().freeze())
+    }
+
+    /// Length of the first complete envelope in `pending`
+    fn next_end(&self) -> Option<usize> {
+        let buf = &self.pending[..];
+        if buf.is_empty() {
+            return None;
+        }
+        let prefix = self.affixes.prefix.as_deref().unwrap_or_default();
+        // the prefix is not searched for the suffix (both may be the same byte)
+        let body = if buf.starts_with(prefix) {
+            prefix.len()
+        } else {
+            0
+        };
+        match &self.affixes.suffix {
+            Some(suffix) => find(&buf[body..], suffix).map(|at| body + at + suffix.len()),
+            None => {
+                let from = body.max(1);
+                find(&buf[from..], prefix).map(|at| from + at)
+            }
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn affixes(prefix: &'static [u8], suffix: &'static [u8]) -> Affixes {
+        let some = |b: &'static [u8]| (!b.is_empty()).then(|| Bytes::from_static(b));
+        Affixes::new(some(prefix), some(suffix))
+    }
+
+    #[test]
+    fn strips_only_matching_ends() {
+        let stx_etx = affixes(b"\x02", b"\x03");
+        assert_eq!(stx_etx.strip(b"\x02OK\x03"), b"OK");
+        assert_eq!(stx_etx.strip(b"\x02OK"), b"OK");
+        assert_eq!(stx_etx.strip(b"OK\x03"), b"OK");
+        assert_eq!(stx_etx.strip(b"OK"), b"OK");
+        assert_eq!(stx_etx.strip(b"\x02\x03"), b"");
+        // a lone byte that is the prefix is not also taken as the suffix
+        assert_eq!(affixes(b"\x7e", b"\x7e").strip(b"\x7e"), b"");
+        assert_eq!(affixes(b"", b"\r\n").strip(b"line\r\n"), b"line");
+    }
+
+    #[test]
+    fn envelopes_are_carried_across_reads() {
+        let mut stx_etx = Envelopes::new(affixes(b"\x02", b"\x03"));
+        assert!(stx_etx.feed(b"\x02O").is_empty());
+        assert_eq!(stx_etx.feed(b"K\x03\x02ER"), [&b"\x02OK\x03"[..]]);
+        assert_eq!(stx_etx.feed(b"R\x03"), [&b"\x02ERR\x03"[..]]);
+        assert_eq!(stx_etx.flush(), None);
+
+        let mut hdlc = Envelopes::new(affixes(b"\x7e", b"\x7e"));
+        assert_eq!(hdlc.feed(b"\x7eA\x7e\x7eB"), [&b"\x7eA\x7e"[..]]);
+
+        let mut prefix_only = Envelopes::new(affixes(b"$", b""));
+        assert_eq!(
+            prefix_only.feed(b"noise$GPA,1$GPB"),
+            [&b"noise"[..], &b"$GPA,1"[..]]
+        );
+        assert_eq!(prefix_only.flush().unwrap(), &b"$GPB"[..]);
+        assert_eq!(prefix_only.flush(), None);
+    }
+
+    #[test]
+    fn wraps_client_data() {
+        let stx_etx = affixes(b"\x02", b"\x03");
+        assert_eq!(stx_etx.wrap(Bytes::from_static(b"GO")), &b"\x02GO\x03"[..]);
+        assert_eq!(
+            affixes(b"", b"\r").wrap(Bytes::from_static(b"AT")),
+            &b"AT\r"[..]
+        );
+        assert_eq!(Affixes::default().wrap(Bytes::from_static(b"x")), &b"x"[..]);
+    }
+}
@@ -0,0 +1,225 @@
+// Side listeners (health, event feed, ...) that sit next to the data port.
+// Each gets its own bind address so it can be firewalled separately, and all
+// of them stop with the main stop flag.
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use crossbeam_channel as channel;
+use tracing::{info, warn};
+
+/// Handles one accepted client; long-lived handlers should watch the stop flag
+pub type AuxHandler = dyn Fn(TcpStream, SocketAddr, &AtomicBool) + Send + Sync;
+
+pub struct AuxListeners {
+    stop: Arc<AtomicBool>,
+    handles: Vec<(&'static str, JoinHandle<()>)>,
+}
+
+impl AuxListeners {
+    pub fn new(stop: Arc<AtomicBool>) -> Self {
+        Self {
+            stop,
+            handles: Vec::new(),
+        }
+    }
+
+    /// Serve every client of a listener from [`bind`] on its own thread.
+    /// Returns the bound address (useful with port 0).
+    pub fn spawn(
+        &mut self,
+        name: &'static str,
+        listener: TcpListener,
+        handler: Arc<AuxHandler>,
+    ) -> Result<SocketAddr> {
+        let local = listener.local_addr()?;
+        info!(%local, "{name} listener ready");
+        let stop = Arc::clone(&self.stop);
+        let handle = thread::spawn(move || {
+            while !stop.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        if let Err(e) = stream.set_nonblocking(false) {
+                            warn!(?e, %peer, "{name}: failed to set blocking mode");
+                            continue;
+                        }
+                        let handler = Arc::clone(&handler);
+                        let stop = Arc::clone(&stop);
+                        thread::spawn(move || handler(stream, peer, &stop));
+                    }
+                    Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        thread::sleep(Duration::from_millis(50));
+                    }
+                    Err(e) => warn!(?e, "{name}: accept failed"),
+                }
+            }
+        });
+        self.handles.push((name, handle));
+        Ok(local)
+    }
+
+    /// Wait for every accept loop to notice the stop flag
+    pub fn join(self) {
+        for (name, handle) in self.handles {
+            if handle.join().is_err() {
+                warn!("{name} listener panicked");
+            }
+        }
+    }
+}
+
+/// Bind a side listener up front, so a taken address fails startup before the
+/// TUI owns the terminal
+pub fn bind(name: &str, addr: SocketAddr) -> Result<TcpListener> {
+    let listener =
+        TcpListener::bind(addr).with_context(|| format!("Binding {name} listener at {addr}"))?;
+    listener
+        .set_nonblocking(true)
+        .with_context(|| format!("Setting {name} listener non-blocking mode"))?;
+    Ok(listener)
+}
+
+/// Plain HTTP/1.0 so both load balancer probes and `nc` can read it
+pub fn health_response(serial_connected: bool, uptime: Duration) -> String {
+    let (status, body) = if serial_connected {
+        ("200 OK", "ok")
+    } else {
+        ("503 Service Unavailable", "serial disconnected")
+    };
+    let body = format!("{body} uptime={}s\n", uptime.as_secs());
+    format!(
+        "HTTP/1.0 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// Answer one probe: drain whatever request was sent, reply, close
+pub fn serve_health(mut stream: TcpStream, response: &str) {
+    let _ = stream.set_read_timeout(Some(Duration::from_millis(200)));
+    let mut scratch = [0u8; 1024];
+    let _ = stream.read(&mut scratch);
+    let _ = stream.write_all(response.as_bytes());
+}
+
+// Lines a subscriber may fall behind by before it is dropped
+const EVENT_BACKLOG: usize = 256;
+
+/// Fan-out of event strings to `--event-addr` subscribers; each gets its own copy
+#[derive(Default)]
+pub struct EventFeed {
+    subscribers: Mutex<Vec<channel::Sender<String>>>,
+}
+
+impl EventFeed {
+    pub fn subscribe(&self) -> channel::Receiver<String> {
+        let (tx, rx) = channel::bounded(EVENT_BACKLOG);
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(tx);
+        rx
+    }
+
+    /// Subscribers that went away or stopped reading are dropped here
+    pub fn publish(&self, event: &str) {
+        let line = event.replace(['\r', '\n'], " ");
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|tx| tx.try_send(line.clone()).is_ok());
+    }
+}
+
+/// Stream events to one subscriber, one per line, until it disconnects,
+/// falls too far behind, or the gateway stops
+pub fn serve_events(mut stream: TcpStream, feed: &EventFeed, stop: &AtomicBool) {
+    let rx = feed.subscribe();
+    while !stop.load(Ordering::Relaxed) {
+        match rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(event) => {
+                if stream.write_all(format!("{event}\n").as_bytes()).is_err() {
+                    break;
+                }
+            }
+            Err(channel::RecvTimeoutError::Timeout) => {}
+            Err(channel::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_reflects_serial_state() {
+        let up = health_response(true, Duration::from_secs(42));
+        assert!(up.starts_with("HTTP/1.0 200 OK\r\n"));
+        assert!(up.ends_with("\r\n\r\nok uptime=42s\n"));
+        assert!(up.contains("Content-Length: 14\r\n"));
+        let down = health_response(false, Duration::ZERO);
+        assert!(down.starts_with("HTTP/1.0 503 "));
+    }
+
+    #[test]
+    fn listeners_serve_and_stop_together() {
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut aux = AuxListeners::new(Arc::clone(&stop));
+        let handler: Arc<AuxHandler> =
+            Arc::new(|stream, _, _: &AtomicBool| serve_health(stream, "pong\n"));
+        let a = aux
+            .spawn(
+                "first",
+                bind("first", "127.0.0.1:0".parse().unwrap()).unwrap(),
+                Arc::clone(&handler),
+            )
+            .unwrap();
+        let b = aux
+            .spawn(
+                "second",
+                bind("second", "127.0.0.1:0".parse().unwrap()).unwrap(),
+                handler,
+            )
+            .unwrap();
+        assert_ne!(a.port(), b.port());
+        for addr in [a, b] {
+            let mut s = TcpStream::connect(addr).unwrap();
+            s.write_all(b"GET / HTTP/1.0\r\n\r\n").unwrap();
+            let mut out = String::new();
+            s.read_to_string(&mut out).unwrap();
+            assert_eq!(out, "pong\n");
+        }
+        stop.store(true, Ordering::Relaxed);
+        aux.join();
+    }
+
+    #[test]
+    fn event_feed_copies_to_every_subscriber() {
+        let feed = EventFeed::default();
+        let a = feed.subscribe();
+        let b = feed.subscribe();
+        feed.publish("Accepted: 127.0.0.1:4000\nx");
+        assert_eq!(a.try_recv().unwrap(), "Accepted: 127.0.0.1:4000 x");
+        assert_eq!(b.try_recv().unwrap(), "Accepted: 127.0.0.1:4000 x");
+        drop(a);
+        feed.publish("next");
+        assert_eq!(b.try_recv().unwrap(), "next");
+        assert_eq!(feed.subscribers.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn stalled_subscriber_is_dropped() {
+        let feed = EventFeed::default();
+        let rx = feed.subscribe();
+        for i in 0..=EVENT_BACKLOG {
+            feed.publish(&i.to_string());
+        }
+        assert!(feed.subscribers.lock().unwrap().is_empty());
+        assert_eq!(rx.iter().count(), EVENT_BACKLOG);
+    }
+}
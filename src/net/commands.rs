@@ -0,0 +1,156 @@
+// `--stdin-commands`: one command per line on stdin while running headless,
+// for scripting without a network control port.
+//
+//   send <HEX>    write bytes to the device, e.g. `send 68656c6c6f`
+//   pause         stop reading the device (as the TUI's `r` key)
+//   resume        read it again
+//   baud <RATE>   change the serial line rate
+//
+// Blank lines and `#` comments are skipped; anything else is logged and ignored.
+
+use std::io::BufRead;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use bytes::Bytes;
+use crossbeam_channel as channel;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::ui::inject::{parse_hex, InjectError};
+use crate::ui::overview::Counters;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Send(Vec<u8>),
+    Pause,
+    Resume,
+    Baud(u32),
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CommandError {
+    #[error("unknown command '{0}'")]
+    Unknown(String),
+    #[error("usage: {0}")]
+    Usage(&'static str),
+    #[error(transparent)]
+    Hex(#[from] InjectError),
+}
+
+/// The command on one line; `None` for blank lines and comments
+pub fn parse_command(line: &str) -> Result<Option<Command>, CommandError> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    let command = match word {
+        "send" => Command::Send(parse_hex(rest)?),
+        "pause" if rest.is_empty() => Command::Pause,
+        "resume" if rest.is_empty() => Command::Resume,
+        "pause" | "resume" => return Err(CommandError::Usage("pause | resume")),
+        "baud" => match rest.parse() {
+            Ok(rate) if rate > 0 => Command::Baud(rate),
+            _ => return Err(CommandError::Usage("baud <RATE>")),
+        },
+        other => return Err(CommandError::Unknown(other.to_string())),
+    };
+    Ok(Some(command))
+}
+
+/// Read commands from stdin until it closes. The thread is left blocked in
+/// `read` at shutdown, so it is not joined.
+pub fn spawn_stdin_commands(
+    to_serial_tx: channel::Sender<Bytes>,
+    counters: Arc<Counters>,
+    status_tx: channel::Sender<String>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            let command = match parse_command(&line) {
+                Ok(Some(command)) => command,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!(%line, "Ignoring stdin command: {e}");
+                    continue;
+                }
+            };
+            let message = match command {
+                Command::Send(bytes) => {
+                    let len = bytes.len();
+                    match to_serial_tx.try_send(Bytes::from(bytes)) {
+                        Ok(()) => format!("Stdin: sent {len} byte(s) to the device"),
+                        Err(_) => "Stdin: device queue full, send dropped".to_string(),
+                    }
+                }
+                Command::Pause => {
+                    counters.serial_read_paused.store(true, Ordering::Relaxed);
+                    "Stdin: serial reads paused".to_string()
+                }
+                Command::Resume => {
+                    counters.serial_read_paused.store(false, Ordering::Relaxed);
+                    "Stdin: serial reads resumed".to_string()
+                }
+                // applied by the serial reader, which owns the port
+                Command::Baud(rate) => {
+                    counters.baud_request.store(rate, Ordering::Relaxed);
+                    continue;
+                }
+            };
+            let _ = status_tx.send(message);
+        }
+        info!("stdin closed, no more commands");
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_grammar() {
+        assert_eq!(
+            parse_command("send 68656c6c6f"),
+            Ok(Some(Command::Send(b"hello".to_vec())))
+        );
+        assert_eq!(
+            parse_command("  send 0d 0a "),
+            Ok(Some(Command::Send(vec![0x0d, 0x0a])))
+        );
+        assert_eq!(parse_command("pause"), Ok(Some(Command::Pause)));
+        assert_eq!(parse_command("resume\r"), Ok(Some(Command::Resume)));
+        assert_eq!(parse_command("baud 9600"), Ok(Some(Command::Baud(9600))));
+        assert_eq!(parse_command(""), Ok(None));
+        assert_eq!(parse_command("# comment"), Ok(None));
+    }
+
+    #[test]
+    fn rejects_bad_lines() {
+        assert_eq!(
+            parse_command("reboot now"),
+            Err(CommandError::Unknown("reboot".into()))
+        );
+        assert_eq!(
+            parse_command("baud fast"),
+            Err(CommandError::Usage("baud <RATE>"))
+        );
+        assert_eq!(
+            parse_command("baud 0"),
+            Err(CommandError::Usage("baud <RATE>"))
+        );
+        assert_eq!(
+            parse_command("pause 3"),
+            Err(CommandError::Usage("pause | resume"))
+        );
+        assert_eq!(
+            parse_command("send"),
+            Err(CommandError::Hex(InjectError::Empty))
+        );
+    }
+}
@@ -0,0 +1,409 @@
+// The "device" side of the bridge: a local serial port, or a TCP connection to
+// an upstream server (e.g. another sergw) when running as a fan-out proxy.
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serialport::SerialPort;
+
+use crate::cli::Listen;
+use crate::serial::lineerr::LineErrors;
+use crate::serial::usb_description;
+use crate::ui::overview::DeviceLabel;
+
+// Matches the serial read timeout so reader loops notice shutdown promptly
+const UPSTREAM_READ_TIMEOUT: Duration = Duration::from_millis(200);
+const UPSTREAM_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub trait DeviceIo: Read + Write + Send {
+    /// Serial ports expose modem status lines; other transports have none.
+    fn as_serial(&mut self) -> Option<&mut dyn SerialPort> {
+        None
+    }
+
+    /// How long a read waits for data; `None` if the handle can't change it
+    /// (such handles are never coalesced)
+    fn read_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn set_read_timeout(&mut self, _timeout: Duration) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Break/framing/parity/overrun totals kept by the driver; `None` if the
+    /// handle has no way to ask
+    fn line_errors(&self) -> Option<io::Result<LineErrors>> {
+        None
+    }
+}
+
+impl DeviceIo for Box<dyn SerialPort> {
+    fn as_serial(&mut self) -> Option<&mut dyn SerialPort> {
+        Some(self.as_mut())
+    }
+
+    fn read_timeout(&self) -> Option<Duration> {
+        Some(self.timeout())
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.set_timeout(timeout).map_err(io::Error::from)
+    }
+}
+
+// Opened natively on Linux so the reader can poll the driver's error counters
+#[cfg(target_os = "linux")]
+impl DeviceIo for serialport::TTYPort {
+    fn as_serial(&mut self) -> Option<&mut dyn SerialPort> {
+        Some(self)
+    }
+
+    fn read_timeout(&self) -> Option<Duration> {
+        Some(self.timeout())
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.set_timeout(timeout).map_err(io::Error::from)
+    }
+
+    fn line_errors(&self) -> Option<io::Result<LineErrors>> {
+        use std::os::fd::AsRawFd;
+        Some(crate::serial::lineerr::read_line_errors(self.as_raw_fd()))
+    }
+}
+
+/// Upstream TCP stream made to read like a serial port: idle reads report
+/// `TimedOut` and a closed peer reports `BrokenPipe`, so the bridge's
+/// reconnect handling applies unchanged.
+pub(crate) struct UpstreamStream(pub(crate) TcpStream);
+
+impl UpstreamStream {
+    pub(crate) fn connect(addr: SocketAddr) -> Result<Self> {
+        let stream = TcpStream::connect_timeout(&addr, UPSTREAM_CONNECT_TIMEOUT)
+            .with_context(|| format!("Connecting to upstream {addr}"))?;
+        stream.set_nodelay(true).ok();
+        stream
+            .set_read_timeout(Some(UPSTREAM_READ_TIMEOUT))
+            .context("Setting upstream read timeout")?;
+        Ok(Self(stream))
+    }
+}
+
+impl Read for UpstreamStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.read(buf) {
+            Ok(0) if !buf.is_empty() => Err(io::ErrorKind::BrokenPipe.into()),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Err(io::ErrorKind::TimedOut.into()),
+            other => other,
+        }
+    }
+}
+
+impl Write for UpstreamStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl DeviceIo for UpstreamStream {
+    fn read_timeout(&self) -> Option<Duration> {
+        self.0.read_timeout().ok().flatten()
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        // A zero socket timeout is rejected rather than meaning "don't wait"
+        self.0
+            .set_read_timeout(Some(timeout.max(Duration::from_millis(1))))
+    }
+}
+
+pub type DeviceHandle = Box<dyn DeviceIo>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceTarget {
+    Serial(String),
+    Upstream(SocketAddr),
+    /// Serial port on an RFC 2217 (Telnet COM-PORT-OPTION) device server
+    #[cfg(feature = "rfc2217")]
+    Rfc2217(SocketAddr),
+}
+
+impl DeviceTarget {
+    /// Label used in status messages ("Serial: disconnected", ...)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DeviceTarget::Serial(_) => "Serial",
+            DeviceTarget::Upstream(_) => "Upstream",
+            #[cfg(feature = "rfc2217")]
+            DeviceTarget::Rfc2217(_) => "RFC2217",
+        }
+    }
+
+    /// Whether the reader and writer must share one connection, reopened by
+    /// the reader alone: a second upstream connection would be an extra
+    /// client nobody reads from, and single-session RFC 2217 servers (ser2net)
+    /// refuse a second session outright
+    pub fn shared_reconnect(&self) -> bool {
+        match self {
+            DeviceTarget::Serial(_) => false,
+            DeviceTarget::Upstream(_) => true,
+            #[cfg(feature = "rfc2217")]
+            DeviceTarget::Rfc2217(_) => true,
+        }
+    }
+
+    /// Short name for service advertisement and the terminal title, e.g. `ttyUSB0`
+    pub fn short_name(&self) -> String {
+        match self {
+            DeviceTarget::Serial(path) => path.rsplit('/').next().unwrap_or(path).to_string(),
+            DeviceTarget::Upstream(addr) => format!("upstream-{addr}"),
+            #[cfg(feature = "rfc2217")]
+            DeviceTarget::Rfc2217(addr) => format!("rfc2217-{addr}"),
+        }
+    }
+
+    /// Path, baud applicability and USB adapter for the overview's device line
+    pub fn label(&self) -> DeviceLabel {
+        DeviceLabel {
+            name: self.to_string(),
+            has_baud: !matches!(self, DeviceTarget::Upstream(_)),
+            adapter: match self {
+                DeviceTarget::Serial(path) => usb_description(path),
+                _ => None,
+            },
+        }
+    }
+
+    /// Cheap presence check for --dry-run; never opens the device.
+    pub fn check_present(&self) -> Result<()> {
+        match self {
+            DeviceTarget::Serial(path) if !serial_present(path) => Err(serialport::Error::new(
+                serialport::ErrorKind::NoDevice,
+                format!("Serial device {path} not found"),
+            )
+            .into()),
+            // Resolving the address is as far as a dry run goes for upstreams
+            _ => Ok(()),
+        }
+    }
+
+    /// Open a reader handle and a writer handle onto the same device.
+    pub fn open_pair(&self, listen: &Listen) -> Result<(DeviceHandle, DeviceHandle)> {
+        match self {
+            DeviceTarget::Serial(path) => {
+                let builder = serialport::new(path, listen.baud());
+                #[cfg(target_os = "linux")]
+                let port = crate::serial::configure_serial_native(builder, &listen.line);
+                #[cfg(not(target_os = "linux"))]
+                let port = crate::serial::configure_serial(builder, &listen.line);
+                let mut port = port.with_context(|| format!("Opening serial port {path}"))?;
+                // re-applied on every reopen, since a reset adapter forgets them
+                set_modem_lines(&mut port, path, listen)?;
+                let writer = port
+                    .try_clone()
+                    .with_context(|| format!("Cloning serial port {path} for writer"))?;
+                // Only the reader polls; the writer keeps the blocking timeout
+                if listen.nonblocking_serial {
+                    port.set_timeout(Duration::ZERO)
+                        .with_context(|| format!("Setting non-blocking reads on {path}"))?;
+                }
+                Ok((Box::new(port), Box::new(writer)))
+            }
+            DeviceTarget::Upstream(addr) => {
+                let stream = UpstreamStream::connect(*addr)?;
+                let writer = stream
+                    .0
+                    .try_clone()
+                    .with_context(|| format!("Cloning upstream stream {addr} for writer"))?;
+                Ok((Box::new(stream), Box::new(UpstreamStream(writer))))
+            }
+            #[cfg(feature = "rfc2217")]
+            DeviceTarget::Rfc2217(addr) => crate::net::rfc2217::open_pair(*addr, listen),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn serial_present(path: &str) -> bool {
+    std::path::Path::new(path).exists()
+}
+
+// COM ports are not filesystem paths; ask the enumerator instead
+#[cfg(not(unix))]
+fn serial_present(path: &str) -> bool {
+    crate::serial::list_available_ports(true)
+        .iter()
+        .any(|p| p.port_name.eq_ignore_ascii_case(path))
+}
+
+/// Drive DTR/RTS as `--dtr`/`--rts` ask; lines not given keep the driver's default
+pub fn set_modem_lines(port: &mut dyn SerialPort, path: &str, listen: &Listen) -> Result<()> {
+    if let Some(level) = listen.dtr {
+        port.write_data_terminal_ready(level.is_on())
+            .with_context(|| format!("Setting DTR {level} on {path}"))?;
+    }
+    if let Some(level) = listen.rts {
+        port.write_request_to_send(level.is_on())
+            .with_context(|| format!("Setting RTS {level} on {path}"))?;
+    }
+    Ok(())
+}
+
+/// What `--dtr`/`--rts` set and what the status lines read back, e.g.
+/// "DTR on, RTS off (outputs, not readable); CTS on, DSR on, CD off, RI off"
+pub fn modem_report(listen: &Listen, port: &mut dyn SerialPort) -> String {
+    let set: Vec<String> = [("DTR", listen.dtr), ("RTS", listen.rts)]
+        .into_iter()
+        .filter_map(|(name, level)| Some(format!("{name} {}", level?)))
+        .collect();
+    let read = [
+        ("CTS", port.read_clear_to_send()),
+        ("DSR", port.read_data_set_ready()),
+        ("CD", port.read_carrier_detect()),
+        ("RI", port.read_ring_indicator()),
+    ];
+    format!(
+        "{} (outputs, not readable); {}",
+        set.join(", "),
+        describe_status_lines(read)
+    )
+}
+
+fn describe_status_lines(read: [(&str, serialport::Result<bool>); 4]) -> String {
+    read.into_iter()
+        .map(|(name, level)| match level {
+            Ok(true) => format!("{name} on"),
+            Ok(false) => format!("{name} off"),
+            Err(e) => format!("{name} unreadable ({e})"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl fmt::Display for DeviceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceTarget::Serial(path) => f.write_str(path),
+            DeviceTarget::Upstream(addr) => write!(f, "tcp://{addr}"),
+            #[cfg(feature = "rfc2217")]
+            DeviceTarget::Rfc2217(addr) => write!(f, "rfc2217://{addr}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn display_names() {
+        let up = DeviceTarget::Upstream("10.0.0.1:5656".parse().unwrap());
+        assert_eq!(up.to_string(), "tcp://10.0.0.1:5656");
+        assert_eq!(up.kind(), "Upstream");
+        #[cfg(feature = "mdns")]
+        {
+            assert_eq!(up.short_name(), "upstream-10.0.0.1:5656");
+            assert_eq!(
+                DeviceTarget::Serial("/dev/ttyUSB0".into()).short_name(),
+                "ttyUSB0"
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn missing_serial_is_a_serial_error() {
+        let err = DeviceTarget::Serial("/dev/sergw-does-not-exist".into())
+            .check_present()
+            .unwrap_err();
+        assert!(err.is::<serialport::Error>());
+        assert!(DeviceTarget::Serial("/dev/null".into())
+            .check_present()
+            .is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn nonblocking_serial_reader_returns_immediately() {
+        use crate::cli::{Cli, Commands};
+        use clap::Parser;
+        use nix::pty::{openpty, Winsize};
+        use std::os::fd::AsRawFd;
+        use std::time::Instant;
+
+        let pty = openpty(None::<&Winsize>, None).unwrap();
+        let path = std::fs::read_link(format!("/proc/self/fd/{}", pty.slave.as_raw_fd())).unwrap();
+        let cli = Cli::parse_from([
+            "sergw",
+            "listen",
+            "--serial",
+            &path.to_string_lossy(),
+            "--nonblocking-serial",
+        ]);
+        let Some(Commands::Listen(listen)) = cli.command else {
+            panic!("expected listen");
+        };
+        let target = DeviceTarget::Serial(path.to_string_lossy().into_owned());
+        let (mut reader, mut writer) = target.open_pair(&listen).unwrap();
+
+        let start = Instant::now();
+        let mut buf = [0u8; 8];
+        match reader.read(&mut buf) {
+            Ok(0) => {}
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+            Ok(n) => panic!("unexpected {n} bytes"),
+        }
+        assert!(start.elapsed() < Duration::from_millis(100));
+        assert_eq!(writer.write(b"x").unwrap(), 1);
+    }
+
+    #[test]
+    fn upstream_reads_like_a_serial_port() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(20)))
+            .unwrap();
+        let mut up = UpstreamStream(stream);
+        let (peer, _) = server.accept().unwrap();
+
+        let mut buf = [0u8; 8];
+        assert_eq!(
+            up.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::TimedOut
+        );
+        drop(peer);
+        assert_eq!(
+            up.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+    }
+
+    #[test]
+    fn status_lines_note_what_cannot_be_read() {
+        let unsupported = || {
+            Err(serialport::Error::new(
+                serialport::ErrorKind::Unknown,
+                "not supported",
+            ))
+        };
+        assert_eq!(
+            describe_status_lines([
+                ("CTS", Ok(true)),
+                ("DSR", Ok(false)),
+                ("CD", unsupported()),
+                ("RI", Ok(false)),
+            ]),
+            "CTS on, DSR off, CD unreadable (not supported), RI off"
+        );
+    }
+}
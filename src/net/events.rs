@@ -0,0 +1,127 @@
+// `--event-format json`: the bridge's event strings ("Connected: 1.2.3.4:5",
+// "Serial: reconnected (reader) after 2 attempt(s)", ...) turned into one JSON
+// object per line for log shippers. The original text is kept in `message`.
+
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::cli::EventFormat;
+
+// Client and listener events: "<Prefix>: <addr>[ ...]"
+const CLIENT_EVENTS: [(&str, &str); 10] = [
+    ("Connected", "connected"),
+    ("Disconnected", "disconnected"),
+    ("Named", "named"),
+    ("Rejected", "rejected"),
+    ("Kicked", "kicked"),
+    ("Resolved", "resolved"),
+    ("Controller", "controller"),
+    ("Observer", "observer"),
+    ("Rate limited", "rate_limited"),
+    ("Listening", "listening"),
+];
+
+#[derive(Debug, Serialize)]
+struct EventRecord<'a> {
+    event: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    addr: Option<SocketAddr>,
+    /// Seconds since the Unix epoch
+    ts: f64,
+    message: &'a str,
+}
+
+fn classify(message: &str) -> (&'static str, Option<SocketAddr>) {
+    let Some((prefix, rest)) = message.split_once(": ") else {
+        return ("message", None);
+    };
+    if let Some((_, event)) = CLIENT_EVENTS.iter().find(|(p, _)| *p == prefix) {
+        let addr = rest.split_whitespace().next().and_then(|a| a.parse().ok());
+        return (event, addr);
+    }
+    // Device status lines are prefixed with the device kind (Serial, Upstream, ...)
+    let event = if rest.contains("reconnected") {
+        "device_reconnected"
+    } else if rest.contains("reconnecting") {
+        "device_reconnecting"
+    } else if rest.starts_with("disconnected") || rest.starts_with("write failed") {
+        "device_disconnected"
+    } else {
+        "message"
+    };
+    (event, None)
+}
+
+/// Render one event for headless output and `--event-addr` subscribers
+pub fn format_event(format: EventFormat, message: &str, at: SystemTime) -> String {
+    match format {
+        EventFormat::Text => message.to_string(),
+        EventFormat::Json => {
+            let (event, addr) = classify(message);
+            let ts = at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0.0, |d| (d.as_millis() as f64) / 1000.0);
+            let record = EventRecord {
+                event,
+                addr,
+                ts,
+                message,
+            };
+            serde_json::to_string(&record).unwrap_or_else(|_| message.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn client_and_device_events_are_classified() {
+        assert_eq!(
+            classify("Connected: 127.0.0.1:4000 on 0.0.0.0:5656 (1 client(s) connected)"),
+            ("connected", Some("127.0.0.1:4000".parse().unwrap()))
+        );
+        assert_eq!(
+            classify("Named: [::1]:9 as scope"),
+            ("named", Some("[::1]:9".parse().unwrap()))
+        );
+        assert_eq!(
+            classify("Serial: reconnected (reader) after 2 attempt(s)"),
+            ("device_reconnected", None)
+        );
+        assert_eq!(
+            classify("Upstream: disconnected, attempting reconnect..."),
+            ("device_disconnected", None)
+        );
+        assert_eq!(
+            classify("Controller: 10.0.0.3:700 promoted after 10.0.0.2:51000 left"),
+            ("controller", Some("10.0.0.3:700".parse().unwrap()))
+        );
+        assert_eq!(
+            classify("Rate limited: 10.0.0.4:5 sent over 100 B/s for 3s, disconnecting"),
+            ("rate_limited", Some("10.0.0.4:5".parse().unwrap()))
+        );
+        assert_eq!(
+            classify("Listening: 127.0.0.1:41234"),
+            ("listening", Some("127.0.0.1:41234".parse().unwrap()))
+        );
+        assert_eq!(classify("Serial reads paused"), ("message", None));
+    }
+
+    #[test]
+    fn json_line_carries_event_addr_and_ts() {
+        let at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+        assert_eq!(
+            format_event(EventFormat::Json, "Disconnected: 10.0.0.2:51000", at),
+            r#"{"event":"disconnected","addr":"10.0.0.2:51000","ts":1700000000.25,"message":"Disconnected: 10.0.0.2:51000"}"#
+        );
+        assert_eq!(
+            format_event(EventFormat::Text, "Disconnected: 10.0.0.2:51000", at),
+            "Disconnected: 10.0.0.2:51000"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+// Optional TCP framing. In `length-prefix` mode every message on the wire is
+// a little-endian u32 byte count followed by that many payload bytes.
+
+use bytes::Bytes;
+use thiserror::Error;
+
+const HEADER: usize = 4;
+/// Refuse frames above this size rather than buffering without bound
+pub const MAX_FRAME: usize = 1024 * 1024;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FrameError {
+    #[error("frame of {len} bytes exceeds the {max} byte limit")]
+    TooLarge { len: usize, max: usize },
+}
+
+pub fn encode(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER + payload.len());
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Reassembles length-prefixed frames from arbitrary TCP read boundaries.
+#[derive(Default)]
+pub struct Deframer {
+    pending: Vec<u8>,
+}
+
+impl Deframer {
+    /// Returns every frame completed by `data`; partial frames are kept.
+    pub fn feed(&mut self, data: &[u8]) -> Result<Vec<Bytes>, FrameError> {
+        self.pending.extend_from_slice(data);
+        let mut frames = Vec::new();
+        let mut pos = 0;
+        while self.pending.len() - pos >= HEADER {
+            let mut len_bytes = [0u8; HEADER];
+            len_bytes.copy_from_slice(&self.pending[pos..pos + HEADER]);
+            let len = u32::from_le_bytes(len_bytes) as usize;
+            if len > MAX_FRAME {
+                return Err(FrameError::TooLarge {
+                    len,
+                    max: MAX_FRAME,
+                });
+            }
+            if self.pending.len() - pos - HEADER < len {
+                break;
+            }
+            let start = pos + HEADER;
+            frames.push(Bytes::copy_from_slice(&self.pending[start..start + len]));
+            pos = start + len;
+        }
+        self.pending.drain(..pos);
+        Ok(frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut wire = encode(b"hello");
+        wire.extend(encode(b""));
+        wire.extend(encode(b"AT\r\n"));
+        let frames = Deframer::default().feed(&wire).unwrap();
+        assert_eq!(frames, vec![&b"hello"[..], &b""[..], &b"AT\r\n"[..]]);
+    }
+
+    #[test]
+    fn header_is_little_endian() {
+        assert_eq!(&encode(&[0xAA; 258])[..4], &[2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn split_across_reads() {
+        let wire = encode(b"payload");
+        let mut d = Deframer::default();
+        assert!(d.feed(&wire[..2]).unwrap().is_empty());
+        assert!(d.feed(&wire[2..6]).unwrap().is_empty());
+        assert_eq!(d.feed(&wire[6..]).unwrap(), vec![&b"payload"[..]]);
+    }
+
+    #[test]
+    fn oversized_frame_is_rejected() {
+        let header = ((MAX_FRAME + 1) as u32).to_le_bytes();
+        let err = Deframer::default().feed(&header).unwrap_err();
+        assert_eq!(
+            err,
+            FrameError::TooLarge {
+                len: MAX_FRAME + 1,
+                max: MAX_FRAME
+            }
+        );
+    }
+}
@@ -0,0 +1,196 @@
+// Optional client hello: leading `SERGW-<KEY> <value>\n` lines sent right after
+// connecting. They are consumed here and never reach the serial device. Bytes
+// held back as a possible hello line are released as data once the client
+// goes quiet without finishing it.
+
+const PREFIX: &[u8] = b"SERGW-";
+// Give up on a would-be hello line that never terminates
+const MAX_LINE: usize = 256;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HelloLine {
+    Name(String),
+    Mode(ClientMode),
+}
+
+/// How serial output is delivered to one client
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ClientMode {
+    /// Bytes exactly as read from the device
+    #[default]
+    Raw,
+    /// Only complete `\n`-terminated lines, one message per line
+    Line,
+}
+
+impl HelloLine {
+    fn parse(key: &str, value: &str) -> Option<Self> {
+        match key {
+            "NAME" => Some(HelloLine::Name(sanitize(value)?)),
+            "MODE" => match value.trim().to_ascii_lowercase().as_str() {
+                "raw" => Some(HelloLine::Mode(ClientMode::Raw)),
+                "line" => Some(HelloLine::Mode(ClientMode::Line)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+fn sanitize(value: &str) -> Option<String> {
+    let cleaned: String = value
+        .trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(64)
+        .collect();
+    (!cleaned.is_empty()).then_some(cleaned)
+}
+
+/// Peels hello lines off the start of a client stream. Once anything that is
+/// not a hello line shows up, the handshake is over and bytes pass through.
+#[derive(Default)]
+pub struct Handshake {
+    pending: Vec<u8>,
+    done: bool,
+}
+
+impl Handshake {
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Returns the hello lines found and whatever should be forwarded as data.
+    pub fn feed(&mut self, data: &[u8]) -> (Vec<HelloLine>, Vec<u8>) {
+        if self.done {
+            return (Vec::new(), data.to_vec());
+        }
+        self.pending.extend_from_slice(data);
+        let mut lines = Vec::new();
+        loop {
+            let buf = &self.pending[..];
+            if buf.is_empty() {
+                return (lines, Vec::new());
+            }
+            let head = &buf[..buf.len().min(PREFIX.len())];
+            if !PREFIX.starts_with(head) {
+                break;
+            }
+            let Some(nl) = buf.iter().position(|&b| b == b'\n') else {
+                if buf.len() > MAX_LINE {
+                    break;
+                }
+                // Could still become a hello line; wait for more
+                return (lines, Vec::new());
+            };
+            let line = String::from_utf8_lossy(&buf[PREFIX.len().min(nl)..nl]).into_owned();
+            let line = line.trim_end_matches('\r');
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match HelloLine::parse(key, value) {
+                Some(h) => {
+                    lines.push(h);
+                    self.pending.drain(..=nl);
+                }
+                None => break,
+            }
+        }
+        self.done = true;
+        (lines, std::mem::take(&mut self.pending))
+    }
+
+    /// The client went idle: if it stopped mid-line, end the handshake and
+    /// return what was held back (e.g. a lone `SERG`) so it can be forwarded
+    /// as data. A client that has sent nothing yet may still say hello.
+    pub fn flush(&mut self) -> Vec<u8> {
+        if !self.pending.is_empty() {
+            self.done = true;
+        }
+        std::mem::take(&mut self.pending)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_line_is_stripped() {
+        let mut h = Handshake::default();
+        let (lines, rest) = h.feed(b"SERGW-NAME scope-1\r\nAT\r\n");
+        assert_eq!(lines, vec![HelloLine::Name("scope-1".into())]);
+        assert_eq!(rest, b"AT\r\n");
+        assert!(h.is_done());
+    }
+
+    #[test]
+    fn mode_and_name_together() {
+        let mut h = Handshake::default();
+        let (lines, rest) = h.feed(b"SERGW-MODE line\nSERGW-NAME log\n");
+        assert_eq!(
+            lines,
+            vec![
+                HelloLine::Mode(ClientMode::Line),
+                HelloLine::Name("log".into())
+            ]
+        );
+        assert!(rest.is_empty());
+        // unknown modes are not consumed
+        let mut h = Handshake::default();
+        let (lines, rest) = h.feed(b"SERGW-MODE json\n");
+        assert!(lines.is_empty());
+        assert_eq!(rest, b"SERGW-MODE json\n");
+    }
+
+    #[test]
+    fn split_across_reads() {
+        let mut h = Handshake::default();
+        let (lines, rest) = h.feed(b"SERG");
+        assert!(lines.is_empty() && rest.is_empty());
+        let (lines, rest) = h.feed(b"W-NAME bench\nhi");
+        assert_eq!(lines, vec![HelloLine::Name("bench".into())]);
+        assert_eq!(rest, b"hi");
+    }
+
+    #[test]
+    fn idle_flush_releases_a_partial_prefix() {
+        let mut h = Handshake::default();
+        assert!(h.flush().is_empty());
+        assert!(!h.is_done());
+        let (lines, rest) = h.feed(b"SER");
+        assert!(lines.is_empty() && rest.is_empty());
+        assert_eq!(h.flush(), b"SER");
+        assert!(h.is_done());
+        let (_, rest) = h.feed(b"GW-NAME late\n");
+        assert_eq!(rest, b"GW-NAME late\n");
+        assert!(h.flush().is_empty());
+    }
+
+    #[test]
+    fn plain_data_passes_through() {
+        let mut h = Handshake::default();
+        let (lines, rest) = h.feed(b"hello\n");
+        assert!(lines.is_empty());
+        assert_eq!(rest, b"hello\n");
+        assert!(h.is_done());
+        let (_, rest) = h.feed(b"SERGW-NAME late\n");
+        assert_eq!(rest, b"SERGW-NAME late\n");
+    }
+
+    #[test]
+    fn unknown_key_is_data() {
+        let mut h = Handshake::default();
+        let (lines, rest) = h.feed(b"SERGW-FOO x\n");
+        assert!(lines.is_empty());
+        assert_eq!(rest, b"SERGW-FOO x\n");
+    }
+
+    #[test]
+    fn unterminated_line_gives_up() {
+        let mut h = Handshake::default();
+        let mut long = b"SERGW-NAME ".to_vec();
+        long.extend(std::iter::repeat(b'a').take(300));
+        let (lines, rest) = h.feed(&long);
+        assert!(lines.is_empty());
+        assert_eq!(rest, long);
+    }
+}
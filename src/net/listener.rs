@@ -1,5 +1,6 @@
 use std::io::{Read, Write};
-use std::net::TcpStream;
+use std::net::{SocketAddr, TcpStream};
+use std::path::Path;
 use std::sync::{
     atomic::{AtomicBool, AtomicU64, Ordering},
     Arc, Mutex,
@@ -9,9 +10,9 @@ use std::time::{Duration, Instant};
 use anyhow::Result;
 use crossbeam_channel as channel;
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{self, DisableBracketedPaste, Event, KeyCode, KeyModifiers},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{disable_raw_mode, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
@@ -20,31 +21,179 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph, Wrap},
     Terminal,
 };
+use serialport::SerialPort;
 
 use crate::cli::Chat;
-use crate::metrics::ThroughputAverager;
+use crate::metrics::{next_smoothing, ThroughputAverager, DEFAULT_SMOOTHING};
+use crate::net::resolve::resolve_host;
+use crate::rate::TokenBucket;
+use crate::serial::configure_serial;
+use crate::ui::input::InputLine;
+use crate::ui::inspector::{clip_line, max_hscroll, HSCROLL_STEP};
+use crate::ui::keymap::{caret_notation, control_byte, Action, Keymaps};
 
-pub fn run_chat(chat: Chat) -> Result<()> {
-    // Connect TCP (retry until available)
-    let connect = |host: std::net::SocketAddr| -> TcpStream {
+const SEND_CHUNK: usize = 1024;
+// Short so the reader doesn't hold the port lock while the UI wants to write
+const SERIAL_POLL: Duration = Duration::from_millis(20);
+
+/// What the chat UI talks to: a TCP connection or a local serial port
+pub trait ChatStream: Read + Write + Send {
+    /// A pending socket error, checked before each send
+    fn take_error(&self) -> std::io::Result<Option<std::io::Error>> {
+        Ok(None)
+    }
+}
+
+impl ChatStream for TcpStream {
+    fn take_error(&self) -> std::io::Result<Option<std::io::Error>> {
+        TcpStream::take_error(self)
+    }
+}
+
+impl ChatStream for Box<dyn SerialPort> {}
+
+#[derive(Clone)]
+enum Endpoint {
+    Tcp(SocketAddr),
+    Serial(String),
+}
+
+impl Endpoint {
+    /// Open the endpoint, retrying until it is available
+    fn connect(&self, chat: &Chat) -> Box<dyn ChatStream> {
         loop {
-            match TcpStream::connect(host) {
-                Ok(s) => {
-                    let _ = s.set_nodelay(true);
-                    let _ = s.set_nonblocking(true);
-                    break s;
+            match self {
+                Endpoint::Tcp(host) => {
+                    if let Ok(s) = TcpStream::connect(host) {
+                        let _ = s.set_nodelay(true);
+                        let _ = s.set_nonblocking(true);
+                        return Box::new(s);
+                    }
                 }
-                Err(_) => {
-                    std::thread::sleep(Duration::from_millis(800));
+                Endpoint::Serial(path) => {
+                    if let Ok(mut port) =
+                        configure_serial(serialport::new(path, chat.baud), &chat.line)
+                    {
+                        let _ = port.set_timeout(SERIAL_POLL);
+                        return Box::new(port);
+                    }
+                }
+            }
+            std::thread::sleep(Duration::from_millis(800));
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Endpoint::Tcp(host) => host.to_string(),
+            Endpoint::Serial(path) => path.clone(),
+        }
+    }
+}
+
+/// Write all of `data`, retrying on WouldBlock. The lock is taken per attempt so
+/// the reader thread can keep draining replies during a long upload.
+fn write_fully<W: Write + ?Sized>(
+    stream: &Mutex<Box<W>>,
+    mut data: &[u8],
+    stop: &AtomicBool,
+) -> std::io::Result<()> {
+    while !data.is_empty() {
+        if stop.load(Ordering::Relaxed) {
+            return Err(std::io::ErrorKind::Interrupted.into());
+        }
+        let res = match stream.lock() {
+            Ok(mut s) => s.write(data),
+            Err(_) => Err(std::io::ErrorKind::Other.into()),
+        };
+        match res {
+            Ok(0) => return Err(std::io::ErrorKind::WriteZero.into()),
+            Ok(n) => data = &data[n..],
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Stream a file to the connection in chunks, optionally paced, reporting
+/// progress every quarter and on completion.
+fn send_file<W: Write + ?Sized>(
+    path: &Path,
+    stream: &Mutex<Box<W>>,
+    rate: Option<u64>,
+    tx_bytes: &AtomicU64,
+    log: &channel::Sender<String>,
+    stop: &AtomicBool,
+) {
+    let data = match std::fs::read(path) {
+        Ok(d) => d,
+        Err(e) => {
+            let _ = log.send(format!("! /send {}: {e}", path.display()));
+            return;
+        }
+    };
+    let name = path.display();
+    let total = data.len();
+    let _ = log.send(format!("* sending {name} ({total} bytes)"));
+    let mut bucket = rate.map(TokenBucket::new);
+    let started = Instant::now();
+    let mut sent = 0usize;
+    let mut next_report = 25;
+    for chunk in data.chunks(SEND_CHUNK) {
+        if let Some(b) = bucket.as_mut() {
+            while !b.try_take(chunk.len() as u64) {
+                if stop.load(Ordering::Relaxed) {
+                    let _ = log.send(format!(
+                        "! sending {name} stopped after {sent}/{total} bytes"
+                    ));
+                    return;
                 }
+                std::thread::sleep(Duration::from_millis(10));
             }
         }
+        if let Err(e) = write_fully(stream, chunk, stop) {
+            let _ = log.send(format!(
+                "! sending {name} failed after {sent}/{total} bytes: {e}"
+            ));
+            return;
+        }
+        sent += chunk.len();
+        tx_bytes.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        let pct = sent * 100 / total;
+        if pct >= next_report && sent < total {
+            let _ = log.send(format!("* sending {name}: {sent}/{total} bytes ({pct}%)"));
+            next_report = pct / 25 * 25 + 25;
+        }
+    }
+    let _ = log.send(format!(
+        "* sent {name}: {sent} bytes in {:.1}s",
+        started.elapsed().as_secs_f64()
+    ));
+}
+
+pub fn run_chat(chat: Chat) -> Result<()> {
+    let endpoint = match &chat.serial {
+        Some(path) => Endpoint::Serial(path.clone()),
+        None => Endpoint::Tcp(resolve_host(&chat.host, chat.prefer_ip)?),
     };
-    let stream = connect(chat.host);
-    let stream = Arc::new(Mutex::new(stream));
+    let connect = || endpoint.connect(&chat);
+    let keys = Keymaps::load(chat.keymap.as_deref())?.chat;
+    let input_title = format!(
+        "Input ({} to send, /send <path> to upload a file, {} to clear, {} to wrap/clip, {} <key> for Ctrl+<key>, {} to quit)",
+        keys.label(Action::Send),
+        keys.label(Action::ClearInput),
+        keys.label(Action::ToggleWrap),
+        keys.label(Action::ControlPrefix),
+        keys.label(Action::Quit)
+    );
+    let control_title = "Input (control key: press a key to send it as Ctrl+<key>, Esc to cancel)";
+    let stream = Arc::new(Mutex::new(connect()));
 
     // helper to write with one retry on WouldBlock
-    let try_send = |s: &mut TcpStream, data: &[u8]| -> bool {
+    let try_send = |s: &mut dyn ChatStream, data: &[u8]| -> bool {
         match s.write_all(data) {
             Ok(_) => true,
             Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -56,13 +205,18 @@ pub fn run_chat(chat: Chat) -> Result<()> {
     };
 
     // UI setup
-    enable_raw_mode()?;
-    let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
+    // Bracketed paste delivers a paste as one event instead of a burst of keys
+    crate::ui::terminal::enter(true)?;
+    let backend = CrosstermBackend::new(std::io::stdout());
     let mut terminal = Terminal::new(backend)?;
 
     let stop = Arc::new(AtomicBool::new(false));
+    // Raw mode turns Ctrl+C into a key; this catches SIGTERM/SIGHUP so the
+    // terminal is restored on the way out
+    {
+        let stop = stop.clone();
+        let _ = ctrlc::set_handler(move || stop.store(true, Ordering::Relaxed));
+    }
     let rx_bytes = Arc::new(AtomicU64::new(0));
     let tx_bytes = Arc::new(AtomicU64::new(0));
     let (log_tx, log_rx) = channel::unbounded::<String>();
@@ -72,7 +226,10 @@ pub fn run_chat(chat: Chat) -> Result<()> {
     let rx_b = rx_bytes.clone();
     let rstream = Arc::clone(&stream);
     let log_tx_reader = log_tx.clone();
+    let chat_r = chat.clone();
+    let endpoint_r = endpoint.clone();
     std::thread::spawn(move || {
+        let connect = || endpoint_r.connect(&chat_r);
         let mut buf = [0u8; 4096];
         while !stop_r.load(Ordering::Relaxed) {
             // lock the stream for this read iteration
@@ -87,7 +244,7 @@ pub fn run_chat(chat: Chat) -> Result<()> {
                 Ok(0) => {
                     // EOF: server closed; reconnect proactively
                     drop(guard);
-                    let new_s = connect(chat.host);
+                    let new_s = connect();
                     if let Ok(mut g) = rstream.lock() {
                         *g = new_s;
                     }
@@ -104,10 +261,15 @@ pub fn run_chat(chat: Chat) -> Result<()> {
                     drop(guard);
                     std::thread::sleep(Duration::from_millis(20));
                 }
+                // serial read timeout: nothing arrived this poll
+                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                    drop(guard);
+                    std::thread::yield_now();
+                }
                 Err(_) => {
                     drop(guard);
                     // attempt immediate reconnect and notify
-                    let new_s = connect(chat.host);
+                    let new_s = connect();
                     if let Ok(mut g) = rstream.lock() {
                         *g = new_s;
                     }
@@ -119,15 +281,20 @@ pub fn run_chat(chat: Chat) -> Result<()> {
     });
 
     let mut logs: Vec<String> = Vec::new();
-    let mut input = String::new();
+    let mut input = InputLine::default();
+    let mut wrap = true;
+    let mut hscroll = 0usize;
+    let mut msg_cols = 0usize;
     let mut last_sent: Option<Vec<u8>> = None;
+    // the control prefix was pressed; the next key becomes a control byte
+    let mut control_pending = false;
     let mut last_rx = 0u64;
     let mut last_tx = 0u64;
-    let mut avg_in = ThroughputAverager::new(5.0);
-    let mut avg_out = ThroughputAverager::new(5.0);
+    let mut avg_in = ThroughputAverager::new(DEFAULT_SMOOTHING);
+    let mut avg_out = ThroughputAverager::new(DEFAULT_SMOOTHING);
     let mut last_time = Instant::now();
 
-    loop {
+    while !stop.load(Ordering::Relaxed) {
         while let Ok(line) = log_rx.try_recv() {
             logs.push(line);
             if logs.len() > 200 {
@@ -140,8 +307,8 @@ pub fn run_chat(chat: Chat) -> Result<()> {
         let dt = now.duration_since(last_time).as_secs_f64().max(0.001);
         let rx = rx_bytes.load(Ordering::Relaxed);
         let tx = tx_bytes.load(Ordering::Relaxed);
-        let inbound = avg_in.update(rx - last_rx, dt) as u64; // from TCP (smoothed)
-        let outbound = avg_out.update(tx - last_tx, dt) as u64; // to TCP (smoothed)
+        let inbound = avg_in.update(rx - last_rx, dt) as u64; // from the peer (smoothed)
+        let outbound = avg_out.update(tx - last_tx, dt) as u64; // to the peer (smoothed)
         last_rx = rx;
         last_tx = tx;
         last_time = now;
@@ -157,96 +324,289 @@ pub fn run_chat(chat: Chat) -> Result<()> {
                 .split(f.size());
 
             let header = Paragraph::new(format!(
-                "listener | {} | In: {} B/s Out: {} B/s",
-                chat.host, inbound, outbound
+                "listener | {} | In: {} B/s Out: {} B/s ({}s avg)",
+                endpoint.label(),
+                inbound,
+                outbound,
+                avg_in.tau_secs()
             ));
             f.render_widget(header, chunks[0]);
 
             // Auto-scroll: render only the last lines that fit
             let viewport = chunks[1].height.saturating_sub(2) as usize; // minus borders
+            msg_cols = chunks[1].width.saturating_sub(2) as usize;
             let start = logs.len().saturating_sub(viewport);
             let lines: Vec<Line> = logs
                 .iter()
                 .skip(start)
-                .map(|l| Line::from(Span::raw(l.clone())))
+                .map(|l| match wrap {
+                    true => Line::from(Span::raw(l.clone())),
+                    false => Line::from(Span::raw(clip_line(l, hscroll, msg_cols))),
+                })
                 .collect();
-            let para = Paragraph::new(lines)
-                .wrap(Wrap { trim: false })
-                .block(Block::default().title("Messages").borders(Borders::ALL));
+            let title = match wrap {
+                true => "Messages",
+                false => "Messages (clipped, Shift+←/→ to scroll)",
+            };
+            let para = match wrap {
+                true => Paragraph::new(lines).wrap(Wrap { trim: false }),
+                false => Paragraph::new(lines),
+            }
+            .block(Block::default().title(title).borders(Borders::ALL));
             f.render_widget(para, chunks[1]);
 
-            let input_box = Paragraph::new(input.clone()).block(
+            let (shown, cursor) = input.view(chunks[2].width.saturating_sub(2) as usize);
+            let input_box = Paragraph::new(shown).block(
                 Block::default()
-                    .title("Input (Enter to send, Ctrl+C to quit)")
+                    .title(match control_pending {
+                        true => control_title,
+                        false => input_title.as_str(),
+                    })
                     .borders(Borders::ALL),
             );
             f.render_widget(input_box, chunks[2]);
+            f.set_cursor(chunks[2].x + 1 + cursor as u16, chunks[2].y + 1);
         })?;
 
         if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(k) = event::read()? {
-                match k.code {
-                    KeyCode::Char('c')
-                        if k.modifiers
-                            .contains(crossterm::event::KeyModifiers::CONTROL) =>
-                    {
-                        break
-                    }
-                    KeyCode::Char(c) => input.push(c),
-                    KeyCode::Backspace => {
-                        input.pop();
-                    }
-                    KeyCode::Enter => {
-                        if !input.is_empty() {
-                            let mut to_send = input.clone();
-                            to_send.push('\n');
-                            let mut wrote = false;
-                            // try write with reconnect on failure
-                            if let Ok(mut g) = stream.lock() {
-                                if let Ok(Some(_)) = g.take_error() {
-                                    // immediate reconnect if socket error present
-                                    let new_s = connect(chat.host);
-                                    if let Ok(mut gg) = stream.lock() {
-                                        *gg = new_s;
-                                    }
-                                }
-                                wrote = try_send(&mut g, to_send.as_bytes());
-                                if !wrote {
-                                    let _ = log_tx.send("! write error: Broken pipe".to_string());
-                                }
-                            }
-                            if !wrote {
-                                // reconnect and retry once
-                                let new_s = connect(chat.host);
-                                if let Ok(mut g) = stream.lock() {
-                                    *g = new_s;
-                                }
-                                if let Ok(mut g) = stream.lock() {
-                                    if let Some(prev) = &last_sent {
-                                        let _ = try_send(&mut g, prev.as_slice());
-                                    }
-                                    std::thread::sleep(Duration::from_millis(150));
-                                    wrote = try_send(&mut g, to_send.as_bytes());
-                                }
+            let k = match event::read()? {
+                Event::Key(k) => k,
+                Event::Paste(text) => {
+                    input.insert_str(&text);
+                    continue;
+                }
+                _ => continue,
+            };
+            if control_pending {
+                control_pending = false;
+                // typed straight to the device, without a newline
+                if let Some(byte) = control_byte(&k) {
+                    let wrote = stream
+                        .lock()
+                        .is_ok_and(|mut g| try_send(g.as_mut(), &[byte]));
+                    let _ = log_tx.send(match wrote {
+                        true => {
+                            tx_bytes.fetch_add(1, Ordering::Relaxed);
+                            format!("> {} (0x{byte:02x})", caret_notation(byte))
+                        }
+                        false => "! write error: Broken pipe".to_string(),
+                    });
+                }
+                continue;
+            }
+            let shifted = k.modifiers.contains(KeyModifiers::SHIFT);
+            match keys.action(&k) {
+                Some(Action::Quit) => break,
+                Some(Action::Send) if input.as_str().starts_with("/send ") => {
+                    let path = std::path::PathBuf::from(input.as_str()["/send ".len()..].trim());
+                    let stream = Arc::clone(&stream);
+                    let tx_bytes = Arc::clone(&tx_bytes);
+                    let log_tx = log_tx.clone();
+                    let stop = Arc::clone(&stop);
+                    let rate = chat.send_rate;
+                    std::thread::spawn(move || {
+                        send_file(&path, &stream, rate, &tx_bytes, &log_tx, &stop)
+                    });
+                    input.clear();
+                }
+                Some(Action::Send) if !input.is_empty() => {
+                    let mut to_send = input.as_str().to_string();
+                    to_send.push('\n');
+                    let mut wrote = false;
+                    // try write with reconnect on failure
+                    if let Ok(mut g) = stream.lock() {
+                        if let Ok(Some(_)) = g.take_error() {
+                            // immediate reconnect if socket error present
+                            let new_s = connect();
+                            if let Ok(mut gg) = stream.lock() {
+                                *gg = new_s;
                             }
-                            if wrote {
-                                tx_bytes.fetch_add(to_send.len() as u64, Ordering::Relaxed);
-                                let _ = log_tx.send(format!("> {input}"));
-                                last_sent = Some(to_send.as_bytes().to_vec());
+                        }
+                        wrote = try_send(g.as_mut(), to_send.as_bytes());
+                        if !wrote {
+                            let _ = log_tx.send("! write error: Broken pipe".to_string());
+                        }
+                    }
+                    if !wrote {
+                        // reconnect and retry once
+                        let new_s = connect();
+                        if let Ok(mut g) = stream.lock() {
+                            *g = new_s;
+                        }
+                        if let Ok(mut g) = stream.lock() {
+                            if let Some(prev) = &last_sent {
+                                let _ = try_send(g.as_mut(), prev.as_slice());
                             }
-                            input.clear();
+                            std::thread::sleep(Duration::from_millis(150));
+                            wrote = try_send(g.as_mut(), to_send.as_bytes());
                         }
                     }
-                    KeyCode::Esc => input.clear(),
-                    _ => {}
+                    if wrote {
+                        tx_bytes.fetch_add(to_send.len() as u64, Ordering::Relaxed);
+                        let _ = log_tx.send(format!("> {}", input.as_str()));
+                        last_sent = Some(to_send.as_bytes().to_vec());
+                    }
+                    input.clear();
+                }
+                Some(Action::ClearInput) => input.clear(),
+                Some(Action::ControlPrefix) => control_pending = true,
+                Some(Action::ToggleWrap) => {
+                    wrap = !wrap;
+                    hscroll = 0;
                 }
+                Some(Action::CycleSmoothing) => {
+                    let tau = next_smoothing(avg_in.tau_secs());
+                    avg_in = ThroughputAverager::new(tau);
+                    avg_out = ThroughputAverager::new(tau);
+                }
+                // Unbound keys edit the input line
+                _ => match k.code {
+                    KeyCode::Left if shifted && !wrap => {
+                        hscroll = hscroll.saturating_sub(HSCROLL_STEP)
+                    }
+                    KeyCode::Right if shifted && !wrap => {
+                        let max = max_hscroll(logs.iter().map(String::as_str), msg_cols);
+                        hscroll = (hscroll + HSCROLL_STEP).min(max);
+                    }
+                    KeyCode::Left => input.left(),
+                    KeyCode::Right => input.right(),
+                    KeyCode::Home => input.home(),
+                    KeyCode::End => input.end(),
+                    KeyCode::Char(c) => input.insert(c),
+                    KeyCode::Backspace => input.backspace(),
+                    KeyCode::Delete => input.delete(),
+                    _ => {}
+                },
             }
         }
     }
 
     stop.store(true, Ordering::Relaxed);
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    )?;
     terminal.show_cursor()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn send_file_streams_every_byte() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        client.set_nonblocking(true).unwrap();
+        let (mut peer, _) = server.accept().unwrap();
+
+        let path = std::env::temp_dir().join(format!("sergw-send-{}.bin", std::process::id()));
+        let blob: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&path, &blob).unwrap();
+
+        let stream = Mutex::new(Box::new(client));
+        let sent = AtomicU64::new(0);
+        let (log_tx, log_rx) = channel::unbounded();
+        send_file(
+            &path,
+            &stream,
+            None,
+            &sent,
+            &log_tx,
+            &AtomicBool::new(false),
+        );
+        let _ = std::fs::remove_file(&path);
+
+        let mut got = vec![0u8; blob.len()];
+        peer.read_exact(&mut got).unwrap();
+        assert_eq!(got, blob);
+        assert_eq!(sent.load(Ordering::Relaxed), 5000);
+        let logs: Vec<String> = log_rx.try_iter().collect();
+        assert!(logs.last().unwrap().starts_with("* sent "));
+        assert!(logs.iter().any(|l| l.contains("(40%)")));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn serial_endpoint_round_trip() {
+        use clap::Parser;
+        use nix::pty::{openpty, Winsize};
+        use std::fs::File;
+        use std::os::fd::AsRawFd;
+
+        let pty = openpty(None::<&Winsize>, None).unwrap();
+        let path = std::fs::read_link(format!("/proc/self/fd/{}", pty.slave.as_raw_fd())).unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let cli = crate::cli::Cli::parse_from(["sergw", "mock", "listener", "--serial", &path]);
+        let Some(crate::cli::Commands::Mock {
+            cmd: crate::cli::MockCmd::Listener { chat },
+        }) = cli.command
+        else {
+            panic!("expected mock listener");
+        };
+        let mut port = Endpoint::Serial(path).connect(&chat);
+        let mut master = File::from(pty.master);
+        master.write_all(b"hi").unwrap();
+        let mut buf = [0u8; 2];
+        port.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hi");
+        port.write_all(b"ok").unwrap();
+        master.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ok");
+    }
+
+    #[test]
+    fn paced_send_can_be_stopped() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        let _peer = server.accept().unwrap();
+        let path = std::env::temp_dir().join(format!("sergw-paced-{}.bin", std::process::id()));
+        std::fs::write(&path, vec![0u8; 3 * SEND_CHUNK]).unwrap();
+
+        let stop = AtomicBool::new(false);
+        let (log_tx, log_rx) = channel::unbounded();
+        let started = Instant::now();
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(200));
+                stop.store(true, Ordering::Relaxed);
+            });
+            // one byte a second: the second chunk would take about 17 minutes
+            send_file(
+                &path,
+                &Mutex::new(Box::new(client)),
+                Some(1),
+                &AtomicU64::new(0),
+                &log_tx,
+                &stop,
+            );
+        });
+        let _ = std::fs::remove_file(&path);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let logs: Vec<String> = log_rx.try_iter().collect();
+        assert!(logs
+            .last()
+            .unwrap()
+            .contains("stopped after 1024/3072 bytes"));
+    }
+
+    #[test]
+    fn missing_file_is_reported() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        let (log_tx, log_rx) = channel::unbounded();
+        send_file(
+            Path::new("/nonexistent/sergw.bin"),
+            &Mutex::new(Box::new(client)),
+            None,
+            &AtomicU64::new(0),
+            &log_tx,
+            &AtomicBool::new(false),
+        );
+        assert!(log_rx.recv().unwrap().starts_with("! /send"));
+    }
+}
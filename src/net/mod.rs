@@ -1,2 +1,15 @@
+pub mod affix;
+pub mod auxiliary;
+pub mod commands;
+pub mod device;
+pub mod events;
+pub mod frame;
+pub mod hello;
 pub mod listener;
+pub mod resolve;
+#[cfg(feature = "rfc2217")]
+pub mod rfc2217;
 pub mod server;
+pub mod splitlog;
+pub mod tap;
+pub mod tee;
@@ -0,0 +1,98 @@
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::cli::IpFamily;
+
+/// Resolve `host:port`, accepting literals (`[::1]:5656`) and names (`localhost:5656`).
+/// When a name yields several addresses, the preferred family wins if present.
+pub fn resolve_host(host: &str, prefer: IpFamily) -> Result<SocketAddr> {
+    if let Ok(addr) = host.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+    let addrs: Vec<SocketAddr> = host
+        .to_socket_addrs()
+        .with_context(|| format!("Resolving host '{host}' (expected HOST:PORT)"))?
+        .collect();
+    pick_preferred(&addrs, prefer).ok_or_else(|| anyhow!("Host '{host}' resolved to no addresses"))
+}
+
+fn pick_preferred(addrs: &[SocketAddr], prefer: IpFamily) -> Option<SocketAddr> {
+    let wanted = |a: &&SocketAddr| match prefer {
+        IpFamily::V4 => a.is_ipv4(),
+        IpFamily::V6 => a.is_ipv6(),
+    };
+    addrs.iter().find(wanted).or_else(|| addrs.first()).copied()
+}
+
+/// Host name registered for `ip` (PTR record or hosts file), if any. This can
+/// block for as long as the system resolver takes, so keep it off the accept path.
+#[cfg(unix)]
+pub fn reverse_lookup(ip: IpAddr) -> Option<String> {
+    use std::ffi::CStr;
+
+    let addr = socket2::SockAddr::from(SocketAddr::new(ip, 0));
+    // NI_MAXHOST
+    let mut host = [0 as libc::c_char; 1025];
+    // SAFETY: `addr` is a valid sockaddr of `addr.len()` bytes and `host` is
+    // writable for its whole length; getnameinfo NUL-terminates on success.
+    let rc = unsafe {
+        libc::getnameinfo(
+            addr.as_ptr(),
+            addr.len(),
+            host.as_mut_ptr(),
+            host.len() as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    if rc != 0 {
+        return None;
+    }
+    // SAFETY: see above
+    let name = unsafe { CStr::from_ptr(host.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(not(unix))]
+pub fn reverse_lookup(_ip: IpAddr) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_fast_path() {
+        let v6 = resolve_host("[::1]:5656", IpFamily::V4).unwrap();
+        assert_eq!(v6, "[::1]:5656".parse().unwrap());
+        let v4 = resolve_host("127.0.0.1:1", IpFamily::V6).unwrap();
+        assert_eq!(v4, "127.0.0.1:1".parse().unwrap());
+    }
+
+    #[test]
+    fn preference_with_fallback() {
+        let v4: SocketAddr = "127.0.0.1:1".parse().unwrap();
+        let v6: SocketAddr = "[::1]:1".parse().unwrap();
+        assert_eq!(pick_preferred(&[v4, v6], IpFamily::V6), Some(v6));
+        assert_eq!(pick_preferred(&[v6, v4], IpFamily::V4), Some(v4));
+        assert_eq!(pick_preferred(&[v6], IpFamily::V4), Some(v6));
+        assert_eq!(pick_preferred(&[], IpFamily::V4), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn reverse_lookup_never_echoes_the_address() {
+        // NI_NAMEREQD: no name is None, not the numeric form
+        let ip: IpAddr = "127.0.0.1".parse().unwrap();
+        assert_ne!(reverse_lookup(ip).as_deref(), Some("127.0.0.1"));
+    }
+
+    #[test]
+    fn missing_port_is_an_error() {
+        let err = resolve_host("localhost", IpFamily::V4).unwrap_err();
+        assert!(err.to_string().contains("localhost"));
+    }
+}
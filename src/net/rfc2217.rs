@@ -0,0 +1,404 @@
+// RFC 2217 (Telnet COM-PORT-OPTION) client: the device is a serial port on a
+// remote device server. Line settings are sent as COM-PORT-OPTION
+// subnegotiations, data is Telnet-escaped, and the server's option
+// negotiation is answered conservatively.
+
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tracing::debug;
+
+use crate::cli::{DataBitsOpt, LineSettings, Listen, ParityOpt, StopBitsOpt};
+use crate::net::device::{DeviceHandle, DeviceIo, UpstreamStream};
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+
+const OPT_BINARY: u8 = 0;
+const OPT_SGA: u8 = 3;
+const COM_PORT_OPTION: u8 = 44;
+
+// Client-to-server COM-PORT-OPTION commands; server replies add 100
+const SET_BAUDRATE: u8 = 1;
+const SET_DATASIZE: u8 = 2;
+const SET_PARITY: u8 = 3;
+const SET_STOPSIZE: u8 = 4;
+const SET_CONTROL: u8 = 5;
+const SERVER_OFFSET: u8 = 100;
+
+// Longest subnegotiation we keep; anything longer is garbage and is dropped
+const MAX_SB: usize = 64;
+
+fn subnegotiation(out: &mut Vec<u8>, command: u8, value: &[u8]) {
+    out.extend_from_slice(&[IAC, SB, COM_PORT_OPTION, command]);
+    out.extend_from_slice(&escape(value));
+    out.extend_from_slice(&[IAC, SE]);
+}
+
+/// Opening bytes: offer binary mode and COM-PORT-OPTION, then set the line
+pub fn negotiation(baud: u32, line: &LineSettings) -> Vec<u8> {
+    let mut out = vec![
+        IAC,
+        WILL,
+        COM_PORT_OPTION,
+        IAC,
+        WILL,
+        OPT_BINARY,
+        IAC,
+        DO,
+        OPT_BINARY,
+        IAC,
+        WILL,
+        OPT_SGA,
+        IAC,
+        DO,
+        OPT_SGA,
+    ];
+    let data_size = match line.data_bits {
+        DataBitsOpt::Five => 5,
+        DataBitsOpt::Six => 6,
+        DataBitsOpt::Seven => 7,
+        DataBitsOpt::Eight => 8,
+    };
+    let parity = match line.parity {
+        ParityOpt::None => 1,
+        ParityOpt::Odd => 2,
+        ParityOpt::Even => 3,
+        ParityOpt::Mark => 4,
+        ParityOpt::Space => 5,
+    };
+    let stop_size = match line.stop_bits {
+        StopBitsOpt::One => 1,
+        StopBitsOpt::Two => 2,
+    };
+    subnegotiation(&mut out, SET_BAUDRATE, &baud.to_be_bytes());
+    subnegotiation(&mut out, SET_DATASIZE, &[data_size]);
+    subnegotiation(&mut out, SET_PARITY, &[parity]);
+    subnegotiation(&mut out, SET_STOPSIZE, &[stop_size]);
+    // 1 = no flow control
+    subnegotiation(&mut out, SET_CONTROL, &[1]);
+    out
+}
+
+/// Double every IAC so data bytes are never read as commands
+pub fn escape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for &b in data {
+        out.push(b);
+        if b == IAC {
+            out.push(IAC);
+        }
+    }
+    out
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum State {
+    #[default]
+    Data,
+    Iac,
+    Option(u8),
+    Sub,
+    SubIac,
+}
+
+/// Splits the server's byte stream into serial data and Telnet commands.
+#[derive(Default)]
+pub struct TelnetDecoder {
+    state: State,
+    sub: Vec<u8>,
+}
+
+impl TelnetDecoder {
+    /// Appends serial data to `data` and any negotiation answers to `replies`
+    pub fn feed(&mut self, input: &[u8], data: &mut Vec<u8>, replies: &mut Vec<u8>) {
+        for &b in input {
+            self.state = match (self.state, b) {
+                (State::Data, IAC) => State::Iac,
+                (State::Data, _) => {
+                    data.push(b);
+                    State::Data
+                }
+                (State::Iac, IAC) => {
+                    data.push(IAC);
+                    State::Data
+                }
+                (State::Iac, DO | DONT | WILL | WONT) => State::Option(b),
+                (State::Iac, SB) => {
+                    self.sub.clear();
+                    State::Sub
+                }
+                // NOP, GA, break and friends carry no data
+                (State::Iac, _) => State::Data,
+                (State::Option(cmd), opt) => {
+                    answer(cmd, opt, replies);
+                    State::Data
+                }
+                (State::Sub, IAC) => State::SubIac,
+                (State::Sub, _) => {
+                    if self.sub.len() < MAX_SB {
+                        self.sub.push(b);
+                    }
+                    State::Sub
+                }
+                (State::SubIac, IAC) => {
+                    if self.sub.len() < MAX_SB {
+                        self.sub.push(IAC);
+                    }
+                    State::Sub
+                }
+                (State::SubIac, SE) => {
+                    log_subnegotiation(&self.sub);
+                    State::Data
+                }
+                (State::SubIac, _) => State::Data,
+            };
+        }
+    }
+}
+
+// Agree to what we offered, refuse everything else; never answer a refusal
+fn answer(cmd: u8, opt: u8, replies: &mut Vec<u8>) {
+    let reply = match cmd {
+        DO if matches!(opt, OPT_BINARY | OPT_SGA | COM_PORT_OPTION) => None,
+        DO => Some(WONT),
+        WILL if matches!(opt, OPT_BINARY | OPT_SGA) => None,
+        WILL => Some(DONT),
+        _ => None,
+    };
+    if let Some(reply) = reply {
+        replies.extend_from_slice(&[IAC, reply, opt]);
+    }
+}
+
+fn log_subnegotiation(sub: &[u8]) {
+    match sub {
+        [COM_PORT_OPTION, cmd, value @ ..] if *cmd > SERVER_OFFSET => {
+            let what = match cmd - SERVER_OFFSET {
+                SET_BAUDRATE if value.len() == 4 => {
+                    let baud = u32::from_be_bytes([value[0], value[1], value[2], value[3]]);
+                    format!("baud rate {baud}")
+                }
+                SET_DATASIZE => format!("data size {value:?}"),
+                SET_PARITY => format!("parity {value:?}"),
+                SET_STOPSIZE => format!("stop size {value:?}"),
+                SET_CONTROL => format!("control {value:?}"),
+                other => format!("command {other} {value:?}"),
+            };
+            debug!("RFC2217 server confirmed {what}");
+        }
+        _ => debug!(?sub, "Ignoring Telnet subnegotiation"),
+    }
+}
+
+struct Rfc2217Reader {
+    inner: UpstreamStream,
+    decoder: TelnetDecoder,
+    writer: Arc<Mutex<TcpStream>>,
+}
+
+impl Read for Rfc2217Reader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // Decoded data is never longer than the raw bytes, so it always fits
+        let mut raw = vec![0u8; buf.len()];
+        loop {
+            let n = self.inner.read(&mut raw)?;
+            let mut data = Vec::with_capacity(n);
+            let mut replies = Vec::new();
+            self.decoder.feed(&raw[..n], &mut data, &mut replies);
+            if !replies.is_empty() {
+                let mut w = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+                w.write_all(&replies)?;
+            }
+            // A read of nothing but Telnet commands is not end of stream
+            if !data.is_empty() || n == 0 {
+                buf[..data.len()].copy_from_slice(&data);
+                return Ok(data.len());
+            }
+        }
+    }
+}
+
+impl Write for Rfc2217Reader {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl DeviceIo for Rfc2217Reader {
+    fn read_timeout(&self) -> Option<Duration> {
+        self.inner.read_timeout()
+    }
+
+    fn set_read_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.inner.set_read_timeout(timeout)
+    }
+}
+
+struct Rfc2217Writer {
+    stream: Arc<Mutex<TcpStream>>,
+}
+
+impl Read for Rfc2217Writer {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+impl Write for Rfc2217Writer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut s = self.stream.lock().unwrap_or_else(|e| e.into_inner());
+        s.write_all(&escape(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .flush()
+    }
+}
+
+impl DeviceIo for Rfc2217Writer {}
+
+/// Connect, send the line settings and hand back reader/writer handles
+pub fn open_pair(addr: SocketAddr, listen: &Listen) -> Result<(DeviceHandle, DeviceHandle)> {
+    let stream = UpstreamStream::connect(addr)?;
+    let mut writer = stream
+        .0
+        .try_clone()
+        .with_context(|| format!("Cloning RFC2217 stream {addr} for writer"))?;
+    writer
+        .write_all(&negotiation(listen.baud(), &listen.line))
+        .with_context(|| format!("Sending RFC2217 line settings to {addr}"))?;
+    let writer = Arc::new(Mutex::new(writer));
+    Ok((
+        Box::new(Rfc2217Reader {
+            inner: stream,
+            decoder: TelnetDecoder::default(),
+            writer: Arc::clone(&writer),
+        }),
+        Box::new(Rfc2217Writer { stream: writer }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line_7e2() -> LineSettings {
+        LineSettings {
+            data_bits: DataBitsOpt::Seven,
+            parity: ParityOpt::Even,
+            stop_bits: StopBitsOpt::Two,
+        }
+    }
+
+    #[test]
+    fn negotiation_sets_baud_and_line() {
+        let bytes = negotiation(9600, &line_7e2());
+        assert!(bytes.starts_with(&[IAC, WILL, COM_PORT_OPTION]));
+        let find = |needle: &[u8]| bytes.windows(needle.len()).any(|w| w == needle);
+        assert!(find(&[IAC, SB, 44, 1, 0, 0, 0x25, 0x80, IAC, SE]));
+        assert!(find(&[IAC, SB, 44, 2, 7, IAC, SE]));
+        assert!(find(&[IAC, SB, 44, 3, 3, IAC, SE]));
+        assert!(find(&[IAC, SB, 44, 4, 2, IAC, SE]));
+    }
+
+    #[test]
+    fn baud_containing_iac_is_escaped() {
+        // 0x0000FF00 = 65280
+        let bytes = negotiation(65_280, &line_7e2());
+        let needle = [IAC, SB, 44, 1, 0, 0, IAC, IAC, 0, IAC, SE];
+        assert!(bytes.windows(needle.len()).any(|w| w == needle));
+    }
+
+    #[test]
+    fn decoder_separates_data_and_commands() {
+        let mut dec = TelnetDecoder::default();
+        let (mut data, mut replies) = (Vec::new(), Vec::new());
+        // data, escaped 0xFF, server DO ECHO (refused), confirmation SB, more data
+        let input = [
+            b'a', IAC, IAC, IAC, DO, 1, IAC, SB, 44, 101, 0, 0, 0x25, 0x80, IAC, SE, b'b',
+        ];
+        // split mid-command to exercise state carried between reads
+        dec.feed(&input[..4], &mut data, &mut replies);
+        dec.feed(&input[4..], &mut data, &mut replies);
+        assert_eq!(data, [b'a', IAC, b'b']);
+        assert_eq!(replies, [IAC, WONT, 1]);
+
+        // agreed options get no answer, so negotiation can't loop
+        let mut replies = Vec::new();
+        dec.feed(
+            &[IAC, DO, COM_PORT_OPTION, IAC, WILL, OPT_BINARY],
+            &mut data,
+            &mut replies,
+        );
+        assert!(replies.is_empty());
+    }
+
+    #[test]
+    fn bridges_through_a_device_server() {
+        use crate::cli::{Cli, Commands};
+        use clap::Parser;
+        use std::net::TcpListener;
+
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        let cli = Cli::parse_from([
+            "sergw",
+            "listen",
+            "--upstream-rfc2217",
+            &addr.to_string(),
+            "--baud",
+            "9600",
+        ]);
+        let Some(Commands::Listen(listen)) = cli.command else {
+            panic!("expected listen");
+        };
+        let (mut reader, mut writer) = open_pair(addr, &listen).unwrap();
+        let (mut peer, _) = server.accept().unwrap();
+
+        let expected = negotiation(9600, &listen.line);
+        let mut got = vec![0u8; expected.len()];
+        peer.read_exact(&mut got).unwrap();
+        assert_eq!(got, expected);
+
+        writer.write_all(&[b'x', IAC]).unwrap();
+        let mut got = [0u8; 3];
+        peer.read_exact(&mut got).unwrap();
+        assert_eq!(got, [b'x', IAC, IAC]);
+
+        // A command-only segment first: the reader must keep going, not report EOF
+        peer.write_all(&[IAC, WILL, 1]).unwrap();
+        peer.write_all(b"ok").unwrap();
+        let mut buf = [0u8; 16];
+        let mut data = Vec::new();
+        while data.len() < 2 {
+            let n = reader.read(&mut buf).unwrap();
+            data.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(data, b"ok");
+        let mut refusal = [0u8; 3];
+        peer.read_exact(&mut refusal).unwrap();
+        assert_eq!(refusal, [IAC, DONT, 1]);
+    }
+
+    #[test]
+    fn escape_doubles_iac() {
+        assert_eq!(escape(&[1, IAC, 2]), [1, IAC, IAC, 2]);
+    }
+}
@@ -1,23 +1,55 @@
 use std::io::{Read, Write};
-use std::net::TcpListener;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use std::thread;
-use std::time::Duration;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use bytes::Bytes;
 use crossbeam_channel as channel;
 use tracing::{info, warn};
 
-use crate::cli::Listen;
-use crate::serial::{configure_serial, select_serial_port};
+use crate::cli::{EventFormat, FrameMode, Listen, SerialFraming, SerialWritePolicy};
+use crate::net::affix::{Affixes, Envelopes};
+use crate::net::auxiliary::{self, AuxHandler, AuxListeners, EventFeed};
+use crate::net::commands::spawn_stdin_commands;
+use crate::net::device::{modem_report, DeviceHandle, DeviceIo, DeviceTarget};
+use crate::net::events;
+use crate::net::frame::{self, Deframer};
+use crate::net::hello::{Handshake, HelloLine};
+use crate::net::resolve::{resolve_host, reverse_lookup};
+use crate::net::splitlog::SplitLogs;
+use crate::net::tap::{spawn_tap, TapOptions};
+use crate::net::tee::Tee;
+use crate::rate::{RateWatch, TokenBucket};
+use crate::serial::autobaud::{choose_baud, sample_at};
+use crate::serial::cobs::{self, CobsDecoder};
+use crate::serial::lineerr::LineErrors;
+use crate::serial::noise::NoiseDetector;
+use crate::serial::{caps, configure_serial, select_serial_port};
 use crate::state::SharedState;
-use crate::ui::inspector::{DirectionTag, Sample};
-use crate::ui::overview::{run_tui, Counters};
+use crate::ui::demo::spawn_inspector_demo;
+use crate::ui::inspector::{offer_sample, DirectionTag, InspectorFilter, Sample};
+use crate::ui::keymap::{Keymap, Keymaps};
+use crate::ui::overview::{run_tui, Counters, TuiOptions};
 #[cfg(feature = "mdns")]
 use libmdns as _mdns;
 
+// Immediate empty serial reads in a row before the device counts as unplugged
+const EMPTY_READ_LIMIT: u32 = 20;
+// Sleep after an empty --nonblocking-serial read: bounds idle wakeups to ~200/s
+const NONBLOCKING_IDLE: Duration = Duration::from_millis(5);
+
+// Longest the serial writer waits for client data before re-checking stop and polls
+const WRITER_IDLE: Duration = Duration::from_millis(200);
+
+// Client reads block for at most this long so the reader still notices stop flags
+const CLIENT_READ_POLL: Duration = Duration::from_millis(200);
+
+// How often --wait-for-device notices a stop request between attempts
+const WAIT_POLL: Duration = Duration::from_millis(50);
+
 pub fn run_listen(listen: Listen) -> Result<()> {
     let stop_flag = Arc::new(AtomicBool::new(false));
     {
@@ -30,72 +62,362 @@ pub fn run_listen(listen: Listen) -> Result<()> {
     run_listen_with_shutdown(listen, stop_flag)
 }
 
-pub(crate) fn run_listen_with_shutdown(listen: Listen, stop_flag: Arc<AtomicBool>) -> Result<()> {
-    let serial_path = select_serial_port(&listen.serial)?;
-    info!(serial = %serial_path, baud = listen.baud, host = %listen.host, "Starting sergw");
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum StopReason {
+    Duration,
+    ByteLimit,
+    DeviceLost,
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::Duration => write!(f, "duration elapsed"),
+            StopReason::ByteLimit => write!(f, "byte limit reached"),
+            StopReason::DeviceLost => write!(f, "device disconnected"),
+        }
+    }
+}
+
+/// `--exit-on-disconnect` stopped the bridge because the device went away
+#[derive(Debug, thiserror::Error)]
+#[error("{0} disconnected")]
+pub(crate) struct DeviceDisconnected(String);
+
+// Records why we are stopping; the first reason wins.
+fn request_stop(stop_flag: &AtomicBool, slot: &Mutex<Option<StopReason>>, reason: StopReason) {
+    let mut guard = slot.lock().unwrap_or_else(|e| e.into_inner());
+    if guard.is_none() {
+        *guard = Some(reason);
+    }
+    stop_flag.store(true, Ordering::Relaxed);
+}
+
+/// `None` disables the reporter. Unset means every 5s headless and off under the
+/// TUI, which already shows throughput.
+fn metrics_interval(flag: Option<u64>, headless: bool) -> Option<Duration> {
+    match flag {
+        Some(0) => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None if headless => Some(Duration::from_secs(5)),
+        None => None,
+    }
+}
+
+fn capture_limit_reached(
+    elapsed: Duration,
+    bytes: u64,
+    duration: Option<Duration>,
+    max_bytes: Option<u64>,
+) -> Option<StopReason> {
+    if max_bytes.is_some_and(|max| bytes >= max) {
+        return Some(StopReason::ByteLimit);
+    }
+    if duration.is_some_and(|d| elapsed >= d) {
+        return Some(StopReason::Duration);
+    }
+    None
+}
+
+pub(crate) fn run_listen_with_shutdown(
+    mut listen: Listen,
+    stop_flag: Arc<AtomicBool>,
+) -> Result<()> {
+    let started = Instant::now();
+    let stop_reason: Arc<Mutex<Option<StopReason>>> = Arc::new(Mutex::new(None));
+    // with --wait-for-device an adapter that has not enumerated yet is waited for
+    let target = wait_for_device(&listen, &stop_flag, &wanted_device(&listen), || {
+        device_target(&listen)
+    })?;
+    let hosts = listen
+        .host
+        .iter()
+        .map(|h| resolve_host(h, listen.prefer_ip))
+        .collect::<Result<Vec<_>>>()?;
+    // raw TCP would merge and split decoded packets at random
+    if listen.framing == SerialFraming::Cobs && listen.frame != FrameMode::LengthPrefix {
+        bail!("--framing cobs keeps packet boundaries only over --frame length-prefix; add it");
+    }
+    if listen.tee.is_some() {
+        if listen.wants_tui() {
+            bail!("--tee writes to stdout, which the TUI draws on; add --no-tui");
+        }
+        if listen.event_format == EventFormat::Json {
+            bail!("--tee and --event-format json would both write to stdout; use one of them");
+        }
+    }
+    // Load before any thread starts (and before a dry run's verdict) so a broken
+    // keymap or script fails the launch cleanly
+    let keymap = if listen.wants_tui() {
+        Keymaps::load(listen.keymap.as_deref())?.gateway
+    } else {
+        Keymap::gateway_defaults()
+    };
+    let on_connect = listen
+        .on_connect_script
+        .as_deref()
+        .map(|path| {
+            std::fs::read(path)
+                .map(Bytes::from)
+                .with_context(|| format!("Reading on-connect script {}", path.display()))
+        })
+        .transpose()?;
+    if listen.dry_run {
+        return dry_run(&target, &hosts, &listen);
+    }
+    // opens the port, so never as part of a dry run
+    if listen.show_caps {
+        show_caps(&target, &listen)?;
+    }
+    info!(device = %target, baud = listen.baud(), ?hosts, "Starting sergw");
+    let split_logs = listen
+        .split_logs
+        .as_deref()
+        .map(SplitLogs::new)
+        .transpose()?
+        .map(Arc::new);
     let (status_tx, status_rx) = channel::unbounded::<String>();
     let status_tx_reader = status_tx.clone();
     let status_tx_writer = status_tx.clone();
 
-    // Open serial with auto-reconnect loop for writer and reader handles
-    let (mut serial_port, mut serial_writer_port) = open_serial_pair(&serial_path, &listen)?;
+    // --baud with several rates: settle on one before the device is opened for good
+    if listen.baud_rates.len() > 1 {
+        let message = match &target {
+            DeviceTarget::Serial(path) => {
+                let choice = choose_baud(&listen.baud_rates, &stop_flag, |baud| {
+                    sample_at(path, baud, &listen, &stop_flag)
+                });
+                if stop_flag.load(Ordering::Relaxed) {
+                    bail!("Stopped while trying --baud rates");
+                }
+                listen.set_baud(choice.baud);
+                choice.describe(&listen.baud_rates)
+            }
+            _ => format!(
+                "a --baud list is only tried on a local port; using {}",
+                listen.baud()
+            ),
+        };
+        let _ = status_tx.send(format!("{}: {message}", target.kind()));
+    }
+
+    // Open the device with auto-reconnect loop for writer and reader handles
+    let (mut serial_port, mut serial_writer_port) =
+        wait_for_device(&listen, &stop_flag, &target.to_string(), || {
+            target.open_pair(&listen)
+        })?;
+    if listen.dtr.is_some() || listen.rts.is_some() {
+        if let Some(port) = serial_port.as_serial() {
+            let report = modem_report(&listen, port);
+            let _ = status_tx.send(format!("{}: {report}", target.kind()));
+        }
+    }
+
+    // TCP acceptors, bound before the TUI starts so it can show the addresses
+    let mut listeners = Vec::with_capacity(hosts.len());
+    let mut bound = Vec::with_capacity(hosts.len());
+    for &host in &hosts {
+        let listener = bind_listener(host, listen.reuse_addr, listen.bind_retries)
+            .with_context(|| format!("Binding TCP listener at {host}"))?;
+        listener
+            .set_nonblocking(true)
+            .context("Setting TCP listener non-blocking mode")?;
+        // differs from --host when it asked for port 0; headless, the event is
+        // logged at info (or printed as JSON)
+        let local = listener.local_addr().unwrap_or(host);
+        let _ = status_tx.send(format!("Listening: {local}"));
+        bound.push(local);
+        listeners.push(listener);
+    }
+    let health_listener = listen
+        .health_addr
+        .map(|addr| auxiliary::bind("health", addr))
+        .transpose()?;
+    let event_listener = listen
+        .event_addr
+        .map(|addr| auxiliary::bind("events", addr))
+        .transpose()?;
 
     // Channels
     // - to_serial_rx: buffers from TCP -> serial writer
     let (to_serial_tx, to_serial_rx) = channel::bounded::<Bytes>(listen.buffer);
 
+    // - --tap: copies of broadcast frames for the tap connection
+    let (tap_tx, tap_rx) = match listen.tap {
+        Some(_) => {
+            let (tx, rx) = channel::bounded::<Bytes>(listen.buffer);
+            (Some(tx), Some(rx))
+        }
+        None => (None, None),
+    };
+
     // - shared state for broadcasting serial -> TCP
-    let shared_state = Arc::new(SharedState::new());
+    let shared_state = Arc::new(
+        SharedState::new()
+            .with_scrollback(listen.scrollback)
+            .with_tcp_rate(listen.tcp_rate)
+            .with_tap(tap_tx)
+            .with_single_controller(listen.single_controller),
+    );
     let counters = Arc::new(Counters::default());
+    counters.serial_connected.store(true, Ordering::Relaxed);
+    counters.baud.store(listen.baud(), Ordering::Relaxed);
     let (event_tx_base, event_rx) = channel::unbounded::<String>();
     let event_tx = Some(event_tx_base);
 
+    // On-demand state snapshot (SIGUSR1) for headless deployments; no-op off Unix
+    #[cfg(unix)]
+    {
+        let dump_requested = Arc::new(AtomicBool::new(false));
+        if let Err(e) =
+            signal_hook::flag::register(signal_hook::consts::SIGUSR1, dump_requested.clone())
+        {
+            warn!(?e, "Failed to install SIGUSR1 handler");
+        }
+        let shared_for_dump = Arc::clone(&shared_state);
+        let counters_for_dump = Arc::clone(&counters);
+        let stop_for_dump = stop_flag.clone();
+        let event_tx_dump = event_tx.clone();
+        thread::spawn(move || {
+            while !stop_for_dump.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(200));
+                if dump_requested.swap(false, Ordering::Relaxed) {
+                    let summary = log_state_snapshot(started, &counters_for_dump, &shared_for_dump);
+                    if let Some(tx) = &event_tx_dump {
+                        let _ = tx.send(summary);
+                    }
+                }
+            }
+        });
+    }
+
     // TUI thread(s)
     let shared_for_tui = Arc::clone(&shared_state);
     let counters_for_tui = Arc::clone(&counters);
     let stop_for_tui = stop_flag.clone();
+    let tui_options = TuiOptions {
+        keymap,
+        perspective: listen.throughput_perspective,
+        inject: to_serial_tx.clone(),
+        title: listen.terminal_title.then(|| target.short_name()),
+        device: target.label(),
+        hex_columns: listen.inspector_hex_columns.map(usize::from),
+        listening: bound.clone(),
+    };
     // Inspector UI: channel
-    let (insp_tx, insp_rx) = channel::bounded::<Sample>(1024);
+    let (insp_tx, insp_rx) = channel::bounded::<Sample>(listen.inspector_buffer as usize);
+    let insp_filter = Arc::new(InspectorFilter::default());
+    let insp_filter_for_tui = Arc::clone(&insp_filter);
     let status_rx_tui = status_rx.clone();
-    let tui_handle = Some(thread::spawn(move || {
-        // Merge status messages into events
-        let (tx, merged_rx) = channel::unbounded::<String>();
-        std::thread::spawn(move || loop {
-            crossbeam_channel::select! {
-                recv(event_rx) -> msg => if let Ok(m)=msg { let _=tx.send(m); } else { break; },
-                recv(status_rx_tui) -> msg => if let Ok(m)=msg { let _=tx.send(m); } else { break; },
-            }
-        });
-        let _ = run_tui(
-            shared_for_tui,
-            counters_for_tui,
-            merged_rx,
-            insp_rx,
-            stop_for_tui,
+    // --event-addr subscribers see everything the events pane/log does
+    let event_feed = listen.event_addr.map(|_| Arc::new(EventFeed::default()));
+    let feed_for_tui = event_feed.clone();
+    let event_format = listen.event_format;
+    let publish = move |m: &str| {
+        if let Some(feed) = &feed_for_tui {
+            feed.publish(&events::format_event(event_format, m, SystemTime::now()));
+        }
+    };
+    let headless = !listen.wants_tui();
+    if headless && !listen.no_tui && !listen.stdin_commands {
+        eprintln!(
+            "stdout is not a terminal; running headless (pass --no-tui to silence this notice)"
         );
-    }));
+    }
+    let tui_handle = if headless {
+        let stop_for_log = stop_flag.clone();
+        // JSON events go to stdout on their own so they can be piped apart from the logs
+        let log_event = move |m: &str| match event_format {
+            EventFormat::Text => info!("{m}"),
+            EventFormat::Json => {
+                println!(
+                    "{}",
+                    events::format_event(event_format, m, SystemTime::now())
+                )
+            }
+        };
+        Some(thread::spawn(move || {
+            // No TUI: surface events and status through the log instead
+            while !stop_for_log.load(Ordering::Relaxed) {
+                crossbeam_channel::select! {
+                    recv(event_rx) -> msg => if let Ok(m) = msg { publish(&m); log_event(&m); } else { break; },
+                    recv(status_rx_tui) -> msg => if let Ok(m) = msg { publish(&m); log_event(&m); } else { break; },
+                    default(Duration::from_millis(200)) => {}
+                }
+            }
+            Ok(())
+        }))
+    } else {
+        Some(thread::spawn(move || {
+            // Merge status messages into events
+            let (tx, merged_rx) = channel::unbounded::<String>();
+            std::thread::spawn(move || loop {
+                crossbeam_channel::select! {
+                    recv(event_rx) -> msg => if let Ok(m)=msg { publish(&m); let _=tx.send(m); } else { break; },
+                    recv(status_rx_tui) -> msg => if let Ok(m)=msg { publish(&m); let _=tx.send(m); } else { break; },
+                }
+            });
+            let stop_on_error = stop_for_tui.clone();
+            let res = run_tui(
+                shared_for_tui,
+                counters_for_tui,
+                merged_rx,
+                insp_rx,
+                insp_filter_for_tui,
+                stop_for_tui,
+                tui_options,
+            );
+            // without a UI there is no way to quit, so don't keep bridging blind;
+            // the error is returned once everything has shut down
+            if res.is_err() {
+                stop_on_error.store(true, Ordering::Relaxed);
+            }
+            res.context("TUI failed; re-run with --no-tui to bridge without it")
+        }))
+    };
 
     // Inspector receiver is moved into the TUI above; keep tx for sampling below
 
-    // Metrics reporter (always on; logs to info every 5 seconds)
-    {
+    // Capture bounds supervisor: stop on --duration or --stop-after-bytes, whichever first
+    if listen.duration.is_some() || listen.stop_after_bytes.is_some() {
+        let counters_for_limits = Arc::clone(&counters);
+        let stop_for_limits = stop_flag.clone();
+        let reason_for_limits = Arc::clone(&stop_reason);
+        let duration = listen.duration.map(Duration::from_secs);
+        let max_bytes = listen.stop_after_bytes;
+        thread::spawn(move || {
+            while !stop_for_limits.load(Ordering::Relaxed) {
+                let bytes = counters_for_limits.bytes_out.load(Ordering::Relaxed);
+                if let Some(reason) =
+                    capture_limit_reached(started.elapsed(), bytes, duration, max_bytes)
+                {
+                    request_stop(&stop_for_limits, &reason_for_limits, reason);
+                    break;
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        });
+    }
+
+    // Metrics reporter: periodic throughput log line (off under the TUI unless asked for)
+    if let Some(interval) = metrics_interval(listen.metrics_interval, headless) {
         let counters_for_metrics = Arc::clone(&counters);
         let stop_for_metrics = stop_flag.clone();
         std::thread::spawn(move || {
             let mut last_in: u64 = 0; // bytes_in (TCP -> serial)
             let mut last_out: u64 = 0; // bytes_out (serial -> TCP)
-            let mut last = std::time::Instant::now();
-            while !stop_for_metrics.load(std::sync::atomic::Ordering::Relaxed) {
-                std::thread::sleep(std::time::Duration::from_secs(5));
-                let now = std::time::Instant::now();
+            let mut last = Instant::now();
+            while !stop_for_metrics.load(Ordering::Relaxed) {
+                // Tick often so shutdown isn't held up by a long interval
+                std::thread::sleep(Duration::from_millis(200));
+                let now = Instant::now();
+                if now.duration_since(last) < interval {
+                    continue;
+                }
                 let dt = now.duration_since(last).as_secs_f64().max(0.001);
                 last = now;
-                let bi = counters_for_metrics
-                    .bytes_in
-                    .load(std::sync::atomic::Ordering::Relaxed);
-                let bo = counters_for_metrics
-                    .bytes_out
-                    .load(std::sync::atomic::Ordering::Relaxed);
+                let bi = counters_for_metrics.bytes_in.load(Ordering::Relaxed);
+                let bo = counters_for_metrics.bytes_out.load(Ordering::Relaxed);
                 let outbound = ((bi - last_in) as f64 / dt) as u64; // to serial
                 let inbound = ((bo - last_out) as f64 / dt) as u64; // from serial
                 last_in = bi;
@@ -105,39 +427,266 @@ pub(crate) fn run_listen_with_shutdown(listen: Listen, stop_flag: Arc<AtomicBool
         });
     }
 
+    // Auxiliary listeners: each on its own address, all stopped by stop_flag
+    let mut aux = AuxListeners::new(stop_flag.clone());
+    if let Some(listener) = health_listener {
+        let counters_for_health = Arc::clone(&counters);
+        let handler: Arc<AuxHandler> = Arc::new(move |stream, _, _| {
+            let connected = counters_for_health.serial_connected.load(Ordering::Relaxed);
+            let response = auxiliary::health_response(connected, started.elapsed());
+            auxiliary::serve_health(stream, &response);
+        });
+        aux.spawn("health", listener, handler)?;
+    }
+    if let (Some(listener), Some(feed)) = (event_listener, event_feed) {
+        let handler: Arc<AuxHandler> =
+            Arc::new(move |stream, _, stop| auxiliary::serve_events(stream, &feed, stop));
+        aux.spawn("events", listener, handler)?;
+    }
+
+    let tap = match (&listen.tap, tap_rx) {
+        (Some(target), Some(rx)) => {
+            let options = TapOptions {
+                target: target.clone(),
+                prefer: listen.prefer_ip,
+                write_timeout: (listen.tcp_write_timeout > 0)
+                    .then(|| Duration::from_secs(listen.tcp_write_timeout)),
+            };
+            Some(spawn_tap(options, rx, stop_flag.clone(), status_tx.clone()))
+        }
+        _ => None,
+    };
+
+    if listen.stdin_commands {
+        spawn_stdin_commands(
+            to_serial_tx.clone(),
+            Arc::clone(&counters),
+            status_tx.clone(),
+        );
+    }
+
+    if listen.inspector_demo {
+        spawn_inspector_demo(
+            insp_tx.clone(),
+            Arc::clone(&insp_filter),
+            Arc::clone(&counters),
+            listen.inspector_sample_max,
+            stop_flag.clone(),
+        );
+    }
+
     // Serial reader thread: serial -> broadcast
     let shared_state_for_reader = Arc::clone(&shared_state);
     let stop_reader = stop_flag.clone();
-    let serial_path_for_reader = serial_path.clone();
-    let listen_for_reader = listen.clone();
+    let reason_for_reader = Arc::clone(&stop_reason);
+    let target_for_reader = target.clone();
+    let mut listen_for_reader = listen.clone();
     let counters_reader = Arc::clone(&counters);
     let insp_tx_reader = insp_tx.clone();
+    let insp_filter_reader = Arc::clone(&insp_filter);
+    let mut reconnect_reader = ReconnectNotifier::new(
+        format!("{}: reconnecting reader", target.kind()),
+        listen.reconnect_log_every,
+        headless,
+        status_tx_reader.clone(),
+    );
+    let coalesce = (listen.coalesce_ms > 0).then(|| Duration::from_millis(listen.coalesce_ms));
+    let client_grace = listen.disconnect_clients_after.map(Duration::from_secs);
+    let split_logs_reader = split_logs.clone();
+    let mut tee = listen.tee.map(Tee::stdout);
+    // An upstream relays bytes; only a local line or RFC 2217 port has a baud to get wrong
+    let mut noise = (!listen.no_baud_check && !matches!(target, DeviceTarget::Upstream(_)))
+        .then(NoiseDetector::default);
+    let device_name = target.short_name();
+    let strip = Affixes::new(listen.strip_prefix.clone(), listen.strip_suffix.clone());
+    let mut cobs_reader = (listen.framing == SerialFraming::Cobs).then(CobsDecoder::default);
+    // COBS packets are already whole; otherwise the envelope marks where one ends
+    let mut envelopes =
+        (cobs_reader.is_none() && !strip.is_empty()).then(|| Envelopes::new(strip.clone()));
+    // --close-when-idle, and reconnects of a shared upstream connection: the
+    // reader reopens the device and passes the writer its half (None: close yours)
+    let close_when_idle = listen.close_when_idle;
+    let (writer_port_tx, writer_port_rx) = channel::unbounded::<Option<DeviceHandle>>();
     let serial_reader = thread::spawn(move || -> Result<()> {
         let mut buffer = vec![0u8; 4096];
+        let mut last_modem_poll: Option<Instant> = None;
+        // driver totals at the last poll; errors are reported as they grow
+        let mut last_line_errors: Option<LineErrors> = None;
+        // start of the current device outage, for --disconnect-clients-after
+        let mut outage_since: Option<Instant> = None;
+        let mut empty_reads = EmptyReads::default();
+        let idle = || close_when_idle && shared_state_for_reader.tcp_connections.is_empty();
         loop {
+            empty_reads.reset();
+            let mut went_idle = false;
             while !stop_reader.load(Ordering::Relaxed) {
+                if idle() {
+                    went_idle = true;
+                    break;
+                }
+                // The reader owns the port, so it also samples the modem status lines
+                if last_modem_poll.map_or(true, |t| t.elapsed() >= Duration::from_millis(500)) {
+                    if let Some(port) = serial_port.as_serial() {
+                        counters_reader.modem.poll(port);
+                    }
+                    // Unsupported counters (a pty, most USB adapters) just stay n/a
+                    if let Some(Ok(now)) = serial_port.line_errors() {
+                        let new = last_line_errors
+                            .replace(now)
+                            .map_or_else(LineErrors::default, |before| now.since(&before));
+                        let kind = target_for_reader.kind();
+                        for event in new.events() {
+                            let _ = status_tx_reader.send(format!("{kind}: {event}"));
+                        }
+                        counters_reader
+                            .line_errors
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .get_or_insert_with(LineErrors::default)
+                            .add(&new);
+                    }
+                    last_modem_poll = Some(Instant::now());
+                }
+                let rate = counters_reader.baud_request.swap(0, Ordering::Relaxed);
+                if rate > 0 {
+                    let kind = target_for_reader.kind();
+                    let message = match serial_port.as_serial().map(|p| p.set_baud_rate(rate)) {
+                        Some(Ok(())) => {
+                            // kept for reconnects and the wrong-baud warning
+                            counters_reader.baud.store(rate, Ordering::Relaxed);
+                            listen_for_reader.set_baud(rate);
+                            format!("{kind}: baud rate now {rate}")
+                        }
+                        Some(Err(e)) => format!("{kind}: could not set baud rate {rate}: {e}"),
+                        None => format!("{kind}: baud rate can only be changed on a local port"),
+                    };
+                    let _ = status_tx_reader.send(message);
+                }
+                // Paused from the TUI: leave data in the device/driver buffers so
+                // its flow control kicks in; the port itself stays open
+                if counters_reader.serial_read_paused.load(Ordering::Relaxed) {
+                    thread::sleep(Duration::from_millis(50));
+                    continue;
+                }
+                let read_started = Instant::now();
                 match serial_port.read(&mut buffer) {
                     Ok(n) if n > 0 => {
+                        empty_reads.reset();
+                        counters_reader.last_error.clear();
+                        let n = match coalesce {
+                            Some(window) => {
+                                coalesce_reads(serial_port.as_mut(), &mut buffer, n, window)
+                            }
+                            None => n,
+                        };
                         counters_reader
                             .bytes_out
                             .fetch_add(n as u64, Ordering::Relaxed);
-                        let _ = insp_tx_reader.try_send(Sample {
-                            dir: DirectionTag::Inbound,
-                            data: Bytes::copy_from_slice(&buffer[..n]),
-                        });
-                        let bytes = Bytes::copy_from_slice(&buffer[..n]);
-                        shared_state_for_reader.broadcast(bytes);
+                        if insp_filter_reader.wants(&DirectionTag::Inbound) {
+                            offer_sample(
+                                &insp_tx_reader,
+                                Sample::capture(
+                                    DirectionTag::Inbound,
+                                    &buffer[..n],
+                                    listen_for_reader.inspector_sample_max,
+                                ),
+                                &counters_reader.inspector_dropped,
+                            );
+                        }
+                        if let Some(report) = noise.as_mut().and_then(|d| d.feed(&buffer[..n])) {
+                            let _ = status_tx_reader.send(format!(
+                                "{}: output looks like noise ({:.1} bits/byte, {:.0}% printable); check that --baud {} and the line settings match the device",
+                                target_for_reader.kind(),
+                                report.entropy,
+                                report.printable * 100.0,
+                                listen_for_reader.baud()
+                            ));
+                        }
+                        if let Some(logs) = &split_logs_reader {
+                            logs.device(&device_name, &buffer[..n]);
+                        }
+                        if let Some(tee) = tee.as_mut() {
+                            tee.write(&buffer[..n]);
+                        }
+                        // The inspector above still sees the device's own framing
+                        let packets = match (cobs_reader.as_mut(), envelopes.as_mut()) {
+                            (Some(decoder), _) => decoder.feed(&buffer[..n]),
+                            (None, Some(envelopes)) => {
+                                envelopes.feed(&buffer[..n]).into_iter().map(Ok).collect()
+                            }
+                            (None, None) => vec![Ok(Bytes::copy_from_slice(&buffer[..n]))],
+                        };
+                        for packet in packets {
+                            let packet = match packet {
+                                Ok(packet) => packet,
+                                Err(e) => {
+                                    let _ = status_tx_reader.send(format!(
+                                        "{}: dropped malformed COBS packet ({e})",
+                                        target_for_reader.kind()
+                                    ));
+                                    continue;
+                                }
+                            };
+                            let payload = strip.strip(&packet);
+                            if !payload.is_empty() {
+                                shared_state_for_reader.broadcast(packet.slice_ref(payload));
+                            }
+                        }
+                    }
+                    // Empty read; a zero-timeout port gets here constantly, so back off a little
+                    Ok(_) => {
+                        if empty_reads.record(read_started.elapsed(), serial_port.read_timeout()) {
+                            counters_reader
+                                .last_error
+                                .record("read: no data (device gone?)");
+                            counters_reader
+                                .serial_connected
+                                .store(false, Ordering::Relaxed);
+                            let _ = status_tx_reader.send(format!(
+                                "{}: disconnected (reads return no data), attempting reconnect...",
+                                target_for_reader.kind()
+                            ));
+                            break;
+                        }
+                        if listen_for_reader.nonblocking_serial {
+                            thread::sleep(NONBLOCKING_IDLE);
+                        }
+                    }
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock
+                        ) =>
+                    {
+                        empty_reads.reset();
+                        if let Some(packet) = envelopes.as_mut().and_then(Envelopes::flush) {
+                            let payload = strip.strip(&packet);
+                            if !payload.is_empty() {
+                                shared_state_for_reader.broadcast(packet.slice_ref(payload));
+                            }
+                        }
+                        if listen_for_reader.nonblocking_serial {
+                            thread::sleep(NONBLOCKING_IDLE);
+                        }
                     }
-                    Ok(_) => {}
-                    Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
                     Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                        counters_reader.last_error.record(format!("read: {e}"));
+                        counters_reader
+                            .serial_connected
+                            .store(false, Ordering::Relaxed);
                         // Quiet console; send to UI
-                        let _ = status_tx_reader
-                            .send("Serial: disconnected, attempting reconnect...".into());
+                        let _ = status_tx_reader.send(format!(
+                            "{}: disconnected, attempting reconnect...",
+                            target_for_reader.kind()
+                        ));
                         break;
                     }
                     Err(e) => {
                         warn!(?e, "Error reading from serial");
+                        counters_reader.last_error.record(format!("read: {e}"));
+                        counters_reader
+                            .serial_connected
+                            .store(false, Ordering::Relaxed);
                         break;
                     }
                 }
@@ -145,17 +694,86 @@ pub(crate) fn run_listen_with_shutdown(listen: Listen, stop_flag: Arc<AtomicBool
             if stop_reader.load(Ordering::Relaxed) {
                 break;
             }
+            // Also while the device is away: nobody needs it back until a client arrives
+            if went_idle || idle() {
+                let kind = target_for_reader.kind();
+                drop(serial_port);
+                let _ = writer_port_tx.send(None);
+                let _ = status_tx_reader.send(format!("{kind}: closed, no clients connected"));
+                serial_port = loop {
+                    if stop_reader.load(Ordering::Relaxed) {
+                        return Ok(());
+                    }
+                    if idle() {
+                        thread::sleep(Duration::from_millis(100));
+                        continue;
+                    }
+                    listen_for_reader.set_baud(counters_reader.baud.load(Ordering::Relaxed));
+                    match target_for_reader.open_pair(&listen_for_reader) {
+                        Ok((sp, spw)) => {
+                            let _ = writer_port_tx.send(Some(spw));
+                            break sp;
+                        }
+                        Err(e) => {
+                            reconnect_reader.failed(&e);
+                            thread::sleep(Duration::from_secs(1));
+                        }
+                    }
+                };
+                outage_since = None;
+                counters_reader
+                    .serial_connected
+                    .store(true, Ordering::Relaxed);
+                let _ = status_tx_reader.send(format!("{kind}: reopened for a client"));
+                continue;
+            }
+            if listen_for_reader.exit_on_disconnect {
+                request_stop(&stop_reader, &reason_for_reader, StopReason::DeviceLost);
+                break;
+            }
+            // A write to the dead half of a shared connection can still succeed
+            // and be lost, so the writer holds off until the new half arrives
+            if target_for_reader.shared_reconnect() && outage_since.is_none() {
+                let _ = writer_port_tx.send(None);
+            }
+            let down_for = outage_since.get_or_insert_with(Instant::now).elapsed();
             // Attempt reconnect every second
-            match open_serial_pair(&serial_path_for_reader, &listen_for_reader) {
+            listen_for_reader.set_baud(counters_reader.baud.load(Ordering::Relaxed));
+            match target_for_reader.open_pair(&listen_for_reader) {
                 Ok((sp, spw)) => {
+                    outage_since = None;
                     serial_port = sp;
-                    // serial writer port is owned by writer thread; we keep only reader here
-                    drop(spw);
+                    // A serial writer reopens its own port; a shared connection's
+                    // writer waits for this half instead
+                    if target_for_reader.shared_reconnect() {
+                        let _ = writer_port_tx.send(Some(spw));
+                    }
+                    counters_reader
+                        .serial_connected
+                        .store(true, Ordering::Relaxed);
+                    counters_reader
+                        .serial_reconnects
+                        .fetch_add(1, Ordering::Relaxed);
                     // Quiet console; status sent to UI
-                    let _ = status_tx_reader.send("Serial: reconnected (reader)".into());
+                    let attempts = reconnect_reader.succeeded();
+                    let _ = status_tx_reader.send(format!(
+                        "{}: reconnected (reader) after {attempts} attempt(s)",
+                        target_for_reader.kind()
+                    ));
                 }
                 Err(e) => {
-                    warn!(?e, "Reconnect failed (reader), retrying in 1s");
+                    reconnect_reader.failed(&e);
+                    // Past the grace, also drop clients that connect during the outage
+                    if client_grace.is_some_and(|grace| down_for >= grace) {
+                        let dropped = shared_state_for_reader.kick_all();
+                        if dropped > 0 {
+                            let _ = status_tx_reader.send(format!(
+                                "{}: down for {}s, disconnected {dropped} client(s)",
+                                target_for_reader.kind(),
+                                down_for.as_secs()
+                            ));
+                        }
+                    }
                     std::thread::sleep(Duration::from_secs(1));
                 }
             }
@@ -165,71 +783,153 @@ pub(crate) fn run_listen_with_shutdown(listen: Listen, stop_flag: Arc<AtomicBool
 
     // Serial writer thread: TCP -> serial
     let stop_writer = stop_flag.clone();
-    let serial_path_for_writer = serial_path.clone();
-    let listen_for_writer = listen.clone();
+    let reason_for_writer = Arc::clone(&stop_reason);
+    let counters_writer = Arc::clone(&counters);
+    let target_for_writer = target.clone();
+    let mut listen_for_writer = listen.clone();
+    let mut reconnect_writer = ReconnectNotifier::new(
+        format!("{}: reconnecting writer", target.kind()),
+        listen.reconnect_log_every,
+        headless,
+        status_tx_writer.clone(),
+    );
+    let mut poll = listen
+        .poll_bytes
+        .clone()
+        .map(|bytes| Poller::new(bytes, Duration::from_millis(listen.poll_interval)));
+    // Client writes only; --poll-bytes are sent exactly as given
+    let add = Affixes::new(listen.add_prefix.clone(), listen.add_suffix.clone());
+    // ...and likewise COBS-encoded, one packet per queued write
+    let framing = listen.framing;
+    let outgoing = move |buf: Bytes| match framing {
+        SerialFraming::Raw => add.wrap(buf),
+        SerialFraming::Cobs => Bytes::from(cobs::encode(&add.wrap(buf))),
+    };
     let serial_writer = thread::spawn(move || -> Result<()> {
         loop {
             if stop_writer.load(Ordering::Relaxed) {
                 break;
             }
-            match to_serial_rx.recv_timeout(Duration::from_millis(200)) {
-                Ok(buf) => {
-                    if let Err(_e) = serial_writer_port.write_all(&buf) {
-                        // Quiet console; status sent to UI
-                        let _ = status_tx_writer
-                            .send("Serial: write failed, reconnecting writer...".into());
-                        // try to reconnect serial writer and send a priming \\\n+                        // zero-length write to ensure OS queues are ready
-                        loop {
-                            if stop_writer.load(Ordering::Relaxed) {
-                                return Ok(());
-                            }
-                            match open_serial_pair(&serial_path_for_writer, &listen_for_writer) {
-                                Ok((sp, spw)) => {
-                                    // keep writer
-                                    serial_writer_port = spw;
-                                    drop(sp); // reader will reconnect separately
-                                              // Quiet console; status sent to UI
-                                    let _ = status_tx_writer
-                                        .send("Serial: reconnected (writer)".into());
-                                    // After successful reconnect, retry the buffered write once
-                                    let _ = serial_writer_port.write_all(&buf);
-                                    let _ = serial_writer_port.flush();
-                                    break;
-                                }
-                                Err(err) => {
-                                    warn!(?err, "Reconnect failed (writer), retrying in 1s");
-                                    std::thread::sleep(Duration::from_secs(1));
-                                }
+            // Closed while idle; queued writes wait for the reopen
+            serial_writer_port =
+                match current_writer_port(serial_writer_port, &writer_port_rx, &stop_writer) {
+                    Some(port) => port,
+                    None => return Ok(()),
+                };
+            let buf = match poll.as_mut().and_then(Poller::take_due) {
+                Some(bytes) => {
+                    counters_writer
+                        .poll_bytes
+                        .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                    bytes
+                }
+                None => {
+                    let wait = poll
+                        .as_ref()
+                        .map_or(WRITER_IDLE, |p| p.due_in().min(WRITER_IDLE));
+                    match to_serial_rx.recv_timeout(wait) {
+                        Ok(buf) => outgoing(match listen_for_writer.serial_write_policy {
+                            SerialWritePolicy::Fifo => buf,
+                            SerialWritePolicy::Latest => {
+                                let (newest, dropped) = take_latest(buf, &to_serial_rx);
+                                counters_writer
+                                    .serial_writes_dropped
+                                    .fetch_add(dropped, Ordering::Relaxed);
+                                newest
                             }
+                        }),
+                        Err(channel::RecvTimeoutError::Timeout) => continue,
+                        Err(channel::RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            };
+            // The reader may have replaced a shared connection while we waited
+            serial_writer_port =
+                match current_writer_port(serial_writer_port, &writer_port_rx, &stop_writer) {
+                    Some(port) => port,
+                    None => return Ok(()),
+                };
+            let written = serial_writer_port
+                .write_all(&buf)
+                .and_then(|()| match listen_for_writer.flush_writes {
+                    true => serial_writer_port.flush(),
+                    false => Ok(()),
+                });
+            if let Err(e) = written {
+                counters_writer.last_error.record(format!("write: {e}"));
+                counters_writer
+                    .serial_connected
+                    .store(false, Ordering::Relaxed);
+                if listen_for_writer.exit_on_disconnect {
+                    request_stop(&stop_writer, &reason_for_writer, StopReason::DeviceLost);
+                    return Ok(());
+                }
+                if target_for_writer.shared_reconnect() {
+                    let _ = status_tx_writer.send(format!(
+                        "{}: write failed, waiting for the reader to reconnect...",
+                        target_for_writer.kind()
+                    ));
+                    serial_writer_port = match next_writer_port(&writer_port_rx, &stop_writer) {
+                        Some(port) => port,
+                        None => return Ok(()),
+                    };
+                    // the write that failed goes out once on the new connection
+                    let _ = serial_writer_port.write_all(&buf);
+                    let _ = serial_writer_port.flush();
+                    continue;
+                }
+                // Quiet console; status sent to UI
+                let _ = status_tx_writer.send(format!(
+                    "{}: write failed, reconnecting writer...",
+                    target_for_writer.kind()
+                ));
+                // try to reconnect serial writer and send a priming \\\n+                        // zero-length write to ensure OS queues are ready
+                loop {
+                    if stop_writer.load(Ordering::Relaxed) {
+                        return Ok(());
+                    }
+                    listen_for_writer.set_baud(counters_writer.baud.load(Ordering::Relaxed));
+                    match target_for_writer.open_pair(&listen_for_writer) {
+                        Ok((sp, spw)) => {
+                            // keep writer
+                            serial_writer_port = spw;
+                            counters_writer
+                                .serial_connected
+                                .store(true, Ordering::Relaxed);
+                            drop(sp); // reader will reconnect separately
+                                      // Quiet console; status sent to UI
+                            let attempts = reconnect_writer.succeeded();
+                            let _ = status_tx_writer.send(format!(
+                                "{}: reconnected (writer) after {attempts} attempt(s)",
+                                target_for_writer.kind()
+                            ));
+                            // After successful reconnect, retry the buffered write once
+                            let _ = serial_writer_port.write_all(&buf);
+                            let _ = serial_writer_port.flush();
+                            break;
+                        }
+                        Err(err) => {
+                            reconnect_writer.failed(&err);
+                            std::thread::sleep(Duration::from_secs(1));
                         }
                     }
                 }
-                Err(channel::RecvTimeoutError::Timeout) => {}
-                Err(channel::RecvTimeoutError::Disconnected) => break,
+            } else {
+                counters_writer.last_error.clear();
             }
         }
         Ok(())
     });
 
-    // TCP acceptor
-    let listener = TcpListener::bind(listen.host)
-        .with_context(|| format!("Binding TCP listener at {}", listen.host))?;
-    listener
-        .set_nonblocking(true)
-        .context("Setting TCP listener non-blocking mode")?;
-
     // mDNS/Bonjour advertisement (zero-config), optional via feature flag
     #[cfg(feature = "mdns")]
     let _mdns_guard: Option<(_mdns::Responder, _mdns::Service)> = {
         // Derive a friendly instance name from the serial device
-        let instance = serial_path
-            .rsplit('/')
-            .next()
-            .map(|s| format!("sergw:{s}"))
-            .unwrap_or_else(|| "sergw".to_string());
+        let instance = format!("sergw:{}", target.short_name());
         match _mdns::Responder::new() {
             Ok(responder) => {
-                let port = listen.host.port();
+                // One instance name can only carry one port: advertise the first
+                let port = bound[0].port();
                 let txt: [&str; 1] = ["provider=sergw"];
                 let service = responder.register("_sergw._tcp".to_string(), instance, port, &txt);
                 Some((responder, service))
@@ -241,10 +941,135 @@ pub(crate) fn run_listen_with_shutdown(listen: Listen, stop_flag: Arc<AtomicBool
         }
     };
 
-    loop {
-        if stop_flag.load(Ordering::Relaxed) {
-            break;
+    // One acceptor per --host, all feeding the same device and broadcast set
+    let clients = ClientContext {
+        accept_budget: listen.accept_rate.map(|r| Mutex::new(TokenBucket::new(r))),
+        accept_dropped: AtomicU64::new(0),
+        on_connect,
+        on_connect_sent: AtomicBool::new(false),
+        listen: listen.clone(),
+        stop_flag: stop_flag.clone(),
+        shared_state: Arc::clone(&shared_state),
+        counters: Arc::clone(&counters),
+        to_serial_tx,
+        event_tx,
+        insp_tx,
+        insp_filter,
+        split_logs,
+    };
+    let clients = Arc::new(clients);
+    let accepted: Result<()> = thread::scope(|scope| {
+        let acceptors: Vec<_> = listeners
+            .into_iter()
+            .map(|listener| {
+                let clients = &clients;
+                scope.spawn(move || {
+                    let res = accept_clients(listener, clients);
+                    if res.is_err() {
+                        clients.stop_flag.store(true, Ordering::Relaxed);
+                    }
+                    res
+                })
+            })
+            .collect();
+        // the scope joins the rest if one fails early
+        acceptors
+            .into_iter()
+            .try_for_each(|a| a.join().unwrap_or(Ok(())))
+    });
+    accepted?;
+
+    // Shutdown
+    match *stop_reason.lock().unwrap_or_else(|e| e.into_inner()) {
+        Some(reason) => info!(%reason, "Shutting down"),
+        None => info!("Shutting down"),
+    }
+    if let Err(e) = serial_reader.join().unwrap_or(Ok(())) {
+        warn!(?e, "Serial reader error on shutdown");
+    }
+    if let Err(e) = serial_writer.join().unwrap_or(Ok(())) {
+        warn!(?e, "Serial writer error on shutdown");
+    }
+    aux.join();
+    if let Some(tap) = tap {
+        let _ = tap.join();
+    }
+    shared_state.dispose();
+
+    let tui_result = tui_handle.map_or(Ok(()), |handle| handle.join().unwrap_or(Ok(())));
+    // After the TUI has left the alternate screen, so the line stays visible
+    eprintln!("{}", session_summary(started.elapsed(), &counters));
+    tui_result?;
+
+    if *stop_reason.lock().unwrap_or_else(|e| e.into_inner()) == Some(StopReason::DeviceLost) {
+        return Err(DeviceDisconnected(target.to_string()).into());
+    }
+    Ok(())
+}
+
+/// What every acceptor and client connection shares with the rest of the bridge
+struct ClientContext {
+    listen: Listen,
+    stop_flag: Arc<AtomicBool>,
+    shared_state: Arc<SharedState>,
+    counters: Arc<Counters>,
+    to_serial_tx: channel::Sender<Bytes>,
+    event_tx: Option<channel::Sender<String>>,
+    insp_tx: channel::Sender<Sample>,
+    insp_filter: Arc<InspectorFilter>,
+    split_logs: Option<Arc<SplitLogs>>,
+    on_connect: Option<Bytes>,
+    on_connect_sent: AtomicBool,
+    // --accept-rate budget, shared by every acceptor, and connections turned
+    // away since it last had room (only the first of a storm is reported)
+    accept_budget: Option<Mutex<TokenBucket>>,
+    accept_dropped: AtomicU64,
+}
+
+impl ClientContext {
+    fn event(&self, message: String) {
+        if let Some(tx) = &self.event_tx {
+            let _ = tx.send(message);
+        }
+    }
+
+    /// --accept-rate and --max-per-ip; false when `addr` is to be closed
+    fn admit(&self, addr: SocketAddr) -> bool {
+        if let Some(budget) = &self.accept_budget {
+            let allowed = budget.lock().unwrap_or_else(|e| e.into_inner()).try_take(1);
+            if !allowed {
+                if self.accept_dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                    warn!(%addr, "Accept rate limit engaged, closing new connections");
+                    self.event(format!(
+                        "Rejected: {addr} (over --accept-rate {}/s, closing new connections until it eases)",
+                        self.listen.accept_rate.unwrap_or_default()
+                    ));
+                }
+                return false;
+            }
+            let dropped = self.accept_dropped.swap(0, Ordering::Relaxed);
+            if dropped > 0 {
+                info!(dropped, "Accept rate limit eased");
+            }
         }
+        if let Some(limit) = self.listen.max_per_ip {
+            let open = self.shared_state.connections_from(addr.ip());
+            if open >= limit {
+                warn!(%addr, open, limit, "Rejected connection: per-IP limit reached");
+                self.event(format!(
+                    "Rejected: {addr} ({open} connection(s) from {} already)",
+                    addr.ip()
+                ));
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// One --host listener's accept loop, until the stop flag
+fn accept_clients(listener: TcpListener, ctx: &Arc<ClientContext>) -> Result<()> {
+    while !ctx.stop_flag.load(Ordering::Relaxed) {
         let (stream, addr) = match listener.accept() {
             Ok(conn) => conn,
             Err(ref e) if e.kind() == std::io::ErrorKind::WouldBlock => {
@@ -257,131 +1082,896 @@ pub(crate) fn run_listen_with_shutdown(listen: Listen, stop_flag: Arc<AtomicBool
                 continue;
             }
         };
-        let mut stream_reader = stream.try_clone().context("Cloning TCP stream (reader)")?;
-        let mut stream_writer = stream;
-        if let Err(e) = stream_reader.set_nodelay(true) {
-            warn!(?e, %addr, "Failed to set TCP_NODELAY on reader");
-        }
-        if let Err(e) = stream_writer.set_nodelay(true) {
-            warn!(?e, %addr, "Failed to set TCP_NODELAY on writer");
-        }
-        info!(%addr, "Accepted connection");
-        if let Some(tx) = &event_tx {
-            let _ = tx.send(format!("Connected: {addr}"));
-        }
-
-        let to_serial_tx_conn = to_serial_tx.clone();
-        let (to_tcp_tx, to_tcp_rx) = channel::bounded::<Bytes>(listen.buffer);
-
-        // Register connection for broadcasts
-        shared_state.insert(addr, to_tcp_tx);
-
-        // TCP reader: TCP -> to_serial
-        let stop_conn = stop_flag.clone();
-        let reader_addr = addr;
-        let counters_in = Arc::clone(&counters);
-        let insp_tx_reader = insp_tx.clone();
-        let tcp_reader = thread::spawn(move || -> Result<()> {
-            let mut buffer = [0u8; 4096];
-            while !stop_conn.load(Ordering::Relaxed) {
-                match stream_reader.read(&mut buffer) {
-                    Ok(0) => break,
-                    Ok(n) => {
-                        counters_in.bytes_in.fetch_add(n as u64, Ordering::Relaxed);
-                        let buf = Bytes::copy_from_slice(&buffer[..n]);
-                        let _ = insp_tx_reader.try_send(Sample {
-                            dir: DirectionTag::Outbound(reader_addr),
-                            data: buf.clone(),
-                        });
-                        if let Err(e) = to_serial_tx_conn.send(buf) {
-                            warn!(?e, "Dropping data to serial, backpressure or shutdown");
-                            break;
+        if ctx.admit(addr) {
+            serve_client(ctx, stream, addr)?;
+        }
+    }
+    Ok(())
+}
+
+/// Register an accepted client and start its reader, writer and supervisor
+fn serve_client(ctx: &Arc<ClientContext>, stream: TcpStream, addr: SocketAddr) -> Result<()> {
+    let listen = &ctx.listen;
+    // Accepted sockets inherit the listener's non-blocking mode on some
+    // platforms (macOS, Windows), which would make the reader spin
+    if let Err(e) = stream.set_nonblocking(false) {
+        warn!(?e, %addr, "Failed to make TCP stream blocking");
+    }
+    let stream_reader = stream.try_clone().context("Cloning TCP stream (reader)")?;
+    if let Err(e) = stream_reader.set_read_timeout(Some(CLIENT_READ_POLL)) {
+        warn!(?e, %addr, "Failed to set TCP read timeout");
+    }
+    let stream_writer = stream;
+    if let Err(e) = stream_reader.set_nodelay(true) {
+        warn!(?e, %addr, "Failed to set TCP_NODELAY on reader");
+    }
+    if let Err(e) = stream_writer.set_nodelay(true) {
+        warn!(?e, %addr, "Failed to set TCP_NODELAY on writer");
+    }
+    // A half-open client would otherwise park its writer in write_all for good
+    let write_timeout =
+        (listen.tcp_write_timeout > 0).then(|| Duration::from_secs(listen.tcp_write_timeout));
+    if let Err(e) = stream_writer.set_write_timeout(write_timeout) {
+        warn!(?e, %addr, "Failed to set TCP write timeout");
+    }
+    let (to_tcp_tx, to_tcp_rx) = channel::bounded::<Bytes>(listen.buffer);
+
+    // Register connection for broadcasts
+    ctx.shared_state.insert(addr, to_tcp_tx);
+    let clients = ctx.shared_state.tcp_connections.len();
+    ctx.counters
+        .peak_clients
+        .fetch_max(clients as u64, Ordering::Relaxed);
+
+    // Which of the --host listeners took it
+    let local = stream_writer
+        .local_addr()
+        .map_or_else(|_| "?".to_string(), |a| a.to_string());
+    info!(%addr, %local, clients, "Accepted connection");
+    ctx.event(format!(
+        "Connected: {addr} on {local} ({clients} client(s) connected)"
+    ));
+    if listen.single_controller {
+        let message = match ctx.shared_state.join_controllers(addr) {
+            true => format!("Controller: {addr} controls the device"),
+            false => format!(
+                "Observer: {addr} is read-only while {} controls the device",
+                ctx.shared_state
+                    .controller()
+                    .map_or_else(|| "?".to_string(), |a| a.to_string())
+            ),
+        };
+        info!("{message}");
+        ctx.event(message);
+    }
+    // Queued before this client's reader starts, so it reaches the device first
+    if let Some(script) = &ctx.on_connect {
+        if listen.on_connect_every || !ctx.on_connect_sent.swap(true, Ordering::Relaxed) {
+            let message = match ctx.to_serial_tx.try_send(script.clone()) {
+                Ok(()) => format!(
+                    "On-connect script: {} byte(s) queued for {addr}",
+                    script.len()
+                ),
+                Err(_) => format!("On-connect script: device queue full, not sent for {addr}"),
+            };
+            info!("{message}");
+            ctx.event(message);
+        }
+    }
+    if listen.reverse_dns {
+        let event_tx = ctx.event_tx.clone();
+        thread::spawn(move || {
+            let name = reverse_lookup(addr.ip());
+            info!(%addr, name = name.as_deref().unwrap_or("-"), "Reverse DNS");
+            if let (Some(tx), Some(name)) = (&event_tx, name) {
+                let _ = tx.send(format!("Resolved: {addr} is {name}"));
+            }
+        });
+    }
+
+    // Per-connection stop so the TUI can kick this client alone
+    let conn_stop = Arc::new(AtomicBool::new(false));
+    match stream_writer.try_clone() {
+        Ok(kick_stream) => {
+            let conn_stop = Arc::clone(&conn_stop);
+            ctx.shared_state.set_kicker(addr, move || {
+                conn_stop.store(true, Ordering::Relaxed);
+                // unblock a reader parked in read()
+                let _ = kick_stream.shutdown(Shutdown::Both);
+            });
+        }
+        Err(e) => warn!(?e, %addr, "Failed to clone TCP stream for kick handle"),
+    }
+
+    let tcp_reader = {
+        let ctx = Arc::clone(ctx);
+        let conn_stop = Arc::clone(&conn_stop);
+        thread::spawn(move || read_client(&ctx, stream_reader, addr, &conn_stop))
+    };
+    let tcp_writer = {
+        let ctx = Arc::clone(ctx);
+        thread::spawn(move || write_client(&ctx, stream_writer, to_tcp_rx, addr, &conn_stop))
+    };
+    let ctx = Arc::clone(ctx);
+    thread::spawn(move || supervise_client(&ctx, addr, tcp_reader, tcp_writer));
+    Ok(())
+}
+
+/// TCP -> device: hello lines, framing, observers, then the serial queue
+fn read_client(
+    ctx: &ClientContext,
+    mut stream: TcpStream,
+    addr: SocketAddr,
+    conn_stop: &AtomicBool,
+) -> Result<()> {
+    let listen = &ctx.listen;
+    let framed = listen.frame == FrameMode::LengthPrefix;
+    let split_logs = ctx
+        .split_logs
+        .as_ref()
+        .filter(|_| listen.split_logs_clients);
+    let mut ingress = listen.client_ingress_rate.map(RateWatch::new);
+    let mut buffer = [0u8; 4096];
+    // an observer's writes are dropped; reported once per connection
+    let mut observer_told = false;
+    let mut handshake = Handshake::default();
+    let mut deframer = Deframer::default();
+    'read: while !ctx.stop_flag.load(Ordering::Relaxed) && !conn_stop.load(Ordering::Relaxed) {
+        let buf = match stream.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => {
+                if let Some(limit) = ingress
+                    .as_mut()
+                    .and_then(|w| w.feed(n as u64).then_some(w.limit()))
+                {
+                    warn!(%addr, "Closing client: over --client-ingress-rate");
+                    ctx.event(format!(
+                        "Rate limited: {addr} sent over {limit} B/s for 3s, disconnecting"
+                    ));
+                    break;
+                }
+                if handshake.is_done() {
+                    Bytes::copy_from_slice(&buffer[..n])
+                } else {
+                    let (hellos, rest) = handshake.feed(&buffer[..n]);
+                    for hello in hellos {
+                        match hello {
+                            HelloLine::Name(label) => {
+                                info!(%addr, %label, "Client identified");
+                                ctx.event(format!("Named: {addr} as {label}"));
+                                ctx.shared_state.set_label(addr, label);
+                            }
+                            HelloLine::Mode(mode) => {
+                                info!(%addr, ?mode, "Client selected mode");
+                                ctx.shared_state.set_mode(addr, mode);
+                            }
                         }
                     }
-                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {}
-                    Err(e) => {
-                        warn!(?e, addr = %reader_addr, "TCP read error");
-                        break;
+                    if rest.is_empty() {
+                        continue;
                     }
+                    Bytes::from(rest)
                 }
             }
-            Ok(())
-        });
+            // Idle for CLIENT_READ_POLL; a hello line left unfinished
+            // is data after all, then loop to re-check the stop flags
+            Err(e) if is_timeout(&e) => {
+                let rest = handshake.flush();
+                if rest.is_empty() {
+                    continue;
+                }
+                Bytes::from(rest)
+            }
+            Err(e) => {
+                warn!(?e, %addr, "TCP read error");
+                break;
+            }
+        };
+        let messages = if framed {
+            match deframer.feed(&buf) {
+                Ok(frames) => frames,
+                Err(e) => {
+                    warn!(%addr, "Closing client: {e}");
+                    break;
+                }
+            }
+        } else {
+            vec![buf]
+        };
+        for buf in messages {
+            if !ctx.shared_state.may_write(&addr) {
+                if !std::mem::replace(&mut observer_told, true) {
+                    ctx.event(format!(
+                        "Observer: {addr} is read-only, its writes are dropped"
+                    ));
+                }
+                continue;
+            }
+            ctx.counters
+                .bytes_in
+                .fetch_add(buf.len() as u64, Ordering::Relaxed);
+            if let Some(logs) = split_logs {
+                logs.client(addr, &buf);
+            }
+            let dir = DirectionTag::Outbound(addr);
+            if ctx.insp_filter.wants(&dir) {
+                offer_sample(
+                    &ctx.insp_tx,
+                    Sample::capture(dir, &buf, listen.inspector_sample_max),
+                    &ctx.counters.inspector_dropped,
+                );
+            }
+            if let Err(e) = ctx.to_serial_tx.send(buf) {
+                warn!(?e, "Dropping data to serial, backpressure or shutdown");
+                break 'read;
+            }
+        }
+    }
+    if let Some(logs) = split_logs {
+        logs.close_client(addr);
+    }
+    Ok(())
+}
 
-        // TCP writer: from broadcast -> TCP
-        let stop_conn = stop_flag.clone();
-        let writer_addr = addr;
-        let tcp_writer = thread::spawn(move || -> Result<()> {
-            while !stop_conn.load(Ordering::Relaxed) {
-                match to_tcp_rx.recv_timeout(Duration::from_millis(200)) {
-                    Ok(buf) => {
-                        if let Err(e) = stream_writer.write_all(&buf) {
-                            warn!(?e, addr = %writer_addr, "TCP write error");
-                            break;
-                        }
+/// Broadcast -> TCP, until the client is removed, kicked or stops taking data
+fn write_client(
+    ctx: &ClientContext,
+    mut stream: TcpStream,
+    to_tcp_rx: channel::Receiver<Bytes>,
+    addr: SocketAddr,
+    conn_stop: &AtomicBool,
+) -> Result<()> {
+    let framed = ctx.listen.frame == FrameMode::LengthPrefix;
+    while !ctx.stop_flag.load(Ordering::Relaxed) && !conn_stop.load(Ordering::Relaxed) {
+        match to_tcp_rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(buf) => {
+                let res = if framed {
+                    stream.write_all(&frame::encode(&buf))
+                } else {
+                    stream.write_all(&buf)
+                };
+                if let Err(e) = res {
+                    if is_timeout(&e) {
+                        warn!(%addr, "Closing client: TCP write timed out");
+                    } else {
+                        warn!(?e, %addr, "TCP write error");
                     }
-                    Err(channel::RecvTimeoutError::Timeout) => {}
-                    Err(_e) => break,
+                    break;
                 }
             }
-            Ok(())
-        });
+            Err(channel::RecvTimeoutError::Timeout) => {}
+            // Removed from the broadcast set (closed, kicked or too slow)
+            Err(_e) => break,
+        }
+    }
+    // Take the reader down too so the supervisor cleans up now rather
+    // than when a half-open client finally sends something
+    conn_stop.store(true, Ordering::Relaxed);
+    let _ = stream.shutdown(Shutdown::Both);
+    Ok(())
+}
 
-        // Detach a supervisor for the connection
-        let shared_state_remove = Arc::clone(&shared_state);
-        let event_tx_conn = event_tx.clone();
-        thread::spawn(move || {
-            // Wait for reader to complete (client closed or error)
-            let _ = tcp_reader.join();
-            // Remove connection immediately so writers drop their sender and exit
-            shared_state_remove.remove(&addr);
-            if let Some(tx) = &event_tx_conn {
-                let _ = tx.send(format!("Disconnected: {addr}"));
-            }
-            // Now wait for writer to finish draining/exit
-            let _ = tcp_writer.join();
-            info!(%addr, "Closed connection");
-        });
+/// Clean up after a client once its reader ends (client closed or error)
+fn supervise_client(
+    ctx: &ClientContext,
+    addr: SocketAddr,
+    tcp_reader: JoinHandle<Result<()>>,
+    tcp_writer: JoinHandle<Result<()>>,
+) {
+    let _ = tcp_reader.join();
+    // Remove connection immediately so writers drop their sender and exit
+    ctx.shared_state.remove(&addr);
+    ctx.event(format!("Disconnected: {addr}"));
+    if let Some(next) = ctx.shared_state.leave_controllers(&addr) {
+        let message = format!("Controller: {next} promoted after {addr} left");
+        info!("{message}");
+        ctx.event(message);
     }
+    // Now wait for writer to finish draining/exit
+    let _ = tcp_writer.join();
+    info!(%addr, "Closed connection");
+}
 
-    // Shutdown
-    info!("Shutting down");
-    if let Err(e) = serial_reader.join().unwrap_or(Ok(())) {
-        warn!(?e, "Serial reader error on shutdown");
+/// `--poll-bytes`: the sequence the serial writer sends on its own every interval
+struct Poller {
+    bytes: Bytes,
+    interval: Duration,
+    next: Instant,
+}
+
+impl Poller {
+    fn new(bytes: Bytes, interval: Duration) -> Self {
+        Self {
+            bytes,
+            interval,
+            next: Instant::now(),
+        }
     }
-    if let Err(e) = serial_writer.join().unwrap_or(Ok(())) {
-        warn!(?e, "Serial writer error on shutdown");
+
+    fn due_in(&self) -> Duration {
+        self.next.saturating_duration_since(Instant::now())
     }
-    shared_state.dispose();
 
-    if let Some(handle) = tui_handle {
-        let _ = handle.join();
+    /// The poll sequence if it is due; a late poll is not followed by a burst of catch-ups
+    fn take_due(&mut self) -> Option<Bytes> {
+        let now = Instant::now();
+        if now < self.next {
+            return None;
+        }
+        self.next = now + self.interval;
+        Some(self.bytes.clone())
     }
+}
 
-    Ok(())
+/// One-line recap printed on exit
+fn session_summary(runtime: Duration, counters: &Counters) -> String {
+    let secs = runtime.as_secs();
+    let polled = counters.poll_bytes.load(Ordering::Relaxed);
+    let polled = match polled {
+        0 => String::new(),
+        n => format!(" | {n} B polled"),
+    };
+    format!(
+        "Session: {}h{:02}m{:02}s | in {} B (clients -> device) | out {} B (device -> clients) | peak {} client(s) | {} device reconnect(s){polled}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        counters.bytes_in.load(Ordering::Relaxed),
+        counters.bytes_out.load(Ordering::Relaxed),
+        counters.peak_clients.load(Ordering::Relaxed),
+        counters.serial_reconnects.load(Ordering::Relaxed),
+    )
 }
 
-fn open_serial_pair(
-    serial_path: &str,
+/// Serial reads that came back `Ok(0)` without waiting out the port's read
+/// timeout. Some USB serial drivers report an unplugged device this way
+/// instead of with an error, so a run of them is treated as a disconnect.
+#[derive(Default)]
+struct EmptyReads {
+    run: u32,
+}
+
+impl EmptyReads {
+    /// Record an `Ok(0)` read that took `took` on a port waiting up to
+    /// `timeout`; true once the run is long enough to call the device gone.
+    /// Zero-timeout ports (--nonblocking-serial) return empty reads while
+    /// idle, so they never count.
+    fn record(&mut self, took: Duration, timeout: Option<Duration>) -> bool {
+        match timeout {
+            Some(timeout) if !timeout.is_zero() && took < timeout / 2 => {
+                self.run += 1;
+                self.run >= EMPTY_READ_LIMIT
+            }
+            _ => {
+                self.run = 0;
+                false
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.run = 0;
+    }
+}
+
+/// Reconnect progress without the spam: the TUI folds every attempt into one
+/// rolling events line, while headless logs only see the first attempt and
+/// then every `every`th one.
+struct ReconnectNotifier {
+    label: String,
+    every: u32,
+    headless: bool,
+    attempt: u32,
+    status_tx: channel::Sender<String>,
+}
+
+impl ReconnectNotifier {
+    fn new(label: String, every: u32, headless: bool, status_tx: channel::Sender<String>) -> Self {
+        Self {
+            label,
+            every,
+            headless,
+            attempt: 0,
+            status_tx,
+        }
+    }
+
+    fn failed(&mut self, err: &anyhow::Error) {
+        self.attempt += 1;
+        if !self.headless || should_report_attempt(self.attempt, self.every) {
+            let _ = self.status_tx.send(format!(
+                "{}… (attempt {}): {err:#}",
+                self.label, self.attempt
+            ));
+        }
+    }
+
+    /// Reset after a successful reopen, returning how many attempts it took
+    fn succeeded(&mut self) -> u32 {
+        std::mem::take(&mut self.attempt) + 1
+    }
+}
+
+/// `port`, or whatever the reader handed over since. A `None` from the reader
+/// (device closed or lost) releases `port` and waits for the reopen; `None` on
+/// shutdown.
+fn current_writer_port(
+    mut port: DeviceHandle,
+    rx: &channel::Receiver<Option<DeviceHandle>>,
+    stop: &AtomicBool,
+) -> Option<DeviceHandle> {
+    loop {
+        match rx.try_recv() {
+            Ok(Some(next)) => port = next,
+            Ok(None) => {
+                drop(port);
+                port = next_writer_port(rx, stop)?;
+            }
+            Err(_) => return Some(port),
+        }
+    }
+}
+
+/// Block until the reader hands over a reopened port; `None` on shutdown
+fn next_writer_port(
+    rx: &channel::Receiver<Option<DeviceHandle>>,
+    stop: &AtomicBool,
+) -> Option<DeviceHandle> {
+    while !stop.load(Ordering::Relaxed) {
+        match rx.recv_timeout(WRITER_IDLE) {
+            Ok(Some(port)) => return Some(port),
+            Ok(None) | Err(channel::RecvTimeoutError::Timeout) => {}
+            Err(channel::RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+    None
+}
+
+// Blocking socket timeouts surface as WouldBlock on Unix and TimedOut on Windows
+fn is_timeout(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+    )
+}
+
+fn should_report_attempt(attempt: u32, every: u32) -> bool {
+    attempt == 1 || (every > 0 && attempt % every == 0)
+}
+
+/// Skip past every frame already queued behind `first`, returning the newest
+/// and how many were discarded.
+fn take_latest(first: Bytes, rx: &channel::Receiver<Bytes>) -> (Bytes, u64) {
+    let mut newest = first;
+    let mut dropped = 0;
+    while let Ok(next) = rx.try_recv() {
+        newest = next;
+        dropped += 1;
+    }
+    (newest, dropped)
+}
+
+/// `--coalesce-ms`: after a first read of `filled` bytes keep reading into
+/// `buf` until `window` has passed, the buffer is full, or a read comes back
+/// empty. Errors just end the window; the next regular read reports them.
+fn coalesce_reads(
+    port: &mut dyn DeviceIo,
+    buf: &mut [u8],
+    mut filled: usize,
+    window: Duration,
+) -> usize {
+    let Some(normal) = port.read_timeout() else {
+        return filled;
+    };
+    let deadline = Instant::now() + window;
+    while filled < buf.len() {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() || port.set_read_timeout(left).is_err() {
+            break;
+        }
+        match port.read(&mut buf[filled..]) {
+            Ok(n) if n > 0 => filled += n,
+            _ => break,
+        }
+    }
+    let _ = port.set_read_timeout(normal);
+    filled
+}
+
+fn log_state_snapshot(started: Instant, counters: &Counters, shared: &SharedState) -> String {
+    let uptime_secs = started.elapsed().as_secs();
+    let bytes_in = counters.bytes_in.load(Ordering::Relaxed);
+    let bytes_out = counters.bytes_out.load(Ordering::Relaxed);
+    let serial_connected = counters.serial_connected.load(Ordering::Relaxed);
+    let clients: Vec<String> = shared
+        .tcp_connections
+        .iter()
+        .map(|e| e.key().to_string())
+        .collect();
+    info!(
+        uptime_secs,
+        bytes_in,
+        bytes_out,
+        serial_connected,
+        clients = ?clients,
+        "State snapshot"
+    );
+    format!(
+        "Snapshot: up {uptime_secs}s | in {bytes_in} B | out {bytes_out} B | serial {} | clients [{}]",
+        if serial_connected { "connected" } else { "disconnected" },
+        clients.join(", ")
+    )
+}
+
+/// Validate the configuration the way a real start would, without opening the
+/// device or keeping the port: the listener is bound and dropped immediately.
+fn dry_run(target: &DeviceTarget, hosts: &[SocketAddr], listen: &Listen) -> Result<()> {
+    target.check_present()?;
+    for &host in hosts {
+        let listener = bind_listener(host, listen.reuse_addr, listen.bind_retries)
+            .with_context(|| format!("Binding TCP listener at {host}"))?;
+        drop(listener);
+    }
+    for (name, addr) in [
+        ("health", listen.health_addr),
+        ("events", listen.event_addr),
+    ] {
+        if let Some(addr) = addr {
+            drop(
+                TcpListener::bind(addr)
+                    .with_context(|| format!("Binding {name} listener at {addr}"))?,
+            );
+        }
+    }
+    println!("Dry run OK");
+    println!("  device: {target}");
+    if let DeviceTarget::Serial(_) = target {
+        println!(
+            "  line:   {} baud, {:?} data bits, {:?} parity, {:?} stop bits",
+            listen.baud(),
+            listen.line.data_bits,
+            listen.line.parity,
+            listen.line.stop_bits
+        );
+    }
+    for host in hosts {
+        println!("  listen: {host} (bindable)");
+    }
+    if let Some(addr) = listen.health_addr {
+        println!("  health: {addr} (bindable)");
+    }
+    if let Some(addr) = listen.event_addr {
+        println!("  events: {addr} (bindable)");
+    }
+    Ok(())
+}
+
+fn device_target(listen: &Listen) -> Result<DeviceTarget> {
+    #[cfg(feature = "rfc2217")]
+    if let Some(server) = &listen.upstream_rfc2217 {
+        return Ok(DeviceTarget::Rfc2217(resolve_host(
+            server,
+            listen.prefer_ip,
+        )?));
+    }
+    Ok(match &listen.upstream {
+        Some(upstream) => DeviceTarget::Upstream(resolve_host(upstream, listen.prefer_ip)?),
+        None => DeviceTarget::Serial(select_serial_port(
+            &listen.serial,
+            listen.include_builtin,
+            listen.port_match.as_deref(),
+        )?),
+    })
+}
+
+/// The device as asked for, before a port has been picked
+fn wanted_device(listen: &Listen) -> String {
+    #[cfg(feature = "rfc2217")]
+    if let Some(server) = &listen.upstream_rfc2217 {
+        return server.clone();
+    }
+    match (&listen.upstream, &listen.serial, &listen.port_match) {
+        (Some(upstream), _, _) => upstream.clone(),
+        (None, Some(path), _) => path.clone(),
+        (None, None, Some(pattern)) => format!("matching '{pattern}'"),
+        (None, None, None) => "(any serial port)".to_string(),
+    }
+}
+
+/// `--show-caps`: open the port briefly, probe it and print the report
+fn show_caps(target: &DeviceTarget, listen: &Listen) -> Result<()> {
+    let DeviceTarget::Serial(path) = target else {
+        println!("Capabilities of {target}: n/a (upstream is a TCP connection, not a serial port)");
+        return Ok(());
+    };
+    let mut port = configure_serial(serialport::new(path, listen.baud()), &listen.line)
+        .with_context(|| format!("Opening serial port {path}"))?;
+    print!("{}", caps::query_caps(path, port.as_mut()));
+    Ok(())
+}
+
+/// `--wait-for-device`: retry `attempt` (picking the port, then opening it)
+/// until it succeeds, the wait runs out or `stop` is set, e.g. at boot while
+/// the adapter is still enumerating. Without the flag it is tried once.
+fn wait_for_device<T>(
     listen: &Listen,
-) -> Result<(
-    Box<dyn serialport::SerialPort>,
-    Box<dyn serialport::SerialPort>,
-)> {
-    let builder = serialport::new(serial_path, listen.baud);
-    let port = configure_serial(builder, listen)
-        .with_context(|| format!("Opening serial port {serial_path}"))?;
-    let writer = port
-        .try_clone()
-        .with_context(|| format!("Cloning serial port {serial_path} for writer"))?;
-    Ok((port, writer))
+    stop: &AtomicBool,
+    device: &str,
+    mut attempt: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    if listen.wait_for_device == 0 {
+        return attempt();
+    }
+    let deadline = Instant::now() + Duration::from_secs(listen.wait_for_device);
+    let mut delay = Duration::from_millis(250);
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            bail!("Stopped while waiting for device {device}");
+        }
+        let e = match attempt() {
+            Ok(found) => return Ok(found),
+            Err(e) => e,
+        };
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(e.context(format!(
+                "Device {device} did not become available within {}s",
+                listen.wait_for_device
+            )));
+        }
+        let remaining = deadline - now;
+        // The TUI is not up yet, so progress goes straight to stderr
+        eprintln!(
+            "Waiting for device {device}... ({}s left)",
+            remaining.as_secs()
+        );
+        info!(%device, remaining_secs = remaining.as_secs(), "Waiting for device");
+        let wake = now + delay.min(remaining);
+        while Instant::now() < wake && !stop.load(Ordering::Relaxed) {
+            thread::sleep(WAIT_POLL);
+        }
+        delay = next_backoff(delay);
+    }
+}
+
+const BIND_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Bind, retrying while a previous instance's socket is still being released.
+fn bind_listener(host: SocketAddr, reuse_addr: bool, retries: u32) -> std::io::Result<TcpListener> {
+    let mut attempt = 0;
+    loop {
+        match bind_once(host, reuse_addr) {
+            Err(e) if e.kind() == std::io::ErrorKind::AddrInUse && attempt < retries => {
+                attempt += 1;
+                warn!(%host, attempt, retries, "Address in use, retrying bind");
+                thread::sleep(BIND_RETRY_DELAY);
+            }
+            res => return res,
+        }
+    }
+}
+
+fn bind_once(host: SocketAddr, reuse_addr: bool) -> std::io::Result<TcpListener> {
+    if !reuse_addr {
+        return TcpListener::bind(host);
+    }
+    use socket2::{Domain, Protocol, Socket, Type};
+    let socket = Socket::new(Domain::for_address(host), Type::STREAM, Some(Protocol::TCP))?;
+    socket.set_reuse_address(true)?;
+    socket.bind(&host.into())?;
+    socket.listen(128)?;
+    Ok(socket.into())
+}
+
+fn next_backoff(delay: Duration) -> Duration {
+    (delay * 2).min(Duration::from_secs(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        assert_eq!(
+            next_backoff(Duration::from_millis(250)),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            next_backoff(Duration::from_millis(1500)),
+            Duration::from_secs(2)
+        );
+        assert_eq!(next_backoff(Duration::from_secs(2)), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn metrics_interval_defaults() {
+        assert_eq!(metrics_interval(None, true), Some(Duration::from_secs(5)));
+        assert_eq!(metrics_interval(None, false), None);
+        assert_eq!(metrics_interval(Some(0), true), None);
+        assert_eq!(
+            metrics_interval(Some(2), false),
+            Some(Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn capture_limits_whichever_first() {
+        let secs = Duration::from_secs;
+        assert_eq!(capture_limit_reached(secs(1), 10, None, None), None);
+        assert_eq!(
+            capture_limit_reached(secs(1), 10, Some(secs(30)), Some(10)),
+            Some(StopReason::ByteLimit)
+        );
+        assert_eq!(
+            capture_limit_reached(secs(30), 9, Some(secs(30)), Some(10)),
+            Some(StopReason::Duration)
+        );
+        assert_eq!(
+            capture_limit_reached(secs(29), 9, Some(secs(30)), Some(10)),
+            None
+        );
+    }
+
+    #[test]
+    fn session_summary_line() {
+        let counters = Counters::default();
+        counters.bytes_in.store(12, Ordering::Relaxed);
+        counters.bytes_out.store(3400, Ordering::Relaxed);
+        counters.peak_clients.fetch_max(3, Ordering::Relaxed);
+        counters.peak_clients.fetch_max(1, Ordering::Relaxed);
+        counters.serial_reconnects.store(2, Ordering::Relaxed);
+        assert_eq!(
+            session_summary(Duration::from_secs(3723), &counters),
+            "Session: 1h02m03s | in 12 B (clients -> device) | out 3400 B (device -> clients) | peak 3 client(s) | 2 device reconnect(s)"
+        );
+    }
+
+    #[test]
+    fn poller_fires_at_once_then_every_interval() {
+        let mut poll = Poller::new(Bytes::from_static(b"?\r"), Duration::from_millis(40));
+        assert_eq!(poll.take_due().as_deref(), Some(&b"?\r"[..]));
+        assert_eq!(poll.take_due(), None);
+        assert!(poll.due_in() > Duration::from_millis(20));
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(poll.take_due().is_some());
+    }
+
+    #[test]
+    fn instant_empty_reads_mean_unplugged() {
+        let timeout = Some(Duration::from_millis(200));
+        let mut empty = EmptyReads::default();
+        for _ in 1..EMPTY_READ_LIMIT {
+            assert!(!empty.record(Duration::ZERO, timeout));
+        }
+        assert!(empty.record(Duration::ZERO, timeout));
+
+        // a read that waited out its timeout shows the port is alive
+        let mut empty = EmptyReads::default();
+        for _ in 1..EMPTY_READ_LIMIT {
+            empty.record(Duration::ZERO, timeout);
+        }
+        assert!(!empty.record(Duration::from_millis(200), timeout));
+        assert!(!empty.record(Duration::ZERO, timeout));
+
+        // zero-timeout ports return empty reads while idle
+        let mut empty = EmptyReads::default();
+        for _ in 0..EMPTY_READ_LIMIT * 2 {
+            assert!(!empty.record(Duration::ZERO, Some(Duration::ZERO)));
+            assert!(!empty.record(Duration::ZERO, None));
+        }
+    }
+
+    #[test]
+    fn reconnect_reports_are_thinned() {
+        let reported: Vec<u32> = (1..=25).filter(|&a| should_report_attempt(a, 10)).collect();
+        assert_eq!(reported, vec![1, 10, 20]);
+        assert!(!should_report_attempt(2, 0));
+
+        let (tx, rx) = channel::unbounded();
+        let mut n = ReconnectNotifier::new("Serial: reconnecting reader".into(), 10, true, tx);
+        for _ in 0..3 {
+            n.failed(&anyhow::anyhow!("gone"));
+        }
+        let msgs: Vec<String> = rx.try_iter().collect();
+        assert_eq!(msgs, vec!["Serial: reconnecting reader… (attempt 1): gone"]);
+        assert_eq!(n.succeeded(), 4);
+        assert_eq!(n.succeeded(), 1);
+    }
+
+    // Hands out queued chunks, then times out like an idle serial port
+    struct Trickle {
+        chunks: std::collections::VecDeque<&'static [u8]>,
+        timeout: Duration,
+    }
+
+    impl std::io::Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let Some(chunk) = self.chunks.pop_front() else {
+                return Err(std::io::ErrorKind::TimedOut.into());
+            };
+            let n = chunk.len().min(buf.len());
+            buf[..n].copy_from_slice(&chunk[..n]);
+            Ok(n)
+        }
+    }
+
+    impl std::io::Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl DeviceIo for Trickle {
+        fn read_timeout(&self) -> Option<Duration> {
+            Some(self.timeout)
+        }
+
+        fn set_read_timeout(&mut self, timeout: Duration) -> std::io::Result<()> {
+            self.timeout = timeout;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn coalesce_gathers_until_quiet_or_full() {
+        let mut port = Trickle {
+            chunks: [&b"bc"[..], b"d"].into(),
+            timeout: Duration::from_millis(200),
+        };
+        let mut buf = [0u8; 16];
+        buf[0] = b'a';
+        let n = coalesce_reads(&mut port, &mut buf, 1, Duration::from_millis(50));
+        assert_eq!(&buf[..n], b"abcd");
+        assert_eq!(port.timeout, Duration::from_millis(200));
+
+        port.chunks = [&b"bcdef"[..]].into();
+        let mut small = [b'a', 0, 0];
+        assert_eq!(
+            coalesce_reads(&mut port, &mut small, 1, Duration::from_millis(50)),
+            3
+        );
+        assert_eq!(&small, b"abc");
+    }
+
+    #[test]
+    fn latest_policy_keeps_newest_frame() {
+        let (tx, rx) = channel::unbounded::<Bytes>();
+        for cmd in ["b", "c", "d"] {
+            tx.send(Bytes::from_static(cmd.as_bytes())).unwrap();
+        }
+        let (newest, dropped) = take_latest(Bytes::from_static(b"a"), &rx);
+        assert_eq!(&newest[..], b"d");
+        assert_eq!(dropped, 3);
+        let (only, dropped) = take_latest(Bytes::from_static(b"e"), &rx);
+        assert_eq!((&only[..], dropped), (&b"e"[..], 0));
+    }
+
+    #[test]
+    fn bind_retries_until_port_is_released() {
+        let holder = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = holder.local_addr().unwrap();
+        let err = bind_listener(addr, false, 0).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            drop(holder);
+        });
+        assert!(bind_listener(addr, false, 4).is_ok());
+        release.join().unwrap();
+    }
 }
 
 #[cfg(all(test, target_os = "linux"))]
 mod itests {
     use super::*;
     use std::fs::File;
-    use std::io::{Read, Write};
+    use std::io::{BufRead, Read, Write};
     use std::net::TcpStream;
     use std::os::unix::io::AsRawFd;
     use std::os::unix::io::OwnedFd;
@@ -404,16 +1994,90 @@ mod itests {
         serial_path: String,
         host: &str,
         buffer: usize,
+        frame: crate::cli::FrameMode,
     ) -> (JoinHandle<anyhow::Result<()>>, Arc<AtomicBool>) {
-        let listen = Listen {
+        spawn_listen(test_listen(serial_path, host, buffer, frame))
+    }
+
+    fn test_listen(
+        serial_path: String,
+        host: &str,
+        buffer: usize,
+        frame: crate::cli::FrameMode,
+    ) -> Listen {
+        Listen {
             serial: Some(serial_path),
-            baud: 115_200,
-            host: host.parse().unwrap(),
-            data_bits: crate::cli::DataBitsOpt::Eight,
-            parity: crate::cli::ParityOpt::None,
-            stop_bits: crate::cli::StopBitsOpt::One,
+            upstream: None,
+            #[cfg(feature = "rfc2217")]
+            upstream_rfc2217: None,
+            include_builtin: false,
+            port_match: None,
+            baud_rates: vec![115_200],
+            host: vec![host.to_string()],
+            prefer_ip: crate::cli::IpFamily::V4,
+            line: crate::cli::LineSettings {
+                data_bits: crate::cli::DataBitsOpt::Eight,
+                parity: crate::cli::ParityOpt::None,
+                stop_bits: crate::cli::StopBitsOpt::One,
+            },
             buffer,
-        };
+            scrollback: 0,
+            no_tui: true,
+            tcp_rate: None,
+            client_ingress_rate: None,
+            metrics_interval: None,
+            duration: None,
+            stop_after_bytes: None,
+            wait_for_device: 0,
+            frame,
+            framing: crate::cli::SerialFraming::Raw,
+            bind_retries: 0,
+            reuse_addr: false,
+            inspector_sample_max: None,
+            inspector_hex_columns: None,
+            inspector_buffer: 1024,
+            serial_write_policy: SerialWritePolicy::Fifo,
+            flush_writes: false,
+            dry_run: false,
+            reconnect_log_every: 10,
+            health_addr: None,
+            event_addr: None,
+            nonblocking_serial: false,
+            coalesce_ms: 0,
+            tcp_write_timeout: 10,
+            max_per_ip: None,
+            accept_rate: None,
+            on_connect_script: None,
+            on_connect_every: false,
+            split_logs: None,
+            split_logs_clients: false,
+            tee: None,
+            tap: None,
+            reverse_dns: false,
+            terminal_title: false,
+            stdin_commands: false,
+            inspector_demo: false,
+            event_format: crate::cli::EventFormat::Text,
+            exit_on_disconnect: false,
+            close_when_idle: false,
+            single_controller: false,
+            disconnect_clients_after: None,
+            poll_bytes: None,
+            poll_interval: 1000,
+            strip_prefix: None,
+            strip_suffix: None,
+            add_prefix: None,
+            add_suffix: None,
+            show_caps: false,
+            dtr: None,
+            rts: None,
+            no_baud_check: false,
+            keymap: None,
+            throughput_perspective: crate::cli::ThroughputPerspective::Tcp,
+        }
+    }
+
+    fn spawn_listen(listen: Listen) -> (JoinHandle<anyhow::Result<()>>, Arc<AtomicBool>) {
         let stop = Arc::new(AtomicBool::new(false));
         let stop_clone = stop.clone();
         let handle = std::thread::spawn(move || run_listen_with_shutdown(listen, stop_clone));
@@ -426,7 +2090,7 @@ mod itests {
         let (master_fd, slave_path) = create_pty().expect("pty");
         let mut master: File = master_fd.into();
         let host = "127.0.0.1:6767"; // fixed test port
-        let (handle, stop) = spawn_server(slave_path, host, 64);
+        let (handle, stop) = spawn_server(slave_path, host, 64, crate::cli::FrameMode::Raw);
 
         // connect TCP client
         std::thread::sleep(Duration::from_millis(100));
@@ -454,4 +2118,689 @@ mod itests {
         stop.store(true, Ordering::Relaxed);
         let _ = handle.join().unwrap();
     }
+
+    #[test]
+    fn flushed_writes_reach_the_device() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let host = "127.0.0.1:6784";
+        let mut listen = test_listen(slave_path, host, 64, crate::cli::FrameMode::Raw);
+        listen.flush_writes = true;
+        let (handle, stop) = spawn_listen(listen);
+        let mut tcp = loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+
+        for round in [&b"AT\r"[..], b"ATI\r"] {
+            tcp.write_all(round).unwrap();
+            let mut buf = vec![0u8; round.len()];
+            master.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, round);
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn length_prefixed_round_trip() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let host = "127.0.0.1:6768";
+        let (handle, stop) =
+            spawn_server(slave_path, host, 64, crate::cli::FrameMode::LengthPrefix);
+
+        std::thread::sleep(Duration::from_millis(100));
+        let mut tcp = loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+
+        // Header is stripped before the device sees the payload
+        tcp.write_all(&crate::net::frame::encode(b"hi")).unwrap();
+        let mut serial_buf = [0u8; 2];
+        master.read_exact(&mut serial_buf).unwrap();
+        assert_eq!(&serial_buf, b"hi");
+
+        master.write_all(b"ok").unwrap();
+        let mut len = [0u8; 4];
+        tcp.read_exact(&mut len).unwrap();
+        let mut payload = vec![0u8; u32::from_le_bytes(len) as usize];
+        tcp.read_exact(&mut payload).unwrap();
+        assert_eq!(payload, b"ok");
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn dry_run_checks_device_and_bind() {
+        let (_master, slave_path) = create_pty().expect("pty");
+        let mut listen = test_listen(slave_path, "127.0.0.1:0", 64, crate::cli::FrameMode::Raw);
+        listen.dry_run = true;
+        run_listen_with_shutdown(listen.clone(), Arc::new(AtomicBool::new(false))).unwrap();
+
+        // flag clashes and unreadable files fail the dry run, not the real launch
+        let mut clash = listen.clone();
+        clash.tee = Some(crate::cli::TeeFormat::Raw);
+        clash.event_format = EventFormat::Json;
+        assert!(run_listen_with_shutdown(clash, Arc::new(AtomicBool::new(false))).is_err());
+        let mut script = listen.clone();
+        script.on_connect_script = Some("/nonexistent/sergw-wake.bin".into());
+        assert!(run_listen_with_shutdown(script, Arc::new(AtomicBool::new(false))).is_err());
+
+        let taken = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listen.host = vec![taken.local_addr().unwrap().to_string()];
+        let err =
+            run_listen_with_shutdown(listen.clone(), Arc::new(AtomicBool::new(false))).unwrap_err();
+        assert_eq!(crate::exit_code_for_error(&err), 4);
+
+        listen.serial = Some("/dev/sergw-does-not-exist".into());
+        let err = run_listen_with_shutdown(listen, Arc::new(AtomicBool::new(false))).unwrap_err();
+        assert_eq!(crate::exit_code_for_error(&err), 5);
+    }
+
+    #[test]
+    fn stuck_client_is_closed_after_write_timeout() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let host = "127.0.0.1:6770";
+        let mut listen = test_listen(slave_path, host, 1 << 20, crate::cli::FrameMode::Raw);
+        listen.tcp_write_timeout = 1;
+        listen.event_addr = Some("127.0.0.1:6771".parse().unwrap());
+        let (handle, stop) = spawn_listen(listen);
+        let events = loop {
+            match TcpStream::connect("127.0.0.1:6771") {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        events
+            .set_read_timeout(Some(Duration::from_secs(15)))
+            .unwrap();
+
+        // A small receive buffer so the flood below outruns it whatever the host's limits
+        let addr: std::net::SocketAddr = host.parse().unwrap();
+        let _tcp: TcpStream = loop {
+            let socket =
+                socket2::Socket::new(socket2::Domain::IPV4, socket2::Type::STREAM, None).unwrap();
+            socket.set_recv_buffer_size(4096).unwrap();
+            match socket.connect(&addr.into()) {
+                Ok(()) => break socket.into(),
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        // Never read while the device floods, so the client's window closes.
+        // The queue is big enough that broadcast never drops it for being slow.
+        std::thread::sleep(Duration::from_millis(100));
+        // Not joined: once the bridge stops reading the PTY, the last writes may block
+        std::thread::spawn(move || {
+            let chunk = [b'x'; 65536];
+            for _ in 0..512 {
+                if master.write_all(&chunk).is_err() {
+                    break;
+                }
+            }
+        });
+        // A stalled write only gives up after a whole timeout without progress
+        let mut events = std::io::BufReader::new(events);
+        let mut line = String::new();
+        while events.read_line(&mut line).unwrap() > 0 && !line.starts_with("Disconnected: ") {
+            line.clear();
+        }
+        assert!(line.starts_with("Disconnected: "));
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn exit_on_disconnect_stops_with_its_own_code() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut listen = test_listen(slave_path, "127.0.0.1:6773", 64, crate::cli::FrameMode::Raw);
+        listen.exit_on_disconnect = true;
+        let (handle, _stop) = spawn_listen(listen);
+        std::thread::sleep(Duration::from_millis(300));
+
+        // Closing the master end makes reads on the device fail, like an unplug
+        drop(master_fd);
+        let err = handle.join().unwrap().unwrap_err();
+        assert!(err.downcast_ref::<DeviceDisconnected>().is_some());
+        assert_eq!(crate::exit_code_for_error(&err), 6);
+    }
+
+    #[test]
+    fn per_ip_limit_refuses_extra_clients() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let host = "127.0.0.1:6772";
+        let mut listen = test_listen(slave_path, host, 64, crate::cli::FrameMode::Raw);
+        listen.max_per_ip = Some(1);
+        let (handle, stop) = spawn_listen(listen);
+        let connect = || loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+
+        // Poke the device until a client hears it, which proves it is registered
+        let mut registered = |client: &mut TcpStream| {
+            client
+                .set_read_timeout(Some(Duration::from_millis(100)))
+                .unwrap();
+            let mut buf = [0u8; 1];
+            for _ in 0..50 {
+                master.write_all(b"a").unwrap();
+                if matches!(client.read(&mut buf), Ok(1)) {
+                    return;
+                }
+            }
+            panic!("client never registered");
+        };
+        let mut first = connect();
+        registered(&mut first);
+
+        let mut second = connect();
+        second
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buf = [0u8; 1];
+        assert_eq!(second.read(&mut buf).unwrap(), 0);
+
+        // The slot frees up when the first client leaves
+        drop(first);
+        std::thread::sleep(Duration::from_millis(300));
+        let mut third = connect();
+        registered(&mut third);
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn accept_rate_closes_the_excess() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let host = "127.0.0.1:6783";
+        let mut listen = test_listen(slave_path, host, 64, crate::cli::FrameMode::Raw);
+        listen.accept_rate = Some(1);
+        let (handle, stop) = spawn_listen(listen);
+        let connect = || loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        let mut registered = |client: &mut TcpStream| {
+            client
+                .set_read_timeout(Some(Duration::from_millis(100)))
+                .unwrap();
+            let mut buf = [0u8; 1];
+            for _ in 0..50 {
+                master.write_all(b"a").unwrap();
+                if matches!(client.read(&mut buf), Ok(1)) {
+                    return;
+                }
+            }
+            panic!("client never registered");
+        };
+
+        // The one-connection burst is spent on the first client
+        let mut first = connect();
+        let mut second = connect();
+        registered(&mut first);
+        second
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut buf = [0u8; 1];
+        assert_eq!(second.read(&mut buf).unwrap(), 0);
+
+        // and a second later there is room again
+        std::thread::sleep(Duration::from_millis(1100));
+        let mut third = connect();
+        registered(&mut third);
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn every_host_shares_the_device() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let hosts = ["127.0.0.1:6774", "127.0.0.1:6775"];
+        let mut listen = test_listen(slave_path, hosts[0], 64, crate::cli::FrameMode::Raw);
+        listen.host.push(hosts[1].to_string());
+        let (handle, stop) = spawn_listen(listen);
+        let mut clients: Vec<TcpStream> = hosts
+            .iter()
+            .map(|host| loop {
+                match TcpStream::connect(host) {
+                    Ok(s) => break s,
+                    Err(_) => std::thread::sleep(Duration::from_millis(50)),
+                }
+            })
+            .collect();
+
+        // Both ports hear the device once registered
+        for client in clients.iter_mut() {
+            client
+                .set_read_timeout(Some(Duration::from_millis(100)))
+                .unwrap();
+            let mut buf = [0u8; 1];
+            let heard = (0..50).any(|_| {
+                master.write_all(b"a").unwrap();
+                matches!(client.read(&mut buf), Ok(1))
+            });
+            assert!(heard, "client never registered");
+        }
+
+        // ...and both reach it
+        for (client, msg) in clients.iter_mut().zip([b"one", b"two"]) {
+            client.write_all(msg).unwrap();
+            let mut got = [0u8; 3];
+            master.read_exact(&mut got).unwrap();
+            assert_eq!(&got, msg);
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn polls_the_device_without_clients() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let mut listen = test_listen(slave_path, "127.0.0.1:6776", 64, crate::cli::FrameMode::Raw);
+        listen.poll_bytes = Some(Bytes::from_static(b"?\r"));
+        listen.poll_interval = 50;
+        let (handle, stop) = spawn_listen(listen);
+
+        // The master reads EIO until sergw has the slave open
+        let mut got = Vec::new();
+        let mut buf = [0u8; 16];
+        while got.len() < 6 {
+            match master.read(&mut buf) {
+                Ok(n) => got.extend_from_slice(&buf[..n]),
+                Err(_) => std::thread::sleep(Duration::from_millis(20)),
+            }
+        }
+        assert_eq!(&got[..6], b"?\r?\r?\r");
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn affixes_unwrap_device_frames_and_wrap_client_writes() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let host = "127.0.0.1:6777";
+        let mut listen = test_listen(slave_path, host, 64, crate::cli::FrameMode::LengthPrefix);
+        listen.strip_prefix = Some(Bytes::from_static(b"\x02"));
+        listen.strip_suffix = Some(Bytes::from_static(b"\x03"));
+        listen.add_prefix = Some(Bytes::from_static(b"\x02"));
+        listen.add_suffix = Some(Bytes::from_static(b"\x03"));
+        let (handle, stop) = spawn_listen(listen);
+
+        let mut tcp = loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        tcp.write_all(&crate::net::frame::encode(b"GO")).unwrap();
+        let mut serial_buf = [0u8; 4];
+        master.read_exact(&mut serial_buf).unwrap();
+        assert_eq!(&serial_buf, b"\x02GO\x03");
+
+        // envelopes split and merged across serial reads still come out whole
+        master.write_all(b"\x02O").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        master.write_all(b"K\x03\x02E").unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        master.write_all(b"R\x03").unwrap();
+        let mut tcp_buf = [0u8; 12];
+        tcp.read_exact(&mut tcp_buf).unwrap();
+        assert_eq!(&tcp_buf, b"\x02\0\0\0OK\x02\0\0\0ER");
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn cobs_packets_are_decoded_and_client_writes_encoded() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let host = "127.0.0.1:6780";
+        let mut listen = test_listen(slave_path, host, 64, crate::cli::FrameMode::LengthPrefix);
+        listen.framing = SerialFraming::Cobs;
+        let (handle, stop) = spawn_listen(listen);
+
+        let mut tcp = loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        // one COBS packet per client message
+        tcp.write_all(&crate::net::frame::encode(b"A\0B")).unwrap();
+        tcp.write_all(&crate::net::frame::encode(b"C")).unwrap();
+        let mut serial_buf = [0u8; 8];
+        master.read_exact(&mut serial_buf).unwrap();
+        assert_eq!(&serial_buf, &[2, b'A', 2, b'B', 0, 2, b'C', 0]);
+
+        // a packet split over two writes, then a malformed one, then another
+        master.write_all(&[3, b'O', b'K']).unwrap();
+        std::thread::sleep(Duration::from_millis(50));
+        master.write_all(&[0, 9, b'x', 0, 2, b'!', 0]).unwrap();
+        let mut read_packet = || {
+            let mut len = [0u8; 4];
+            tcp.read_exact(&mut len).unwrap();
+            let mut packet = vec![0u8; u32::from_le_bytes(len) as usize];
+            tcp.read_exact(&mut packet).unwrap();
+            packet
+        };
+        assert_eq!(read_packet(), b"OK");
+        assert_eq!(read_packet(), b"!");
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn close_when_idle_releases_the_device_between_clients() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let host = "127.0.0.1:6781";
+        let mut listen = test_listen(slave_path, host, 64, crate::cli::FrameMode::Raw);
+        listen.close_when_idle = true;
+        let (handle, stop) = spawn_listen(listen);
+
+        // The master reports a hangup while nothing has the slave open
+        let slave_open = |master: &File| {
+            let mut pfd = libc::pollfd {
+                fd: master.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: one valid pollfd, no timeout
+            unsafe { libc::poll(&mut pfd, 1, 0) };
+            pfd.revents & libc::POLLHUP == 0
+        };
+        let wait_for = |master: &File, open: bool| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while slave_open(master) != open {
+                assert!(Instant::now() < deadline, "device never became open={open}");
+                std::thread::sleep(Duration::from_millis(20));
+            }
+        };
+
+        for round in [b"one", b"two"] {
+            let mut tcp = loop {
+                match TcpStream::connect(host) {
+                    Ok(s) => break s,
+                    Err(_) => std::thread::sleep(Duration::from_millis(50)),
+                }
+            };
+            wait_for(&master, true);
+            tcp.write_all(round).unwrap();
+            let mut got = [0u8; 3];
+            master.read_exact(&mut got).unwrap();
+            assert_eq!(&got, round);
+            drop(tcp);
+            wait_for(&master, false);
+        }
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn single_controller_hands_over_on_disconnect() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let host = "127.0.0.1:6782";
+        let mut listen = test_listen(slave_path, host, 64, crate::cli::FrameMode::Raw);
+        listen.single_controller = true;
+        let (handle, stop) = spawn_listen(listen);
+
+        let mut first = loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        let mut got = [0u8; 2];
+        first.write_all(b"aa").unwrap();
+        master.read_exact(&mut got).unwrap();
+        assert_eq!(&got, b"aa");
+
+        // the observer's write never reaches the device...
+        let mut second = TcpStream::connect(host).unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        second.write_all(b"bb").unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+
+        // ...until the controller leaves and it is promoted
+        drop(first);
+        std::thread::sleep(Duration::from_millis(400));
+        second.write_all(b"BB").unwrap();
+        master.read_exact(&mut got).unwrap();
+        assert_eq!(&got, b"BB");
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn on_connect_script_goes_to_the_device_once() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let script = std::env::temp_dir().join(format!("sergw-on-connect-{}", std::process::id()));
+        std::fs::write(&script, b"ATZ\r").unwrap();
+        let host = "127.0.0.1:6778";
+        let mut listen = test_listen(slave_path, host, 64, crate::cli::FrameMode::Raw);
+        listen.on_connect_script = Some(script.clone());
+        let (handle, stop) = spawn_listen(listen);
+
+        let connect = || loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        let mut first = connect();
+        first.write_all(b"1").unwrap();
+        let mut buf = [0u8; 5];
+        master.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ATZ\r1");
+
+        // the second client's bytes arrive without another copy of the script
+        let mut second = connect();
+        second.write_all(b"2").unwrap();
+        let mut buf = [0u8; 1];
+        master.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"2");
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+        let _ = std::fs::remove_file(script);
+    }
+
+    #[test]
+    fn tap_gets_a_copy_without_any_client() {
+        let collector = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let mut listen = test_listen(slave_path, "127.0.0.1:6779", 64, crate::cli::FrameMode::Raw);
+        listen.tap = Some(collector.local_addr().unwrap().to_string());
+        let (handle, stop) = spawn_listen(listen);
+
+        let (mut tap, _) = collector.accept().unwrap();
+        tap.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        master.write_all(b"tapped").unwrap();
+        let mut buf = [0u8; 6];
+        tap.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"tapped");
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn fans_out_to_upstream() {
+        let upstream = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = "127.0.0.1:6769";
+        let mut listen = test_listen(String::new(), host, 64, crate::cli::FrameMode::Raw);
+        listen.serial = None;
+        listen.upstream = Some(upstream.local_addr().unwrap().to_string());
+        let (handle, stop) = spawn_listen(listen);
+        let (mut device, _) = upstream.accept().unwrap();
+
+        let mut tcp = loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+
+        tcp.write_all(b"up").unwrap();
+        let mut buf = [0u8; 2];
+        device.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"up");
+
+        device.write_all(b"down").unwrap();
+        let mut buf = [0u8; 4];
+        tcp.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"down");
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn upstream_reconnects_with_a_single_connection() {
+        let upstream = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = "127.0.0.1:6785";
+        let mut listen = test_listen(String::new(), host, 64, crate::cli::FrameMode::Raw);
+        listen.serial = None;
+        listen.upstream = Some(upstream.local_addr().unwrap().to_string());
+        let (handle, stop) = spawn_listen(listen);
+        drop(upstream.accept().unwrap());
+
+        // the reader notices and redials; the writer gets its half of that one
+        let (mut device, _) = upstream.accept().unwrap();
+        let mut tcp = loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        tcp.write_all(b"up").unwrap();
+        let mut buf = [0u8; 2];
+        device.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"up");
+
+        upstream.set_nonblocking(true).unwrap();
+        std::thread::sleep(Duration::from_millis(1500));
+        assert!(
+            upstream.accept().is_err(),
+            "a second upstream connection was opened"
+        );
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[cfg(feature = "rfc2217")]
+    #[test]
+    fn rfc2217_reconnects_with_a_single_session() {
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = "127.0.0.1:6786";
+        let mut listen = test_listen(String::new(), host, 64, crate::cli::FrameMode::Raw);
+        listen.serial = None;
+        listen.upstream_rfc2217 = Some(server.local_addr().unwrap().to_string());
+        let negotiation = crate::net::rfc2217::negotiation(115_200, &listen.line);
+        let (handle, stop) = spawn_listen(listen);
+        drop(server.accept().unwrap());
+
+        let (mut session, _) = server.accept().unwrap();
+        let mut buf = vec![0u8; negotiation.len()];
+        session.read_exact(&mut buf).unwrap();
+        let mut tcp = loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        tcp.write_all(b"up").unwrap();
+        let mut buf = [0u8; 2];
+        session.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"up");
+
+        server.set_nonblocking(true).unwrap();
+        std::thread::sleep(Duration::from_millis(1500));
+        assert!(
+            server.accept().is_err(),
+            "a second RFC 2217 session was opened"
+        );
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn unfinished_hello_prefix_reaches_the_device() {
+        let (master_fd, slave_path) = create_pty().expect("pty");
+        let mut master: File = master_fd.into();
+        let host = "127.0.0.1:6787";
+        let listen = test_listen(slave_path, host, 64, crate::cli::FrameMode::Raw);
+        let (handle, stop) = spawn_listen(listen);
+
+        let mut tcp = loop {
+            match TcpStream::connect(host) {
+                Ok(s) => break s,
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        };
+        // could be the start of "SERGW-", so it waits for the client to go quiet
+        tcp.write_all(b"SER").unwrap();
+        let mut serial_buf = [0u8; 3];
+        master.read_exact(&mut serial_buf).unwrap();
+        assert_eq!(&serial_buf, b"SER");
+
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join().unwrap();
+    }
+
+    #[test]
+    fn waiting_for_a_missing_device_can_be_stopped() {
+        let host = "127.0.0.1:6788";
+        let mut listen = test_listen(
+            "/dev/sergw-test-missing".into(),
+            host,
+            64,
+            crate::cli::FrameMode::Raw,
+        );
+        listen.wait_for_device = 60;
+        let (handle, stop) = spawn_listen(listen);
+
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(
+            !handle.is_finished(),
+            "gave up before --wait-for-device ran out"
+        );
+        let stopped = Instant::now();
+        stop.store(true, Ordering::Relaxed);
+        let err = handle.join().unwrap().unwrap_err();
+        assert!(stopped.elapsed() < Duration::from_secs(1));
+        assert!(format!("{err:#}").contains("Stopped while waiting for device"));
+    }
 }
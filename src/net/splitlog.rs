@@ -0,0 +1,117 @@
+// `--split-logs <DIR>`: raw traffic appended to one file per source, so a
+// capture can be taken apart afterwards without demultiplexing. The device's
+// output goes to `<device>.log` (e.g. `ttyUSB0.log`); with
+// `--split-logs-clients` each client's writes go to `client-<ip>_<port>.log`.
+// Files are opened on the first byte from their source and appended to; a
+// client's file is closed again when it disconnects.
+
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use tracing::warn;
+
+pub struct SplitLogs {
+    dir: PathBuf,
+    /// `None` once a source's file failed, so the error is reported only once
+    files: Mutex<HashMap<String, Option<File>>>,
+}
+
+impl SplitLogs {
+    pub fn new(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Creating split log directory {}", dir.display()))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            files: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn device(&self, name: &str, data: &[u8]) {
+        self.append(&file_name(name), data);
+    }
+
+    pub fn client(&self, addr: SocketAddr, data: &[u8]) {
+        self.append(&file_name(&format!("client-{addr}")), data);
+    }
+
+    /// Close a disconnected client's file; it is reopened if the address returns
+    pub fn close_client(&self, addr: SocketAddr) {
+        if let Ok(mut files) = self.files.lock() {
+            files.remove(&file_name(&format!("client-{addr}")));
+        }
+    }
+
+    fn append(&self, file: &str, data: &[u8]) {
+        let Ok(mut files) = self.files.lock() else {
+            return;
+        };
+        let slot = files.entry(file.to_string()).or_insert_with(|| {
+            let path = self.dir.join(file);
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| warn!(?e, path = %path.display(), "Failed to open split log"))
+                .ok()
+        });
+        if let Some(f) = slot {
+            if let Err(e) = f.write_all(data) {
+                warn!(?e, file, "Failed to write split log, closing it");
+                *slot = None;
+            }
+        }
+    }
+}
+
+/// `<source>.log` with anything unsafe in a file name replaced by `_`
+fn file_name(source: &str) -> String {
+    let stem: String = source
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '.' => c,
+            _ => '_',
+        })
+        .collect();
+    format!("{stem}.log")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_file_safe() {
+        assert_eq!(file_name("ttyUSB0"), "ttyUSB0.log");
+        assert_eq!(
+            file_name("client-127.0.0.1:5000"),
+            "client-127.0.0.1_5000.log"
+        );
+        assert_eq!(file_name("client-[::1]:9"), "client-___1__9.log");
+    }
+
+    #[test]
+    fn files_appear_on_first_byte_and_append() {
+        let dir = std::env::temp_dir().join(format!("sergw-split-{}", std::process::id()));
+        let logs = SplitLogs::new(&dir).unwrap();
+        let device = dir.join("ttyUSB0.log");
+        let client = dir.join("client-10.0.0.2_4000.log");
+        assert!(!device.exists());
+
+        logs.device("ttyUSB0", b"he");
+        logs.device("ttyUSB0", b"llo");
+        logs.client("10.0.0.2:4000".parse().unwrap(), b"AT\r");
+        assert_eq!(std::fs::read(&device).unwrap(), b"hello");
+        assert_eq!(std::fs::read(&client).unwrap(), b"AT\r");
+
+        logs.close_client("10.0.0.2:4000".parse().unwrap());
+        assert_eq!(logs.files.lock().unwrap().len(), 1);
+        logs.client("10.0.0.2:4000".parse().unwrap(), b"Z");
+        assert_eq!(std::fs::read(&client).unwrap(), b"AT\rZ");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
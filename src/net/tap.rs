@@ -0,0 +1,85 @@
+// `--tap <HOST:PORT>`: sergw dials out to a collector and sends it a copy of
+// everything broadcast to clients. The tap is write-only (nothing it sends is
+// read) and is redialled whenever it drops; frames that arrive while it is
+// down are discarded rather than replayed.
+
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use crossbeam_channel as channel;
+use tracing::info;
+
+use crate::cli::IpFamily;
+use crate::net::resolve::resolve_host;
+
+const TAP_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const TAP_RETRY: Duration = Duration::from_secs(1);
+// How often an idle tap checks the stop flag
+const TAP_IDLE: Duration = Duration::from_millis(200);
+
+pub struct TapOptions {
+    pub target: String,
+    pub prefer: IpFamily,
+    /// Give up on a write that makes no progress for this long (None: never)
+    pub write_timeout: Option<Duration>,
+}
+
+fn connect(options: &TapOptions) -> Result<TcpStream> {
+    let addr = resolve_host(&options.target, options.prefer)?;
+    let stream = TcpStream::connect_timeout(&addr, TAP_CONNECT_TIMEOUT)
+        .with_context(|| format!("Connecting to tap {addr}"))?;
+    stream.set_nodelay(true).ok();
+    stream
+        .set_write_timeout(options.write_timeout)
+        .context("Setting tap write timeout")?;
+    Ok(stream)
+}
+
+/// Keep a connection to the tap open until `stop`, forwarding frames from `rx`
+pub fn spawn_tap(
+    options: TapOptions,
+    rx: channel::Receiver<Bytes>,
+    stop: Arc<AtomicBool>,
+    status_tx: channel::Sender<String>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let target = &options.target;
+        // only the first failure of an outage is reported
+        let mut reported = false;
+        while !stop.load(Ordering::Relaxed) {
+            let mut stream = match connect(&options) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    if !reported {
+                        let _ = status_tx.send(format!("Tap: {e:#}, retrying every second"));
+                        reported = true;
+                    }
+                    // drop what queued up while the tap is away
+                    while rx.try_recv().is_ok() {}
+                    thread::sleep(TAP_RETRY);
+                    continue;
+                }
+            };
+            reported = false;
+            info!(%target, "Tap connected");
+            let _ = status_tx.send(format!("Tap: connected to {target}"));
+            while !stop.load(Ordering::Relaxed) {
+                let frame = match rx.recv_timeout(TAP_IDLE) {
+                    Ok(frame) => frame,
+                    Err(channel::RecvTimeoutError::Timeout) => continue,
+                    Err(channel::RecvTimeoutError::Disconnected) => return,
+                };
+                if let Err(e) = stream.write_all(&frame) {
+                    let _ = status_tx.send(format!("Tap: lost {target} ({e}), redialling"));
+                    break;
+                }
+            }
+        }
+    })
+}
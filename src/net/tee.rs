@@ -0,0 +1,84 @@
+// `--tee raw|hex`: the device's output is copied to stdout as it is read, so a
+// headless bridge can be piped into other tools while it serves clients. Raw
+// passes the bytes through untouched; hex writes one line per read. Once a
+// write fails (usually the reading end of the pipe closed) teeing stops and
+// bridging carries on.
+
+use std::io::{self, Write};
+
+use tracing::warn;
+
+use crate::cli::TeeFormat;
+
+pub struct Tee<W: Write> {
+    /// `None` after a failed write
+    out: Option<W>,
+    format: TeeFormat,
+}
+
+impl Tee<io::Stdout> {
+    pub fn stdout(format: TeeFormat) -> Self {
+        Self::new(io::stdout(), format)
+    }
+}
+
+impl<W: Write> Tee<W> {
+    pub fn new(out: W, format: TeeFormat) -> Self {
+        Self {
+            out: Some(out),
+            format,
+        }
+    }
+
+    pub fn write(&mut self, data: &[u8]) {
+        let Some(out) = self.out.as_mut() else {
+            return;
+        };
+        let written = match self.format {
+            TeeFormat::Raw => out.write_all(data),
+            TeeFormat::Hex => {
+                let line: Vec<String> = data.iter().map(|b| format!("{b:02x}")).collect();
+                writeln!(out, "{}", line.join(" "))
+            }
+        };
+        if let Err(e) = written.and_then(|()| out.flush()) {
+            warn!(?e, "Writing --tee output failed, no longer teeing");
+            self.out = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_each_read() {
+        let mut raw = Tee::new(Vec::new(), TeeFormat::Raw);
+        raw.write(b"AT\r\n");
+        raw.write(&[0x00, 0xff]);
+        assert_eq!(raw.out.unwrap(), b"AT\r\n\x00\xff");
+
+        let mut hex = Tee::new(Vec::new(), TeeFormat::Hex);
+        hex.write(b"AT\r\n");
+        hex.write(&[0x00, 0xff]);
+        assert_eq!(hex.out.unwrap(), b"41 54 0d 0a\n00 ff\n");
+    }
+
+    #[test]
+    fn stops_after_a_failed_write() {
+        struct Closed;
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut tee = Tee::new(Closed, TeeFormat::Raw);
+        tee.write(b"x");
+        assert!(tee.out.is_none());
+        tee.write(b"y");
+    }
+}
@@ -0,0 +1,137 @@
+use std::time::{Duration, Instant};
+
+/// Classic token bucket: refills at `rate` tokens per second up to a one-second burst.
+pub struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate_per_sec: u64) -> Self {
+        Self {
+            rate: rate_per_sec as f64,
+            tokens: rate_per_sec as f64,
+            last: Instant::now(),
+        }
+    }
+
+    pub fn rate(&self) -> u64 {
+        self.rate as u64
+    }
+
+    pub fn try_take(&mut self, n: u64) -> bool {
+        self.try_take_at(n, Instant::now())
+    }
+
+    pub fn try_take_at(&mut self, n: u64, now: Instant) -> bool {
+        let dt = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        self.tokens = (self.tokens + dt * self.rate).min(self.rate);
+        // Frames larger than the burst pass once the bucket is full and leave
+        // it in debt, so they are paced rather than starved forever.
+        let needed = (n as f64).min(self.rate);
+        if self.tokens >= needed {
+            self.tokens -= n as f64;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Seconds in a row over the limit before a source counts as flooding
+const SUSTAINED_SECS: u32 = 3;
+
+/// Measures a byte rate over one-second windows and reports a source that
+/// stays above `limit` for `SUSTAINED_SECS` of them in a row; short bursts pass.
+pub struct RateWatch {
+    limit: u64,
+    window_start: Instant,
+    bytes: u64,
+    over: u32,
+}
+
+impl RateWatch {
+    pub fn new(limit: u64) -> Self {
+        Self {
+            limit,
+            window_start: Instant::now(),
+            bytes: 0,
+            over: 0,
+        }
+    }
+
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Count `n` bytes; true once the rate has been over the limit for long enough
+    pub fn feed(&mut self, n: u64) -> bool {
+        self.feed_at(n, Instant::now())
+    }
+
+    pub fn feed_at(&mut self, n: u64, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= Duration::from_secs(1) {
+            // a window stretched by a quiet spell averages out below the limit
+            if self.bytes as f64 / elapsed.as_secs_f64() > self.limit as f64 {
+                self.over += 1;
+            } else {
+                self.over = 0;
+            }
+            self.window_start = now;
+            self.bytes = 0;
+        }
+        self.bytes += n;
+        self.over >= SUSTAINED_SECS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allows_burst_then_refills() {
+        let t0 = Instant::now();
+        let mut b = TokenBucket::new(100);
+        b.last = t0;
+        assert!(b.try_take_at(60, t0));
+        assert!(!b.try_take_at(60, t0));
+        assert!(b.try_take_at(60, t0 + Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn oversized_frame_passes_when_full_and_leaves_debt() {
+        let t0 = Instant::now();
+        let mut b = TokenBucket::new(10);
+        b.last = t0;
+        assert!(b.try_take_at(50, t0));
+        // 40 tokens of debt: one second of refill is not enough
+        assert!(!b.try_take_at(1, t0 + Duration::from_secs(1)));
+        assert!(b.try_take_at(1, t0 + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn rate_watch_needs_a_sustained_flood() {
+        let t0 = Instant::now();
+        let mut w = RateWatch::new(100);
+        w.window_start = t0;
+        let at = |ms| t0 + Duration::from_millis(ms);
+        // a one-second burst, then quiet: never reported
+        assert!(!w.feed_at(500, at(0)));
+        assert!(!w.feed_at(10, at(1000)));
+        assert!(!w.feed_at(10, at(2000)));
+        // 200 B/s for three full seconds
+        let mut flagged = false;
+        for tick in 0..40 {
+            flagged = w.feed_at(50, at(3000 + tick * 250));
+            if flagged {
+                assert!(tick * 250 >= 3000);
+                break;
+            }
+        }
+        assert!(flagged);
+    }
+}
@@ -0,0 +1,165 @@
+// `--baud 9600,115200,921600`: before serving clients, each rate is tried in
+// turn and the first one whose output reads as text is kept; when none does,
+// the first rate is used. A rate is judged on up to PROBE_BYTES read within
+// PROBE_WINDOW, with the wrong-baud check's printable measure, so a device
+// that talks binary (or says nothing unprompted) always ends up on the first.
+// The port is opened as the bridge opens it, `--dtr`/`--rts` included, so a
+// device those lines hold in reset is not probed while it is held.
+
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serialport::{ClearBuffer, SerialPort};
+use tracing::warn;
+
+use crate::cli::Listen;
+use crate::net::device::set_modem_lines;
+use crate::serial::noise::NoiseReport;
+
+const PROBE_WINDOW: Duration = Duration::from_secs(1);
+const PROBE_BYTES: usize = 256;
+// Fewer bytes than this says nothing either way
+const MIN_BYTES: usize = 16;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct BaudChoice {
+    pub baud: u32,
+    /// False when no candidate looked right and the first was taken
+    pub readable: bool,
+}
+
+impl BaudChoice {
+    /// "settled on 115200 baud (first readable of 9600, 115200)"
+    pub fn describe(&self, rates: &[u32]) -> String {
+        let tried: Vec<String> = rates.iter().map(u32::to_string).collect();
+        let tried = tried.join(", ");
+        match self.readable {
+            true => format!("settled on {} baud (first readable of {tried})", self.baud),
+            false => format!("no readable output at {tried} baud; using {}", self.baud),
+        }
+    }
+}
+
+/// Take the first of `rates` whose sample looks like text; rates left when
+/// `stop` is set are not tried
+pub fn choose_baud(
+    rates: &[u32],
+    stop: &AtomicBool,
+    mut sample: impl FnMut(u32) -> Result<Vec<u8>>,
+) -> BaudChoice {
+    for &baud in rates {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        match sample(baud) {
+            Ok(data) if data.len() >= MIN_BYTES && NoiseReport::of(&data).is_readable() => {
+                return BaudChoice {
+                    baud,
+                    readable: true,
+                };
+            }
+            Ok(_) => {}
+            Err(e) => warn!(baud, "Trying baud rate failed: {e:#}"),
+        }
+    }
+    BaudChoice {
+        baud: rates.first().copied().unwrap_or(115_200),
+        readable: false,
+    }
+}
+
+/// What the device sends in the first `PROBE_WINDOW` at `baud`, cut short by `stop`
+pub fn sample_at(path: &str, baud: u32, listen: &Listen, stop: &AtomicBool) -> Result<Vec<u8>> {
+    let builder = serialport::new(path, baud);
+    #[cfg(target_os = "linux")]
+    let port = crate::serial::configure_serial_native(builder, &listen.line);
+    #[cfg(not(target_os = "linux"))]
+    let port = crate::serial::configure_serial(builder, &listen.line);
+    let mut port = port.with_context(|| format!("Opening serial port {path} at {baud} baud"))?;
+    set_modem_lines(&mut port, path, listen)?;
+    // anything already queued arrived at the previous rate
+    port.clear(ClearBuffer::Input).ok();
+    let started = Instant::now();
+    let mut data = Vec::new();
+    let mut buf = [0u8; PROBE_BYTES];
+    while started.elapsed() < PROBE_WINDOW
+        && data.len() < PROBE_BYTES
+        && !stop.load(Ordering::Relaxed)
+    {
+        match port.read(&mut buf) {
+            Ok(n) => data.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {}
+            Err(e) => return Err(e).with_context(|| format!("Reading {path} at {baud} baud")),
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_readable_rate_wins() {
+        let line = b"temp=21.5 hum=40%\r\n".repeat(4);
+        let garbage: Vec<u8> = (0..64u32).map(|i| (i * 97 % 251) as u8 | 0x80).collect();
+        let mut tried = Vec::new();
+        let choice = choose_baud(&[9600, 115_200, 921_600], &AtomicBool::new(false), |baud| {
+            tried.push(baud);
+            Ok(match baud {
+                9600 => garbage.clone(),
+                _ => line.clone(),
+            })
+        });
+        assert_eq!(
+            choice,
+            BaudChoice {
+                baud: 115_200,
+                readable: true
+            }
+        );
+        assert_eq!(tried, [9600, 115_200]);
+        assert_eq!(
+            choice.describe(&[9600, 115_200, 921_600]),
+            "settled on 115200 baud (first readable of 9600, 115200, 921600)"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_first_rate() {
+        let choice = choose_baud(
+            &[9600, 115_200],
+            &AtomicBool::new(false),
+            |baud| match baud {
+                9600 => Ok(b"ok".to_vec()),
+                _ => Err(anyhow::anyhow!("rate rejected")),
+            },
+        );
+        assert_eq!(
+            choice,
+            BaudChoice {
+                baud: 9600,
+                readable: false
+            }
+        );
+        assert_eq!(
+            choice.describe(&[9600, 115_200]),
+            "no readable output at 9600, 115200 baud; using 9600"
+        );
+    }
+
+    #[test]
+    fn stop_ends_the_search() {
+        let stop = AtomicBool::new(false);
+        let mut tried = Vec::new();
+        let choice = choose_baud(&[9600, 115_200], &stop, |baud| {
+            tried.push(baud);
+            stop.store(true, Ordering::Relaxed);
+            Ok(Vec::new())
+        });
+        assert_eq!(tried, [9600]);
+        assert!(!choice.readable);
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::cli::{Bench, ReportFormat};
+use crate::metrics::ThroughputAverager;
+use crate::serial::{configure_serial, select_serial_port};
+
+#[derive(Debug, Default, Serialize)]
+pub struct BenchReport {
+    pub port: String,
+    pub baud: u32,
+    pub seconds: f64,
+    pub total_bytes: u64,
+    pub reads: u64,
+    pub empty_reads: u64,
+    pub avg_bps: f64,
+    pub peak_bps: f64,
+    pub smoothed_bps: f64,
+}
+
+impl BenchReport {
+    /// The one-line text report
+    fn summary(&self) -> String {
+        format!(
+            "{}: {} bytes in {:.1}s | avg {:.0} B/s | peak {:.0} B/s | smoothed {:.0} B/s | {} reads ({} empty)",
+            self.port,
+            self.total_bytes,
+            self.seconds,
+            self.avg_bps,
+            self.peak_bps,
+            self.smoothed_bps,
+            self.reads,
+            self.empty_reads,
+        )
+    }
+}
+
+/// Counts reads into a report as they happen, apart from the port I/O
+struct Meter {
+    report: BenchReport,
+    avg: ThroughputAverager,
+    window_start: Instant,
+    window_bytes: u64,
+}
+
+impl Meter {
+    fn new(report: BenchReport, start: Instant) -> Self {
+        Self {
+            report,
+            avg: ThroughputAverager::new(1.0),
+            window_start: start,
+            window_bytes: 0,
+        }
+    }
+
+    /// One read of `n` bytes (0 when it timed out or came back empty) ending at `now`
+    fn record(&mut self, n: usize, now: Instant) {
+        self.report.reads += 1;
+        if n == 0 {
+            self.report.empty_reads += 1;
+        }
+        self.report.total_bytes += n as u64;
+        self.window_bytes += n as u64;
+        let dt = now.duration_since(self.window_start);
+        // Peak is the best 250ms window; the EWMA gives the steady-state figure
+        if dt >= Duration::from_millis(250) {
+            let secs = dt.as_secs_f64();
+            self.report.peak_bps = self.report.peak_bps.max(self.window_bytes as f64 / secs);
+            self.report.smoothed_bps = self.avg.update(self.window_bytes, secs);
+            self.window_bytes = 0;
+            self.window_start = now;
+        }
+    }
+
+    fn finish(mut self, elapsed: Duration) -> BenchReport {
+        self.report.seconds = elapsed.as_secs_f64();
+        self.report.avg_bps = self.report.total_bytes as f64 / self.report.seconds.max(1e-3);
+        self.report
+    }
+}
+
+pub fn run_bench(bench: Bench) -> Result<()> {
+    let serial_path = select_serial_port(&bench.serial, bench.include_builtin, None)?;
+    let builder = serialport::new(&serial_path, bench.baud);
+    let mut port = configure_serial(builder, &bench.line)
+        .with_context(|| format!("Opening serial port {serial_path}"))?;
+    if matches!(bench.format, ReportFormat::Text) {
+        eprintln!(
+            "Reading {serial_path} @ {} baud for {}s...",
+            bench.baud, bench.duration
+        );
+    }
+
+    let start = Instant::now();
+    let mut meter = Meter::new(
+        BenchReport {
+            port: serial_path.clone(),
+            baud: bench.baud,
+            ..Default::default()
+        },
+        start,
+    );
+    let mut buf = vec![0u8; 16 * 1024];
+    let end = start + Duration::from_secs(bench.duration);
+    while Instant::now() < end {
+        let n = match port.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => 0,
+            Err(e) => return Err(e).with_context(|| format!("Reading serial port {serial_path}")),
+        };
+        meter.record(n, Instant::now());
+    }
+    let report = meter.finish(start.elapsed());
+
+    match bench.format {
+        ReportFormat::Text => println!("{}", report.summary()),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> BenchReport {
+        BenchReport {
+            port: "/dev/ttyUSB0".into(),
+            baud: 115200,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn peak_is_the_best_window() {
+        let start = Instant::now();
+        let mut meter = Meter::new(report(), start);
+        // 1000 bytes in the first 250ms window, 500 in the second
+        meter.record(1000, start + Duration::from_millis(100));
+        meter.record(0, start + Duration::from_millis(250));
+        meter.record(500, start + Duration::from_millis(500));
+        let report = meter.finish(Duration::from_secs(1));
+        assert_eq!(report.total_bytes, 1500);
+        assert_eq!(report.reads, 3);
+        assert_eq!(report.empty_reads, 1);
+        assert_eq!(report.peak_bps, 4000.0);
+        assert_eq!(report.avg_bps, 1500.0);
+        assert!(report.smoothed_bps > 0.0);
+    }
+
+    #[test]
+    fn nothing_read_is_all_zero() {
+        let start = Instant::now();
+        let mut meter = Meter::new(report(), start);
+        meter.record(0, start + Duration::from_millis(300));
+        let report = meter.finish(Duration::ZERO);
+        assert_eq!((report.total_bytes, report.empty_reads), (0, 1));
+        assert_eq!((report.avg_bps, report.peak_bps), (0.0, 0.0));
+    }
+
+    #[test]
+    fn text_and_json_summaries() {
+        let report = BenchReport {
+            seconds: 2.0,
+            total_bytes: 3000,
+            reads: 4,
+            empty_reads: 1,
+            avg_bps: 1500.0,
+            peak_bps: 4000.0,
+            smoothed_bps: 1234.4,
+            ..report()
+        };
+        assert_eq!(
+            report.summary(),
+            "/dev/ttyUSB0: 3000 bytes in 2.0s | avg 1500 B/s | peak 4000 B/s | smoothed 1234 B/s | 4 reads (1 empty)"
+        );
+        let json: serde_json::Value = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["port"], "/dev/ttyUSB0");
+        assert_eq!(json["total_bytes"], 3000);
+        assert_eq!(json["peak_bps"], 4000.0);
+    }
+}
@@ -0,0 +1,244 @@
+use std::fmt;
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+use crate::serial::{list_available_ports, usb_description};
+use crate::ui::overview::ModemLines;
+
+// Common rates plus the high ones USB adapters advertise
+const PROBE_BAUDS: [u32; 12] = [
+    9_600, 57_600, 115_200, 230_400, 460_800, 921_600, 1_000_000, 1_500_000, 2_000_000, 3_000_000,
+    4_000_000, 12_000_000,
+];
+
+// What `ports --baud-list` falls back to when a port can't be probed
+const STANDARD_BAUDS: [u32; 9] = [
+    1_200, 2_400, 4_800, 9_600, 19_200, 38_400, 57_600, 115_200, 230_400,
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BaudProbe {
+    Accepted,
+    /// The driver took the request but reports a different rate
+    Rounded(u32),
+    Rejected(String),
+}
+
+/// What an opened port reports about itself. `serialport` has no capability
+/// query, so supported rates are found by setting each one and reading it back.
+pub struct Caps {
+    pub path: String,
+    pub adapter: Option<String>,
+    pub current: String,
+    pub bauds: Vec<(u32, BaudProbe)>,
+    pub modem: String,
+    pub queued: Option<(u32, u32)>,
+}
+
+/// Probes `port` and puts its original baud rate back afterwards
+pub fn query_caps(path: &str, port: &mut dyn SerialPort) -> Caps {
+    let original = port.baud_rate().ok();
+    let current = match (
+        port.baud_rate(),
+        port.data_bits(),
+        port.parity(),
+        port.stop_bits(),
+        port.flow_control(),
+    ) {
+        (Ok(b), Ok(d), Ok(p), Ok(s), Ok(f)) => {
+            format!("{b} baud, {d} data bits, {p} parity, {s} stop bits, flow control {f}")
+        }
+        _ => "unavailable".to_string(),
+    };
+    let modem = ModemLines::default();
+    modem.poll(port);
+    let queued = port
+        .bytes_to_read()
+        .and_then(|r| Ok((r, port.bytes_to_write()?)))
+        .ok();
+
+    let bauds = probe_bauds(port);
+    if let Some(rate) = original {
+        let _ = port.set_baud_rate(rate);
+    }
+
+    Caps {
+        path: path.to_string(),
+        adapter: adapter_description(path),
+        current,
+        bauds,
+        modem: modem.summary(),
+        queued,
+    }
+}
+
+/// Set each of `PROBE_BAUDS` and read it back; the caller restores the rate
+fn probe_bauds(port: &mut dyn SerialPort) -> Vec<(u32, BaudProbe)> {
+    PROBE_BAUDS
+        .iter()
+        .map(|&rate| {
+            let probe = match port.set_baud_rate(rate).and_then(|_| port.baud_rate()) {
+                Ok(got) if got == rate => BaudProbe::Accepted,
+                Ok(got) => BaudProbe::Rounded(got),
+                Err(e) => BaudProbe::Rejected(e.to_string()),
+            };
+            (rate, probe)
+        })
+        .collect()
+}
+
+/// `ports --baud-list`: open `path` just long enough to probe its rates
+pub fn port_bauds(path: &str) -> Result<Vec<(u32, BaudProbe)>, String> {
+    let mut port = serialport::new(path, 9_600)
+        .timeout(Duration::from_millis(200))
+        .open()
+        .map_err(|e| e.to_string())?;
+    Ok(probe_bauds(port.as_mut()))
+}
+
+/// The rates the driver took as asked; the standard list and why when the
+/// port could not be probed
+pub fn supported_bauds(
+    probed: &Result<Vec<(u32, BaudProbe)>, String>,
+) -> (Vec<u32>, Option<String>) {
+    let probes = match probed {
+        Ok(probes) => probes,
+        Err(e) => return (STANDARD_BAUDS.to_vec(), Some(format!("not probed: {e}"))),
+    };
+    let accepted: Vec<u32> = probes
+        .iter()
+        .filter(|(_, probe)| *probe == BaudProbe::Accepted)
+        .map(|(rate, _)| *rate)
+        .collect();
+    let rounded = probes
+        .iter()
+        .any(|(_, probe)| matches!(probe, BaudProbe::Rounded(_)));
+    match (accepted.is_empty(), rounded) {
+        (true, false) => (
+            STANDARD_BAUDS.to_vec(),
+            Some("the driver refused every probe".to_string()),
+        ),
+        _ => (accepted, None),
+    }
+}
+
+/// One line for `ports --baud-list`, with any rates the driver rounded
+pub fn baud_summary(probed: &Result<Vec<(u32, BaudProbe)>, String>) -> String {
+    let (rates, note) = supported_bauds(probed);
+    let mut words: Vec<String> = rates.iter().map(u32::to_string).collect();
+    match (note, probed) {
+        (Some(note), _) => words.push(format!("(standard rates; {note})")),
+        (None, Ok(probes)) => words.extend(probes.iter().filter_map(|(rate, probe)| match probe {
+            BaudProbe::Rounded(got) => Some(format!("{rate}->{got}")),
+            _ => None,
+        })),
+        (None, Err(_)) => {}
+    }
+    words.join(" ")
+}
+
+fn adapter_description(path: &str) -> Option<String> {
+    if let Some(usb) = usb_description(path) {
+        return Some(usb);
+    }
+    let info = list_available_ports(true)
+        .into_iter()
+        .find(|p| p.port_name == path)?;
+    Some(format!("{:?}", info.port_type))
+}
+
+impl fmt::Display for Caps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Capabilities of {}", self.path)?;
+        if let Some(adapter) = &self.adapter {
+            writeln!(f, "  adapter: {adapter}")?;
+        }
+        writeln!(f, "  current: {}", self.current)?;
+        writeln!(f, "  modem:   {}", self.modem)?;
+        if let Some((rx, tx)) = self.queued {
+            writeln!(f, "  queued:  {rx} bytes to read, {tx} bytes to write")?;
+        }
+        writeln!(f, "  baud rates (set and read back; the driver accepting a rate does not prove the hardware reaches it):")?;
+        for (rate, probe) in &self.bauds {
+            match probe {
+                BaudProbe::Accepted => writeln!(f, "    {rate:>10}  ok")?,
+                BaudProbe::Rounded(got) => writeln!(f, "    {rate:>10}  rounded to {got}")?,
+                BaudProbe::Rejected(e) => writeln!(f, "    {rate:>10}  rejected: {e}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_report_layout() {
+        let caps = Caps {
+            path: "/dev/ttyUSB0".into(),
+            adapter: Some("USB 0403:6001 FTDI FT232R".into()),
+            current: "115200 baud".into(),
+            bauds: vec![
+                (115_200, BaudProbe::Accepted),
+                (1_000_000, BaudProbe::Rounded(921_600)),
+                (12_000_000, BaudProbe::Rejected("Invalid argument".into())),
+            ],
+            modem: "n/a".into(),
+            queued: None,
+        };
+        let text = caps.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "Capabilities of /dev/ttyUSB0");
+        assert_eq!(lines[1], "  adapter: USB 0403:6001 FTDI FT232R");
+        assert!(!text.contains("queued"));
+        assert!(text.contains("       115200  ok\n"));
+        assert!(text.contains("      1000000  rounded to 921600\n"));
+        assert!(text.contains("     12000000  rejected: Invalid argument\n"));
+    }
+
+    #[test]
+    fn baud_list_summaries() {
+        let probed = Ok(vec![
+            (9_600, BaudProbe::Accepted),
+            (115_200, BaudProbe::Accepted),
+            (1_000_000, BaudProbe::Rounded(921_600)),
+            (12_000_000, BaudProbe::Rejected("Invalid argument".into())),
+        ]);
+        assert_eq!(baud_summary(&probed), "9600 115200 1000000->921600");
+        assert_eq!(supported_bauds(&probed), (vec![9_600, 115_200], None));
+
+        let refused = Ok(vec![(9_600, BaudProbe::Rejected("EINVAL".into()))]);
+        assert!(baud_summary(&refused)
+            .ends_with("115200 230400 (standard rates; the driver refused every probe)"));
+        let busy = Err("Device or resource busy".to_string());
+        assert_eq!(
+            baud_summary(&busy),
+            "1200 2400 4800 9600 19200 38400 57600 115200 230400 (standard rates; not probed: Device or resource busy)"
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn probe_restores_original_rate() {
+        use crate::cli::{DataBitsOpt, LineSettings, ParityOpt, StopBitsOpt};
+        use nix::pty::{openpty, Winsize};
+        use std::os::fd::AsRawFd;
+
+        let pty = openpty(None::<&Winsize>, None).unwrap();
+        let path = std::fs::read_link(format!("/proc/self/fd/{}", pty.slave.as_raw_fd())).unwrap();
+        let path = path.to_string_lossy().into_owned();
+        let line = LineSettings {
+            data_bits: DataBitsOpt::Eight,
+            parity: ParityOpt::None,
+            stop_bits: StopBitsOpt::One,
+        };
+        let mut port =
+            crate::serial::configure_serial(serialport::new(&path, 9600), &line).unwrap();
+        let caps = query_caps(&path, port.as_mut());
+        assert_eq!(caps.bauds.len(), PROBE_BAUDS.len());
+        assert_eq!(port.baud_rate().unwrap(), 9600);
+    }
+}
@@ -0,0 +1,174 @@
+// `--framing cobs`: the device sends Consistent Overhead Byte Stuffing
+// packets, each ended by a 0x00. Every byte of an encoded packet is non-zero;
+// a code byte N says the next N-1 bytes are data and, unless N is 0xff, that a
+// zero followed them in the original.
+
+use bytes::Bytes;
+use thiserror::Error;
+
+use crate::net::frame::MAX_FRAME;
+
+const DELIMITER: u8 = 0x00;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum CobsError {
+    #[error("code byte at offset {offset} runs past the end of the {len} byte frame")]
+    Truncated { offset: usize, len: usize },
+    #[error("frame longer than {max} bytes without a 0x00 delimiter")]
+    TooLarge { max: usize },
+}
+
+/// `payload` stuffed and terminated with the 0x00 delimiter
+pub fn encode(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + payload.len() / 254 + 2);
+    let mut code_at = 0;
+    out.push(0);
+    for &b in payload {
+        if b == 0 {
+            out[code_at] = (out.len() - code_at) as u8;
+            code_at = out.len();
+            out.push(0);
+            continue;
+        }
+        out.push(b);
+        if out.len() - code_at == 0xff {
+            out[code_at] = 0xff;
+            code_at = out.len();
+            out.push(0);
+        }
+    }
+    out[code_at] = (out.len() - code_at) as u8;
+    out.push(DELIMITER);
+    out
+}
+
+/// One encoded frame, without its delimiter
+pub fn decode(frame: &[u8]) -> Result<Vec<u8>, CobsError> {
+    let mut out = Vec::with_capacity(frame.len());
+    let mut at = 0;
+    while at < frame.len() {
+        let code = frame[at] as usize;
+        let end = at + code;
+        if code == 0 || end > frame.len() {
+            return Err(CobsError::Truncated {
+                offset: at,
+                len: frame.len(),
+            });
+        }
+        out.extend_from_slice(&frame[at + 1..end]);
+        // the implied zero; there is none after a full block or at the very end
+        if code < 0xff && end < frame.len() {
+            out.push(0);
+        }
+        at = end;
+    }
+    Ok(out)
+}
+
+/// Cuts the serial stream at each 0x00 and decodes the packets, keeping a
+/// partial packet until the read that completes it.
+#[derive(Default)]
+pub struct CobsDecoder {
+    pending: Vec<u8>,
+    // dropping an oversized packet until its delimiter shows up
+    skipping: bool,
+}
+
+impl CobsDecoder {
+    /// Every packet completed by `data`, in order; empty packets are skipped
+    pub fn feed(&mut self, data: &[u8]) -> Vec<Result<Bytes, CobsError>> {
+        let mut packets = Vec::new();
+        for chunk in data.split_inclusive(|&b| b == DELIMITER) {
+            let (body, complete) = match chunk.split_last() {
+                Some((&DELIMITER, body)) => (body, true),
+                _ => (chunk, false),
+            };
+            if !self.skipping {
+                self.pending.extend_from_slice(body);
+                if self.pending.len() > MAX_FRAME {
+                    self.pending.clear();
+                    self.skipping = true;
+                    packets.push(Err(CobsError::TooLarge { max: MAX_FRAME }));
+                }
+            }
+            if !complete {
+                continue;
+            }
+            if !self.skipping && !self.pending.is_empty() {
+                packets.push(decode(&self.pending).map(Bytes::from));
+            }
+            self.pending.clear();
+            self.skipping = false;
+        }
+        packets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_encodings() {
+        assert_eq!(encode(&[]), [0x01, 0x00]);
+        assert_eq!(encode(&[0x00]), [0x01, 0x01, 0x00]);
+        assert_eq!(
+            encode(&[0x11, 0x22, 0x00, 0x33]),
+            [3, 0x11, 0x22, 2, 0x33, 0]
+        );
+        assert_eq!(encode(&[0x11, 0x00, 0x00, 0x00]), [2, 0x11, 1, 1, 1, 0]);
+
+        let long: Vec<u8> = (1..=254).collect();
+        let wire = encode(&long);
+        assert_eq!(wire.len(), 257);
+        assert_eq!((wire[0], wire[255], wire[256]), (0xff, 0x01, 0x00));
+    }
+
+    #[test]
+    fn round_trips() {
+        let cases: [Vec<u8>; 5] = [
+            vec![],
+            vec![0; 3],
+            b"AT\r\n".to_vec(),
+            (0..=255).collect(),
+            (0..600).map(|i| (i % 7) as u8).collect(),
+        ];
+        for payload in cases {
+            let wire = encode(&payload);
+            assert!(!wire[..wire.len() - 1].contains(&0));
+            assert_eq!(decode(&wire[..wire.len() - 1]).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn packets_split_across_reads() {
+        let mut wire = encode(b"hello");
+        wire.extend(encode(&[1, 0, 2]));
+        let mut decoder = CobsDecoder::default();
+        assert!(decoder.feed(&wire[..3]).is_empty());
+        let packets = decoder.feed(&wire[3..]);
+        let packets: Vec<Bytes> = packets.into_iter().map(Result::unwrap).collect();
+        assert_eq!(packets, [&b"hello"[..], &[1u8, 0, 2][..]]);
+        // back-to-back delimiters carry nothing
+        assert!(decoder.feed(&[0, 0]).is_empty());
+    }
+
+    #[test]
+    fn malformed_frames_are_reported_and_skipped() {
+        let mut decoder = CobsDecoder::default();
+        let mut wire = vec![0x05, 0x41, 0x00];
+        wire.extend(encode(b"ok"));
+        let packets = decoder.feed(&wire);
+        assert_eq!(packets[0], Err(CobsError::Truncated { offset: 0, len: 2 }));
+        assert_eq!(packets[1].as_deref(), Ok(&b"ok"[..]));
+
+        let junk = vec![0x01; MAX_FRAME + 1];
+        assert_eq!(
+            decoder.feed(&junk),
+            [Err(CobsError::TooLarge { max: MAX_FRAME })]
+        );
+        assert!(decoder.feed(&junk).is_empty());
+        let packets = decoder.feed(&[0x00, 0x02, 0x41, 0x00]);
+        assert_eq!(packets, [Ok(Bytes::from_static(b"A"))]);
+    }
+}
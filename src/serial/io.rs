@@ -4,7 +4,7 @@ use anyhow::Result;
 use serialport::{available_ports, SerialPort, SerialPortBuilder, SerialPortInfo, SerialPortType};
 use thiserror::Error;
 
-use crate::cli::Listen;
+use crate::cli::{LineSettings, ParityOpt};
 
 pub fn list_available_ports(include_all: bool) -> Vec<SerialPortInfo> {
     available_ports()
@@ -14,14 +14,120 @@ pub fn list_available_ports(include_all: bool) -> Vec<SerialPortInfo> {
         .collect::<Vec<_>>()
 }
 
-pub fn select_serial_port(explicit: &Option<String>) -> Result<String> {
+/// "USB 10c4:ea60 Silicon Labs CP2102 ..." for a USB adapter, `None` for other ports
+pub fn usb_description(path: &str) -> Option<String> {
+    let info = list_available_ports(false)
+        .into_iter()
+        .find(|p| p.port_name == path)?;
+    match info.port_type {
+        SerialPortType::UsbPort(usb) => Some(
+            format!(
+                "USB {:04x}:{:04x} {} {}",
+                usb.vid,
+                usb.pid,
+                usb.manufacturer.unwrap_or_default(),
+                usb.product.unwrap_or_default()
+            )
+            .trim_end()
+            .to_string(),
+        ),
+        _ => None,
+    }
+}
+
+/// Shell-style match of a whole port name: `*` is any run of characters, `?`
+/// one character and `[...]` one of a set or range (`[!...]` for none of them)
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    // after a mismatch, retry from the last `*` with it taking one more char
+    let mut star: Option<(usize, usize)> = None;
+    while ni < n.len() {
+        if p.get(pi) == Some(&'*') {
+            pi += 1;
+            star = Some((pi, ni));
+            continue;
+        }
+        if let Some(next) = (pi < p.len()).then(|| glob_step(&p, pi, n[ni])).flatten() {
+            pi = next;
+            ni += 1;
+            continue;
+        }
+        match star {
+            Some((after, taken)) => {
+                pi = after;
+                ni = taken + 1;
+                star = Some((after, ni));
+            }
+            None => return false,
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+// Index past the pattern token at `pi` when it matches `c`
+fn glob_step(p: &[char], pi: usize, c: char) -> Option<usize> {
+    match p[pi] {
+        '?' => Some(pi + 1),
+        '[' => {
+            let mut i = pi + 1;
+            let negate = matches!(p.get(i), Some('!' | '^'));
+            if negate {
+                i += 1;
+            }
+            let start = i;
+            let mut found = false;
+            // a `]` straight after the opening bracket is a member, not the end
+            while i < p.len() && (p[i] != ']' || i == start) {
+                if i + 2 < p.len() && p[i + 1] == '-' && p[i + 2] != ']' {
+                    found |= (p[i]..=p[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    found |= p[i] == c;
+                    i += 1;
+                }
+            }
+            if i == p.len() {
+                // never closed: a literal `[`
+                return (c == '[').then_some(pi + 1);
+            }
+            (found != negate).then_some(i + 1)
+        }
+        literal => (literal == c).then_some(pi + 1),
+    }
+}
+
+/// The names matching `pattern` (all of them without one), in order
+pub fn matching_ports(names: Vec<String>, pattern: Option<&str>) -> Vec<String> {
+    match pattern {
+        Some(pattern) => names
+            .into_iter()
+            .filter(|name| glob_match(pattern, name))
+            .collect(),
+        None => names,
+    }
+}
+
+pub fn select_serial_port(
+    explicit: &Option<String>,
+    include_builtin: bool,
+    pattern: Option<&str>,
+) -> Result<String> {
     if let Some(p) = explicit {
         return Ok(p.clone());
     }
-    let ports = list_available_ports(false)
+    let ports = list_available_ports(include_builtin)
         .into_iter()
         .map(|p| p.port_name)
         .collect::<Vec<_>>();
+    let ports = matching_ports(ports, pattern);
+    if let (Some(pattern), true) = (pattern, ports.is_empty()) {
+        return Err(SerialSelectError::NoMatch {
+            pattern: pattern.to_string(),
+        }
+        .into());
+    }
     decide_port(None, ports)
 }
 
@@ -43,20 +149,81 @@ pub enum SerialSelectError {
         "No serial ports found. Re-run with --serial <PORT> or use --all in 'ports' to inspect."
     )]
     NoPorts,
+    #[error(
+        "No serial ports match '{pattern}'. Run 'sergw ports --all' to see what is available."
+    )]
+    NoMatch { pattern: String },
     #[error("Multiple serial ports detected: {list:?}. Please specify --serial <PORT>.")]
     MultiplePorts { list: Vec<String> },
 }
 
 pub fn configure_serial(
     builder: SerialPortBuilder,
-    listen: &Listen,
+    line: &LineSettings,
 ) -> serialport::Result<Box<dyn SerialPort>> {
+    #[cfg(target_os = "linux")]
+    return configure_serial_native(builder, line)
+        .map(|port| Box::new(port) as Box<dyn SerialPort>);
+    #[cfg(not(target_os = "linux"))]
+    {
+        if line.parity.is_stick() {
+            return Err(stick_parity_unsupported(&line.parity));
+        }
+        line_builder(builder, line).open()
+    }
+}
+
+/// `configure_serial` without the box, for callers that need the fd
+#[cfg(target_os = "linux")]
+pub fn configure_serial_native(
+    builder: SerialPortBuilder,
+    line: &LineSettings,
+) -> serialport::Result<serialport::TTYPort> {
+    let port = line_builder(builder, line).open_native()?;
+    if line.parity.is_stick() {
+        set_stick_parity(&port, &line.parity)?;
+    }
+    Ok(port)
+}
+
+fn line_builder(builder: SerialPortBuilder, line: &LineSettings) -> SerialPortBuilder {
     builder
-        .data_bits(listen.data_bits.clone().into())
-        .parity(listen.parity.clone().into())
-        .stop_bits(listen.stop_bits.clone().into())
+        .data_bits(line.data_bits.clone().into())
+        .parity(line.parity.clone().into())
+        .stop_bits(line.stop_bits.clone().into())
         .timeout(Duration::from_millis(200))
-        .open()
+}
+
+// serialport has no mark/space parity; set CMSPAR on the termios directly
+#[cfg(all(target_os = "linux", not(target_arch = "mips")))]
+fn set_stick_parity(port: &serialport::TTYPort, parity: &ParityOpt) -> serialport::Result<()> {
+    use nix::sys::termios::{tcgetattr, tcsetattr, ControlFlags, SetArg};
+    use std::os::fd::{AsRawFd, BorrowedFd};
+
+    let to_err = |e: nix::Error| {
+        serialport::Error::new(
+            serialport::ErrorKind::Io(std::io::ErrorKind::Other),
+            format!("Setting {parity:?} parity: {e}"),
+        )
+    };
+    // SAFETY: the fd belongs to `port`, which outlives this borrow
+    let fd = unsafe { BorrowedFd::borrow_raw(port.as_raw_fd()) };
+    let mut tio = tcgetattr(fd).map_err(to_err)?;
+    tio.control_flags.insert(ControlFlags::CMSPAR);
+    tcsetattr(fd, SetArg::TCSANOW, &tio).map_err(to_err)
+}
+
+#[cfg(all(target_os = "linux", target_arch = "mips"))]
+fn set_stick_parity(_port: &serialport::TTYPort, parity: &ParityOpt) -> serialport::Result<()> {
+    Err(stick_parity_unsupported(parity))
+}
+
+#[cfg(not(all(target_os = "linux", not(target_arch = "mips"))))]
+fn stick_parity_unsupported(parity: &ParityOpt) -> serialport::Error {
+    serialport::Error::new(
+        serialport::ErrorKind::InvalidInput,
+        format!("{parity:?} parity is only supported on Linux; use none, odd or even"),
+    )
 }
 
 #[cfg(test)]
@@ -87,4 +254,70 @@ mod tests {
             decide_port(None, vec!["/dev/ttyUSB0".into(), "/dev/ttyUSB1".into()]).unwrap_err();
         assert!(err.to_string().contains("Multiple serial ports"));
     }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("/dev/ttyACM*", "/dev/ttyACM0"));
+        assert!(glob_match("/dev/ttyACM*", "/dev/ttyACM"));
+        assert!(!glob_match("/dev/ttyACM*", "/dev/ttyUSB0"));
+        assert!(glob_match("/dev/tty?SB1", "/dev/ttyUSB1"));
+        assert!(glob_match("/dev/ttyUSB[0-2]", "/dev/ttyUSB2"));
+        assert!(!glob_match("/dev/ttyUSB[0-2]", "/dev/ttyUSB3"));
+        assert!(!glob_match("/dev/ttyUSB[!0]", "/dev/ttyUSB0"));
+        assert!(glob_match(
+            "/dev/serial/by-id/*FTDI*",
+            "/dev/serial/by-id/usb-FTDI_FT232R-if00"
+        ));
+        assert!(glob_match("*USB*1", "/dev/ttyUSB11"));
+        assert!(glob_match("COM[13]", "COM3"));
+        assert!(!glob_match("COM[13]", "COM13"));
+        assert!(glob_match("a[b", "a[b"));
+        assert!(!glob_match("/dev/ttyUSB", "/dev/ttyUSB0"));
+    }
+
+    #[test]
+    fn match_narrows_the_port_list() {
+        let names = || {
+            [
+                "/dev/ttyUSB0",
+                "/dev/ttyACM0",
+                "/dev/ttyUSB1",
+                "/dev/ttyACM1",
+            ]
+            .map(String::from)
+            .to_vec()
+        };
+        assert_eq!(
+            matching_ports(names(), Some("/dev/ttyACM*")),
+            ["/dev/ttyACM0", "/dev/ttyACM1"]
+        );
+        assert_eq!(matching_ports(names(), None), names());
+        let one = matching_ports(names(), Some("*USB1"));
+        assert_eq!(decide_port(None, one).unwrap(), "/dev/ttyUSB1");
+        assert!(matching_ports(names(), Some("/dev/ttyS*")).is_empty());
+    }
+
+    #[cfg(all(target_os = "linux", not(target_arch = "mips")))]
+    #[test]
+    fn mark_parity_sets_cmspar() {
+        use crate::cli::{DataBitsOpt, StopBitsOpt};
+        use nix::pty::{openpty, Winsize};
+        use nix::sys::termios::{tcgetattr, ControlFlags};
+        use std::os::fd::AsRawFd;
+
+        let pty = openpty(None::<&Winsize>, None).unwrap();
+        let path = std::fs::read_link(format!("/proc/self/fd/{}", pty.slave.as_raw_fd())).unwrap();
+        let line = LineSettings {
+            data_bits: DataBitsOpt::Eight,
+            parity: ParityOpt::Mark,
+            stop_bits: StopBitsOpt::One,
+        };
+        let _port = configure_serial(serialport::new(path.to_string_lossy(), 9600), &line).unwrap();
+        let flags = tcgetattr(&pty.slave).unwrap().control_flags;
+        // The pty driver clears PARENB itself, so only the bits we control are checked
+        assert!(
+            flags.contains(ControlFlags::PARODD | ControlFlags::CMSPAR),
+            "{flags:?}"
+        );
+    }
 }
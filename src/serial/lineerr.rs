@@ -0,0 +1,124 @@
+// Physical-layer errors. serialport hides breaks and framing, parity and
+// overrun errors (the damaged bytes simply arrive, or go missing), but Linux
+// keeps running totals per port that TIOCGICOUNT reads. The serial reader
+// polls them and reports each increase. Many USB adapter drivers keep no
+// counts, and other platforms have no equivalent.
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LineErrors {
+    pub breaks: u32,
+    pub framing: u32,
+    pub parity: u32,
+    /// UART FIFO and driver buffer overruns together
+    pub overrun: u32,
+}
+
+impl LineErrors {
+    /// What grew since `earlier`; a counter that went backwards (another
+    /// device after a reconnect) counts as nothing new
+    pub fn since(&self, earlier: &LineErrors) -> LineErrors {
+        LineErrors {
+            breaks: self.breaks.saturating_sub(earlier.breaks),
+            framing: self.framing.saturating_sub(earlier.framing),
+            parity: self.parity.saturating_sub(earlier.parity),
+            overrun: self.overrun.saturating_sub(earlier.overrun),
+        }
+    }
+
+    pub fn add(&mut self, more: &LineErrors) {
+        self.breaks = self.breaks.saturating_add(more.breaks);
+        self.framing = self.framing.saturating_add(more.framing);
+        self.parity = self.parity.saturating_add(more.parity);
+        self.overrun = self.overrun.saturating_add(more.overrun);
+    }
+
+    /// One event per kind that occurred, e.g. "break detected", "framing error (x12)"
+    pub fn events(&self) -> Vec<String> {
+        [
+            ("break detected", self.breaks),
+            ("overrun", self.overrun),
+            ("framing error", self.framing),
+            ("parity error", self.parity),
+        ]
+        .into_iter()
+        .filter(|&(_, n)| n > 0)
+        .map(|(what, n)| match n {
+            1 => what.to_string(),
+            n => format!("{what} (x{n})"),
+        })
+        .collect()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "break {}  frame {}  parity {}  overrun {}",
+            self.breaks, self.framing, self.parity, self.overrun
+        )
+    }
+}
+
+/// The port's totals since the driver loaded
+#[cfg(target_os = "linux")]
+pub fn read_line_errors(fd: std::os::fd::RawFd) -> std::io::Result<LineErrors> {
+    use libc::c_int;
+
+    // struct serial_icounter_struct from <linux/serial.h>
+    #[repr(C)]
+    #[derive(Default)]
+    struct Icount {
+        cts: c_int,
+        dsr: c_int,
+        rng: c_int,
+        dcd: c_int,
+        rx: c_int,
+        tx: c_int,
+        frame: c_int,
+        overrun: c_int,
+        parity: c_int,
+        brk: c_int,
+        buf_overrun: c_int,
+        reserved: [c_int; 9],
+    }
+
+    let mut icount = Icount::default();
+    // SAFETY: TIOCGICOUNT fills a serial_icounter_struct, which Icount mirrors
+    if unsafe { libc::ioctl(fd, libc::TIOCGICOUNT, &mut icount) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let count = |n: c_int| n.max(0) as u32;
+    Ok(LineErrors {
+        breaks: count(icount.brk),
+        framing: count(icount.frame),
+        parity: count(icount.parity),
+        overrun: count(icount.overrun).saturating_add(count(icount.buf_overrun)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_what_grew() {
+        let before = LineErrors {
+            breaks: 2,
+            framing: 10,
+            parity: 0,
+            overrun: 1,
+        };
+        let now = LineErrors {
+            breaks: 3,
+            framing: 22,
+            parity: 0,
+            overrun: 1,
+        };
+        let new = now.since(&before);
+        assert_eq!(new.events(), ["break detected", "framing error (x12)"]);
+        assert!(before.since(&now).events().is_empty());
+
+        let mut total = LineErrors::default();
+        total.add(&new);
+        total.add(&new);
+        assert_eq!(total.summary(), "break 2  frame 24  parity 0  overrun 0");
+    }
+}
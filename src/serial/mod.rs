@@ -1,2 +1,10 @@
+pub mod autobaud;
+pub mod bench;
+pub mod caps;
+pub mod cobs;
 pub mod io;
+pub mod lineerr;
+pub mod noise;
+pub mod ping;
+pub mod selftest;
 pub use io::*;
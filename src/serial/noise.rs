@@ -0,0 +1,168 @@
+// Wrong-baud detection. A port read at the wrong rate delivers what looks
+// like random bytes: close to 8 bits of entropy per byte, few printable
+// characters and no line structure. Compressed or encrypted binary protocols
+// look much the same, so a warning needs several noisy windows in a row and
+// is only ever given once.
+
+// Bytes per window; small windows of real data can look random by chance
+const WINDOW: usize = 4096;
+// Consecutive noisy windows before warning
+const NOISY_WINDOWS: u32 = 3;
+// Uniformly random bytes measure ~7.95 bits/byte over 4 KiB
+const MIN_ENTROPY: f64 = 7.5;
+// ~37% of uniformly random bytes are printable ASCII or whitespace
+const MAX_PRINTABLE: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseReport {
+    /// Shannon entropy of the last window, bits per byte
+    pub entropy: f64,
+    /// Share of printable ASCII and whitespace in the last window
+    pub printable: f64,
+}
+
+impl NoiseReport {
+    /// Measure a whole sample at once
+    pub fn of(data: &[u8]) -> Self {
+        let mut counts = [0; 256];
+        for &b in data {
+            counts[b as usize] += 1;
+        }
+        Self::from_counts(&counts, data.len())
+    }
+
+    fn from_counts(counts: &[u32; 256], seen: usize) -> Self {
+        let total = seen.max(1) as f64;
+        let entropy = counts
+            .iter()
+            .filter(|&&n| n > 0)
+            .map(|&n| {
+                let p = n as f64 / total;
+                -p * p.log2()
+            })
+            .sum();
+        let printable = counts
+            .iter()
+            .enumerate()
+            .filter(|&(b, _)| {
+                let b = b as u8;
+                b.is_ascii_graphic() || matches!(b, b' ' | b'\t' | b'\r' | b'\n')
+            })
+            .map(|(_, &n)| n as f64)
+            .sum::<f64>()
+            / total;
+        Self { entropy, printable }
+    }
+
+    /// Mostly text. A short sample can't reach the entropy bound, so this is
+    /// what a few hundred bytes can be judged on
+    pub fn is_readable(&self) -> bool {
+        self.printable > MAX_PRINTABLE
+    }
+}
+
+pub struct NoiseDetector {
+    counts: [u32; 256],
+    seen: usize,
+    noisy: u32,
+    warned: bool,
+}
+
+impl Default for NoiseDetector {
+    fn default() -> Self {
+        Self {
+            counts: [0; 256],
+            seen: 0,
+            noisy: 0,
+            warned: false,
+        }
+    }
+}
+
+impl NoiseDetector {
+    /// Account for one read; returns the report the one time the stream is
+    /// judged to be noise
+    pub fn feed(&mut self, data: &[u8]) -> Option<NoiseReport> {
+        if self.warned {
+            return None;
+        }
+        let mut report = None;
+        for &b in data {
+            self.counts[b as usize] += 1;
+            self.seen += 1;
+            if self.seen == WINDOW {
+                report = report.or(self.close_window());
+            }
+        }
+        report
+    }
+
+    fn close_window(&mut self) -> Option<NoiseReport> {
+        let report = NoiseReport::from_counts(&self.counts, self.seen);
+        self.counts = [0; 256];
+        self.seen = 0;
+
+        if report.entropy < MIN_ENTROPY || report.printable > MAX_PRINTABLE {
+            self.noisy = 0;
+            return None;
+        }
+        self.noisy += 1;
+        if self.noisy < NOISY_WINDOWS {
+            return None;
+        }
+        self.warned = true;
+        Some(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift, so the "noise" is the same on every run
+    fn noise(len: usize, mut state: u32) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sustained_noise_warns_once() {
+        let mut detector = NoiseDetector::default();
+        let data = noise(WINDOW * NOISY_WINDOWS as usize, 0x1234_5678);
+        let (before, last) = data.split_at(data.len() - 1);
+        assert_eq!(detector.feed(before), None);
+        let report = detector.feed(last).expect("warning");
+        assert!(report.entropy > 7.9);
+        assert!(report.printable < 0.45);
+        assert_eq!(detector.feed(&noise(WINDOW * 4, 99)), None);
+    }
+
+    #[test]
+    fn text_and_structured_binary_are_left_alone() {
+        let mut detector = NoiseDetector::default();
+        let text = b"temp=21.5 hum=40%\r\n".repeat(2000);
+        assert_eq!(detector.feed(&text), None);
+
+        // framed binary: header, counter, constant-ish payload
+        let frames: Vec<u8> = (0..4000u32)
+            .flat_map(|i| [0xaa, 0x55, i as u8, 0x00, 0x10, 0x20, 0x30, 0x0d])
+            .collect();
+        assert_eq!(detector.feed(&frames), None);
+    }
+
+    #[test]
+    fn a_clean_window_resets_the_run() {
+        let mut detector = NoiseDetector::default();
+        let clean = b"OK\r\n".repeat(WINDOW / 4);
+        for seed in 1..=10 {
+            assert_eq!(detector.feed(&noise(WINDOW * 2, seed)), None);
+            assert_eq!(detector.feed(&clean), None);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+
+use crate::cli::Ping;
+use crate::serial::{configure_serial, select_serial_port};
+
+pub fn run_ping(ping: Ping) -> Result<()> {
+    let serial_path = select_serial_port(&ping.serial, ping.include_builtin, None)?;
+    let builder = serialport::new(&serial_path, ping.baud);
+    let mut port = configure_serial(builder, &ping.line)
+        .with_context(|| format!("Opening serial port {serial_path}"))?;
+    // Discard anything the device sent before we started
+    let _ = port.clear(serialport::ClearBuffer::All);
+
+    println!(
+        "PING {serial_path} @ {} baud: {} round trips",
+        ping.baud, ping.count
+    );
+    let timeout = Duration::from_millis(ping.timeout_ms);
+    let mut rtts: Vec<Duration> = Vec::with_capacity(ping.count as usize);
+    let mut buf = [0u8; 256];
+    for seq in 0..ping.count {
+        let marker = format!("SERGW-PING-{seq:08x}\n");
+        let start = Instant::now();
+        port.write_all(marker.as_bytes())
+            .with_context(|| format!("Writing to serial port {serial_path}"))?;
+        port.flush().ok();
+
+        let mut received: Vec<u8> = Vec::new();
+        let rtt = loop {
+            if find_subslice(&received, marker.as_bytes()).is_some() {
+                break Some(start.elapsed());
+            }
+            if start.elapsed() >= timeout {
+                break None;
+            }
+            match port.read(&mut buf) {
+                Ok(n) => received.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Reading serial port {serial_path}"))
+                }
+            }
+        };
+        match rtt {
+            Some(d) => {
+                println!("seq={seq} time={:.3} ms", d.as_secs_f64() * 1000.0);
+                rtts.push(d);
+            }
+            None => println!("seq={seq} timeout"),
+        }
+    }
+
+    let lost = ping.count as usize - rtts.len();
+    match LatencyStats::from_samples(&rtts) {
+        Some(stats) => {
+            println!(
+                "{} sent, {} received, {} lost; rtt min/avg/max = {:.3}/{:.3}/{:.3} ms",
+                ping.count,
+                rtts.len(),
+                lost,
+                stats.min.as_secs_f64() * 1000.0,
+                stats.avg.as_secs_f64() * 1000.0,
+                stats.max.as_secs_f64() * 1000.0,
+            );
+            Ok(())
+        }
+        None => {
+            bail!("No echoes received from {serial_path}; is the device echoing or looped back?")
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct LatencyStats {
+    pub min: Duration,
+    pub avg: Duration,
+    pub max: Duration,
+}
+
+impl LatencyStats {
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        let min = *samples.iter().min()?;
+        let max = *samples.iter().max()?;
+        let total: Duration = samples.iter().sum();
+        Some(Self {
+            min,
+            avg: total / samples.len() as u32,
+            max,
+        })
+    }
+}
+
+pub(crate) fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() || haystack.len() < needle.len() {
+        return None;
+    }
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_min_avg_max() {
+        let s = LatencyStats::from_samples(&[
+            Duration::from_millis(2),
+            Duration::from_millis(4),
+            Duration::from_millis(9),
+        ])
+        .unwrap();
+        assert_eq!(s.min, Duration::from_millis(2));
+        assert_eq!(s.avg, Duration::from_millis(5));
+        assert_eq!(s.max, Duration::from_millis(9));
+    }
+
+    #[test]
+    fn stats_empty_is_none() {
+        assert!(LatencyStats::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn find_marker_in_noise() {
+        assert_eq!(find_subslice(b"xxPING\nyy", b"PING\n"), Some(2));
+        assert_eq!(find_subslice(b"PIN", b"PING"), None);
+    }
+}
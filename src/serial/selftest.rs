@@ -0,0 +1,116 @@
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use thiserror::Error;
+
+use crate::cli::Selftest;
+use crate::serial::{configure_serial, select_serial_port};
+
+/// Marker followed by every byte value, so stuck or swapped data lines show up
+fn test_pattern() -> Vec<u8> {
+    let mut pattern = b"SERGW-SELFTEST\n".to_vec();
+    pattern.extend(0..=255u8);
+    pattern
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum LoopbackError {
+    #[error("only {got} of {want} bytes came back; check the TX/RX loopback")]
+    Incomplete { got: usize, want: usize },
+    #[error(
+        "byte {offset} differs: sent 0x{sent:02x}, read 0x{got:02x}; check baud and line settings"
+    )]
+    Mismatch { offset: usize, sent: u8, got: u8 },
+}
+
+/// Ok once `got` starts with all of `sent`; a mismatch fails as soon as it is seen.
+fn check_loopback(sent: &[u8], got: &[u8]) -> Result<(), LoopbackError> {
+    if let Some(offset) = sent.iter().zip(got).position(|(s, g)| s != g) {
+        return Err(LoopbackError::Mismatch {
+            offset,
+            sent: sent[offset],
+            got: got[offset],
+        });
+    }
+    if got.len() < sent.len() {
+        return Err(LoopbackError::Incomplete {
+            got: got.len(),
+            want: sent.len(),
+        });
+    }
+    Ok(())
+}
+
+pub fn run_selftest(test: Selftest) -> Result<()> {
+    let serial_path = select_serial_port(&test.serial, test.include_builtin, None)?;
+    let builder = serialport::new(&serial_path, test.baud);
+    let mut port = configure_serial(builder, &test.line)
+        .with_context(|| format!("Opening serial port {serial_path}"))?;
+    let _ = port.clear(serialport::ClearBuffer::All);
+
+    let pattern = test_pattern();
+    println!(
+        "Loopback self-test on {serial_path} @ {} baud ({} bytes)",
+        test.baud,
+        pattern.len()
+    );
+    let timeout = Duration::from_millis(test.timeout_ms);
+    let start = Instant::now();
+    port.write_all(&pattern)
+        .with_context(|| format!("Writing to serial port {serial_path}"))?;
+    port.flush().ok();
+
+    let mut received: Vec<u8> = Vec::with_capacity(pattern.len());
+    let mut buf = [0u8; 512];
+    let outcome = loop {
+        match check_loopback(&pattern, &received) {
+            Err(LoopbackError::Incomplete { .. }) if start.elapsed() < timeout => {}
+            other => break other,
+        }
+        match port.read(&mut buf) {
+            Ok(n) => received.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+            Err(e) => return Err(e).with_context(|| format!("Reading serial port {serial_path}")),
+        }
+    };
+    match outcome {
+        Ok(()) => {
+            println!(
+                "PASS: pattern read back in {:.3} ms",
+                start.elapsed().as_secs_f64() * 1000.0
+            );
+            Ok(())
+        }
+        Err(e) => bail!("FAIL: loopback self-test on {serial_path}: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_covers_every_byte() {
+        let p = test_pattern();
+        assert!((0..=255u8).all(|b| p.contains(&b)));
+    }
+
+    #[test]
+    fn check_reports_progress_and_mismatch() {
+        assert_eq!(check_loopback(b"abc", b"abc"), Ok(()));
+        assert_eq!(check_loopback(b"abc", b"abcxyz"), Ok(()));
+        assert_eq!(
+            check_loopback(b"abc", b"ab"),
+            Err(LoopbackError::Incomplete { got: 2, want: 3 })
+        );
+        assert_eq!(
+            check_loopback(b"abc", b"aXc"),
+            Err(LoopbackError::Mismatch {
+                offset: 1,
+                sent: b'b',
+                got: b'X'
+            })
+        );
+    }
+}
@@ -1,66 +1,357 @@
-use std::net::SocketAddr;
+use std::collections::VecDeque;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
 use bytes::Bytes;
 use crossbeam_channel as channel;
 use dashmap::DashMap;
 
+use crate::net::hello::ClientMode;
+use crate::rate::TokenBucket;
+
+// Forcibly closes one connection (sets its stop flag and shuts the socket down)
+type Kicker = Box<dyn Fn() + Send + Sync>;
+
 pub struct SharedState {
     // outbound to TCP, concurrent map to avoid global mutex during broadcast
     pub tcp_connections: DashMap<SocketAddr, channel::Sender<Bytes>>,
+    // labels announced by clients via the SERGW-NAME hello
+    labels: DashMap<SocketAddr, String>,
+    kickers: DashMap<SocketAddr, Kicker>,
+    // partial line per client in ClientMode::Line; absent means raw
+    line_buffers: DashMap<SocketAddr, Vec<u8>>,
+    // recent serial output replayed to new clients; capacity 0 disables it
+    scrollback: Mutex<VecDeque<u8>>,
+    scrollback_cap: usize,
+    // optional serial -> TCP pacing; frames over the budget are dropped
+    tcp_rate: Option<Mutex<TokenBucket>>,
+    dropped_frames: AtomicU64,
+    // --tap: a copy of every broadcast frame; dropped when the tap falls behind
+    tap: Option<channel::Sender<Bytes>>,
+    tap_dropped: AtomicU64,
+    // --single-controller: clients in arrival order; only the first may write
+    controllers: Option<Mutex<VecDeque<SocketAddr>>>,
 }
 
 impl SharedState {
     pub fn new() -> Self {
         Self {
             tcp_connections: DashMap::new(),
+            labels: DashMap::new(),
+            kickers: DashMap::new(),
+            line_buffers: DashMap::new(),
+            scrollback: Mutex::new(VecDeque::new()),
+            scrollback_cap: 0,
+            tcp_rate: None,
+            dropped_frames: AtomicU64::new(0),
+            tap: None,
+            tap_dropped: AtomicU64::new(0),
+            controllers: None,
         }
     }
 
+    pub fn with_tcp_rate(mut self, bytes_per_sec: Option<u64>) -> Self {
+        self.tcp_rate = bytes_per_sec.map(|r| Mutex::new(TokenBucket::new(r)));
+        self
+    }
+
+    pub fn with_tap(mut self, tap: Option<channel::Sender<Bytes>>) -> Self {
+        self.tap = tap;
+        self
+    }
+
+    pub fn with_single_controller(mut self, enabled: bool) -> Self {
+        self.controllers = enabled.then(|| Mutex::new(VecDeque::new()));
+        self
+    }
+
+    /// Put a new client in line for control; true if it is the controller
+    /// (always, without --single-controller)
+    pub fn join_controllers(&self, addr: SocketAddr) -> bool {
+        let Some(line) = &self.controllers else {
+            return true;
+        };
+        let mut line = line.lock().unwrap_or_else(|e| e.into_inner());
+        line.push_back(addr);
+        line.len() == 1
+    }
+
+    /// The client whose writes reach the device, with --single-controller
+    pub fn controller(&self) -> Option<SocketAddr> {
+        let line = self.controllers.as_ref()?;
+        let line = line.lock().unwrap_or_else(|e| e.into_inner());
+        line.front().copied()
+    }
+
+    /// Whether this client's data goes to the device
+    pub fn may_write(&self, addr: &SocketAddr) -> bool {
+        self.controllers.is_none() || self.controller().as_ref() == Some(addr)
+    }
+
+    /// Take a departing client out of line; returns the client promoted to
+    /// controller if it was the controller
+    pub fn leave_controllers(&self, addr: &SocketAddr) -> Option<SocketAddr> {
+        let mut line = self
+            .controllers
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let pos = line.iter().position(|a| a == addr)?;
+        line.remove(pos);
+        match pos {
+            0 => line.front().copied(),
+            _ => None,
+        }
+    }
+
+    pub fn tcp_rate(&self) -> Option<u64> {
+        self.tcp_rate
+            .as_ref()
+            .map(|b| b.lock().unwrap_or_else(|e| e.into_inner()).rate())
+    }
+
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    /// Frames the --tap missed because its queue was full
+    pub fn tap_dropped(&self) -> u64 {
+        self.tap_dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn with_scrollback(mut self, capacity: usize) -> Self {
+        self.scrollback = Mutex::new(VecDeque::with_capacity(capacity));
+        self.scrollback_cap = capacity;
+        self
+    }
+
     pub fn insert(&self, addr: SocketAddr, tx: channel::Sender<Bytes>) {
+        if self.scrollback_cap == 0 {
+            self.tcp_connections.insert(addr, tx);
+            return;
+        }
+        // Hold the ring lock across the flush and the insert so a concurrent
+        // broadcast is either part of the replay or delivered live, never both.
+        let ring = self.scrollback.lock().unwrap_or_else(|e| e.into_inner());
+        if !ring.is_empty() {
+            let (a, b) = ring.as_slices();
+            let _ = tx.try_send(Bytes::from([a, b].concat()));
+        }
         self.tcp_connections.insert(addr, tx);
     }
 
+    /// Forget a client. Idempotent, so the connection's own teardown and a
+    /// failed broadcast can both call it.
     pub fn remove(&self, addr: &SocketAddr) {
         self.tcp_connections.remove(addr);
+        self.labels.remove(addr);
+        self.kickers.remove(addr);
+        self.line_buffers.remove(addr);
     }
 
     pub fn dispose(&self) {
         self.tcp_connections.clear();
+        self.labels.clear();
+        self.kickers.clear();
+        self.line_buffers.clear();
     }
 
-    pub fn broadcast(&self, data: Bytes) {
-        // Clone senders without holding any global lock; DashMap provides
-        // per-bucket locking which is brief during iteration.
-        let snapshot: Vec<(SocketAddr, channel::Sender<Bytes>)> = self
-            .tcp_connections
+    pub fn set_mode(&self, addr: SocketAddr, mode: ClientMode) {
+        match mode {
+            ClientMode::Raw => {
+                self.line_buffers.remove(&addr);
+            }
+            ClientMode::Line => {
+                self.line_buffers.entry(addr).or_default();
+            }
+        }
+    }
+
+    pub fn set_kicker(&self, addr: SocketAddr, kick: impl Fn() + Send + Sync + 'static) {
+        self.kickers.insert(addr, Box::new(kick));
+    }
+
+    /// Disconnect a single client. Returns false if it was already gone.
+    pub fn kick(&self, addr: &SocketAddr) -> bool {
+        let kicker = self.kickers.remove(addr);
+        self.remove(addr);
+        match kicker {
+            Some((_, kick)) => {
+                kick();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Disconnect every client; returns how many were still connected
+    pub fn kick_all(&self) -> usize {
+        self.sorted_addrs()
             .iter()
-            .map(|e| (*e.key(), e.value().clone()))
-            .collect();
+            .filter(|addr| self.kick(addr))
+            .count()
+    }
+
+    /// Open connections whose peer has this IP address
+    pub fn connections_from(&self, ip: IpAddr) -> usize {
+        self.tcp_connections
+            .iter()
+            .filter(|e| e.key().ip() == ip)
+            .count()
+    }
+
+    /// Connected client addresses in a stable order
+    pub fn sorted_addrs(&self) -> Vec<SocketAddr> {
+        let mut addrs: Vec<SocketAddr> = self.tcp_connections.iter().map(|e| *e.key()).collect();
+        addrs.sort();
+        addrs
+    }
+
+    pub fn set_label(&self, addr: SocketAddr, label: String) {
+        self.labels.insert(addr, label);
+    }
+
+    pub fn label(&self, addr: &SocketAddr) -> Option<String> {
+        self.labels.get(addr).map(|l| l.value().clone())
+    }
+
+    /// Label if the client announced one, otherwise its address
+    pub fn display_name(&self, addr: &SocketAddr) -> String {
+        match self.label(addr) {
+            Some(label) => format!("{label} ({addr})"),
+            None => addr.to_string(),
+        }
+    }
 
-        let mut to_remove: Vec<SocketAddr> = Vec::new();
+    pub fn broadcast(&self, data: Bytes) {
+        if let Some(bucket) = &self.tcp_rate {
+            // Drop rather than block: the serial reader must keep draining the device
+            let allowed = bucket
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .try_take(data.len() as u64);
+            if !allowed {
+                self.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        if let Some(tap) = &self.tap {
+            if let Err(channel::TrySendError::Full(_)) = tap.try_send(data.clone()) {
+                self.tap_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        let snapshot = if self.scrollback_cap == 0 {
+            self.snapshot()
+        } else {
+            let mut ring = self.scrollback.lock().unwrap_or_else(|e| e.into_inner());
+            let tail = &data[data.len().saturating_sub(self.scrollback_cap)..];
+            let overflow = (ring.len() + tail.len()).saturating_sub(self.scrollback_cap);
+            ring.drain(..overflow);
+            ring.extend(tail);
+            self.snapshot()
+        };
+
+        let mut to_remove: Vec<(SocketAddr, channel::Sender<Bytes>)> = Vec::new();
         for (addr, tx) in snapshot.into_iter() {
-            match tx.try_send(data.clone()) {
-                Ok(()) => {}
-                Err(channel::TrySendError::Full(_)) => {
-                    // Slow client: drop this client to enforce backpressure
-                    to_remove.push(addr);
-                }
-                Err(channel::TrySendError::Disconnected(_)) => {
-                    to_remove.push(addr);
+            // Per-client transform; raw clients share the same buffer
+            let payloads = match self.line_buffers.get_mut(&addr) {
+                Some(mut pending) => split_lines(&mut pending, &data),
+                None => vec![data.clone()],
+            };
+            for payload in payloads {
+                match tx.try_send(payload) {
+                    Ok(()) => {}
+                    Err(channel::TrySendError::Full(_)) => {
+                        // Slow client: drop this client to enforce backpressure
+                        to_remove.push((addr, tx));
+                        break;
+                    }
+                    Err(channel::TrySendError::Disconnected(_)) => {
+                        to_remove.push((addr, tx));
+                        break;
+                    }
                 }
             }
         }
 
-        for addr in to_remove {
-            self.remove(&addr);
+        for (addr, tx) in to_remove {
+            self.remove_stale(&addr, &tx);
+        }
+    }
+
+    // The client may have gone (and the address been reused by a new
+    // connection) since the snapshot; only drop the entry we actually failed on.
+    fn remove_stale(&self, addr: &SocketAddr, stale: &channel::Sender<Bytes>) {
+        let removed = self
+            .tcp_connections
+            .remove_if(addr, |_, current| current.same_channel(stale));
+        if removed.is_some() {
+            self.labels.remove(addr);
+            self.kickers.remove(addr);
+            self.line_buffers.remove(addr);
         }
     }
+
+    fn snapshot(&self) -> Vec<(SocketAddr, channel::Sender<Bytes>)> {
+        // Clone senders without holding any global lock; DashMap provides
+        // per-bucket locking which is brief during iteration.
+        self.tcp_connections
+            .iter()
+            .map(|e| (*e.key(), e.value().clone()))
+            .collect()
+    }
+}
+
+// Longest partial line held for a line-mode client before it is sent as is
+const MAX_PENDING_LINE: usize = 4096;
+
+/// Append `data` to `pending` and return every completed line (with its `\n`).
+fn split_lines(pending: &mut Vec<u8>, data: &[u8]) -> Vec<Bytes> {
+    pending.extend_from_slice(data);
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while let Some(nl) = pending[start..].iter().position(|&b| b == b'\n') {
+        let end = start + nl + 1;
+        lines.push(Bytes::copy_from_slice(&pending[start..end]));
+        start = end;
+    }
+    pending.drain(..start);
+    if pending.len() > MAX_PENDING_LINE {
+        lines.push(Bytes::from(std::mem::take(pending)));
+    }
+    lines
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn control_passes_in_arrival_order() {
+        let state = SharedState::new().with_single_controller(true);
+        let [a, b, c]: [SocketAddr; 3] =
+            ["127.0.0.1:12000", "127.0.0.1:12001", "127.0.0.1:12002"].map(|s| s.parse().unwrap());
+        assert!(state.join_controllers(a));
+        assert!(!state.join_controllers(b));
+        assert!(!state.join_controllers(c));
+        assert!(state.may_write(&a));
+        assert!(!state.may_write(&b));
+
+        // an observer leaving changes nothing; the controller leaving promotes the next
+        assert_eq!(state.leave_controllers(&c), None);
+        assert_eq!(state.leave_controllers(&a), Some(b));
+        assert!(state.may_write(&b));
+        assert_eq!(state.leave_controllers(&b), None);
+        assert_eq!(state.controller(), None);
+
+        let open = SharedState::new();
+        assert!(open.join_controllers(a));
+        assert!(open.join_controllers(b));
+        assert!(open.may_write(&b));
+        assert_eq!(open.leave_controllers(&a), None);
+    }
+
     #[test]
     fn broadcast_removes_dead_receivers() {
         let (tx_alive, rx_alive) = channel::bounded::<Bytes>(1);
@@ -122,6 +413,226 @@ mod tests {
         assert_eq!(rx2.recv().unwrap(), Bytes::from_static(b"abc"));
     }
 
+    #[test]
+    fn scrollback_replayed_to_new_client() {
+        let state = SharedState::new().with_scrollback(8);
+        state.broadcast(Bytes::from_static(b"0123"));
+        state.broadcast(Bytes::from_static(b"456789"));
+
+        let (tx, rx) = channel::unbounded::<Bytes>();
+        let a: SocketAddr = "127.0.0.1:14000".parse().unwrap();
+        state.insert(a, tx);
+        // Only the most recent 8 bytes are kept
+        assert_eq!(rx.recv().unwrap(), Bytes::from_static(b"23456789"));
+
+        state.broadcast(Bytes::from_static(b"x"));
+        assert_eq!(rx.recv().unwrap(), Bytes::from_static(b"x"));
+    }
+
+    #[test]
+    fn scrollback_disabled_sends_nothing_on_insert() {
+        let state = SharedState::new();
+        state.broadcast(Bytes::from_static(b"early"));
+        let (tx, rx) = channel::unbounded::<Bytes>();
+        state.insert("127.0.0.1:14001".parse().unwrap(), tx);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn tcp_rate_drops_frames_over_budget() {
+        let state = SharedState::new().with_tcp_rate(Some(4));
+        let (tx, rx) = channel::unbounded::<Bytes>();
+        state.insert("127.0.0.1:15000".parse().unwrap(), tx);
+
+        state.broadcast(Bytes::from_static(b"abcd"));
+        state.broadcast(Bytes::from_static(b"efgh"));
+
+        assert_eq!(rx.recv().unwrap(), Bytes::from_static(b"abcd"));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(state.dropped_frames(), 1);
+        assert_eq!(state.tcp_rate(), Some(4));
+    }
+
+    #[test]
+    fn tap_counts_frames_it_falls_behind_on() {
+        let (tap_tx, tap_rx) = channel::bounded::<Bytes>(1);
+        let state = SharedState::new().with_tap(Some(tap_tx));
+
+        state.broadcast(Bytes::from_static(b"one"));
+        state.broadcast(Bytes::from_static(b"two"));
+
+        assert_eq!(tap_rx.recv().unwrap(), Bytes::from_static(b"one"));
+        assert_eq!(state.tap_dropped(), 1);
+        assert_eq!(state.dropped_frames(), 0);
+    }
+
+    #[test]
+    fn line_mode_clients_get_whole_lines() {
+        let state = SharedState::new();
+        let raw: SocketAddr = "127.0.0.1:19000".parse().unwrap();
+        let line: SocketAddr = "127.0.0.1:19001".parse().unwrap();
+        let (tx_raw, rx_raw) = channel::unbounded::<Bytes>();
+        let (tx_line, rx_line) = channel::unbounded::<Bytes>();
+        state.insert(raw, tx_raw);
+        state.insert(line, tx_line);
+        state.set_mode(line, ClientMode::Line);
+
+        state.broadcast(Bytes::from_static(b"temp=2"));
+        state.broadcast(Bytes::from_static(b"1\nhum=4"));
+        state.broadcast(Bytes::from_static(b"0\nok\n"));
+
+        let raw_got: Vec<Bytes> = rx_raw.try_iter().collect();
+        assert_eq!(raw_got.len(), 3);
+        let line_got: Vec<Bytes> = rx_line.try_iter().collect();
+        assert_eq!(
+            line_got,
+            vec![
+                Bytes::from_static(b"temp=21\n"),
+                Bytes::from_static(b"hum=40\n"),
+                Bytes::from_static(b"ok\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn overlong_partial_line_is_flushed() {
+        let mut pending = Vec::new();
+        let big = vec![b'a'; MAX_PENDING_LINE + 1];
+        assert_eq!(split_lines(&mut pending, &big).len(), 1);
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn label_shown_until_removed() {
+        let state = SharedState::new();
+        let a: SocketAddr = "127.0.0.1:17000".parse().unwrap();
+        let (tx, _rx) = channel::unbounded::<Bytes>();
+        state.insert(a, tx);
+        assert_eq!(state.display_name(&a), "127.0.0.1:17000");
+        state.set_label(a, "scope".into());
+        assert_eq!(state.display_name(&a), "scope (127.0.0.1:17000)");
+        state.remove(&a);
+        assert_eq!(state.label(&a), None);
+    }
+
+    #[test]
+    fn kick_removes_and_signals_once() {
+        use std::sync::atomic::AtomicUsize;
+        use std::sync::Arc;
+
+        let state = SharedState::new();
+        let a: SocketAddr = "127.0.0.1:18000".parse().unwrap();
+        let (tx, rx) = channel::unbounded::<Bytes>();
+        state.insert(a, tx);
+        let kicked = Arc::new(AtomicUsize::new(0));
+        let k = Arc::clone(&kicked);
+        state.set_kicker(a, move || {
+            k.fetch_add(1, Ordering::Relaxed);
+        });
+
+        assert!(state.kick(&a));
+        assert!(!state.kick(&a));
+        assert_eq!(kicked.load(Ordering::Relaxed), 1);
+        assert!(!state.tcp_connections.contains_key(&a));
+        // sender dropped, so the writer thread sees a disconnect
+        assert!(rx.recv().is_err());
+    }
+
+    #[test]
+    fn kick_all_counts_clients_it_closed() {
+        let state = SharedState::new();
+        let mut receivers = Vec::new();
+        for port in [18100, 18101] {
+            let addr: SocketAddr = format!("127.0.0.1:{port}").parse().unwrap();
+            let (tx, rx) = channel::unbounded::<Bytes>();
+            state.insert(addr, tx);
+            state.set_kicker(addr, || {});
+            receivers.push(rx);
+        }
+        assert_eq!(state.kick_all(), 2);
+        assert!(state.tcp_connections.is_empty());
+        assert_eq!(state.kick_all(), 0);
+    }
+
+    #[test]
+    fn failed_send_spares_a_reconnected_client() {
+        let state = SharedState::new();
+        let a: SocketAddr = "127.0.0.1:20000".parse().unwrap();
+        let (old_tx, old_rx) = channel::unbounded::<Bytes>();
+        state.insert(a, old_tx.clone());
+        // Same address comes back on a new connection before the cleanup runs
+        let (new_tx, new_rx) = channel::unbounded::<Bytes>();
+        state.insert(a, new_tx);
+        state.set_label(a, "fresh".into());
+        drop(old_rx);
+
+        state.remove_stale(&a, &old_tx);
+        assert!(state.tcp_connections.contains_key(&a));
+        assert_eq!(state.label(&a).as_deref(), Some("fresh"));
+        state.broadcast(Bytes::from_static(b"x"));
+        assert_eq!(new_rx.recv().unwrap(), Bytes::from_static(b"x"));
+    }
+
+    #[test]
+    fn concurrent_remove_and_broadcast() {
+        use std::sync::Arc;
+
+        let state = Arc::new(SharedState::new().with_scrollback(16));
+        let addrs: Vec<SocketAddr> = (0..32)
+            .map(|i| format!("127.0.0.1:{}", 21000 + i).parse().unwrap())
+            .collect();
+        let churn: Vec<_> = (0..4)
+            .map(|t| {
+                let state = Arc::clone(&state);
+                let addrs = addrs.clone();
+                std::thread::spawn(move || {
+                    for round in 0..200 {
+                        for addr in addrs.iter().skip(t).step_by(4) {
+                            let (tx, rx) = channel::bounded::<Bytes>(2);
+                            state.insert(*addr, tx);
+                            if round % 2 == 0 {
+                                state.set_mode(*addr, ClientMode::Line);
+                            }
+                            drop(rx);
+                            state.remove(addr);
+                            state.remove(addr);
+                        }
+                    }
+                })
+            })
+            .collect();
+        let broadcaster = {
+            let state = Arc::clone(&state);
+            std::thread::spawn(move || {
+                for _ in 0..2000 {
+                    state.broadcast(Bytes::from_static(b"tick\n"));
+                }
+            })
+        };
+        for h in churn {
+            h.join().unwrap();
+        }
+        broadcaster.join().unwrap();
+
+        assert!(state.tcp_connections.is_empty());
+        assert!(state.line_buffers.is_empty());
+        assert!(state.labels.is_empty());
+    }
+
+    #[test]
+    fn connections_counted_per_ip() {
+        let state = SharedState::new();
+        let (tx, _rx) = channel::unbounded::<Bytes>();
+        for addr in ["10.0.0.1:1000", "10.0.0.1:1001", "10.0.0.2:1000"] {
+            state.insert(addr.parse().unwrap(), tx.clone());
+        }
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        assert_eq!(state.connections_from(ip), 2);
+        state.remove(&"10.0.0.1:1000".parse().unwrap());
+        assert_eq!(state.connections_from(ip), 1);
+        assert_eq!(state.connections_from("10.0.0.3".parse().unwrap()), 0);
+    }
+
     #[test]
     fn dispose_clears_all_connections() {
         let (tx1, _rx1) = channel::unbounded::<Bytes>();
@@ -0,0 +1,57 @@
+// Copy inspector text out of the TUI. Uses the system clipboard when built
+// with the `clipboard` feature and one is reachable; otherwise the text goes
+// to a temp file whose path is reported instead.
+
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Default)]
+pub struct Clipboard {
+    // Kept alive for the whole session: on X11/Wayland the copied text is
+    // served by this process and vanishes once the handle is dropped.
+    #[cfg(feature = "clipboard")]
+    board: Option<arboard::Clipboard>,
+}
+
+impl Clipboard {
+    /// Returns a one-line notice describing where the text went.
+    pub fn copy(&mut self, text: &str) -> String {
+        #[cfg(feature = "clipboard")]
+        {
+            if self.board.is_none() {
+                self.board = arboard::Clipboard::new().ok();
+            }
+            if let Some(board) = self.board.as_mut() {
+                if board.set_text(text.to_owned()).is_ok() {
+                    return format!("copied {} line(s) to clipboard", text.lines().count());
+                }
+            }
+        }
+        match write_temp(text) {
+            Ok(path) => format!("no clipboard; saved to {}", path.display()),
+            Err(e) => format!("copy failed: {e}"),
+        }
+    }
+}
+
+fn write_temp(text: &str) -> std::io::Result<PathBuf> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = std::env::temp_dir().join(format!("sergw-inspector-{millis}.txt"));
+    std::fs::write(&path, text)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_fallback_round_trips() {
+        let path = write_temp("41 42\n43\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "41 42\n43\n");
+        let _ = std::fs::remove_file(path);
+    }
+}
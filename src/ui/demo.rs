@@ -0,0 +1,98 @@
+// `--inspector-demo` (hidden): feed generated samples into the inspector so
+// scrolling, formatting and filtering can be worked on without hardware. The
+// sequence is fixed, so a given sample number always looks the same.
+
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crossbeam_channel as channel;
+
+use crate::ui::inspector::{offer_sample, DirectionTag, InspectorFilter, Sample};
+use crate::ui::overview::Counters;
+
+const DEMO_PERIOD: Duration = Duration::from_millis(250);
+// Sizes cycle through these so wrapping and --inspector-sample-max show up
+const DEMO_SIZES: [usize; 6] = [1, 12, 40, 96, 300, 1500];
+
+/// Two pretend clients in the documentation range, so they never collide
+/// with a real peer in the clients pane
+fn demo_client(n: usize) -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::new(192, 0, 2, 1 + (n % 2) as u8), 40_000))
+}
+
+/// Sample number `i`: device lines, client commands and the odd binary blob
+pub fn demo_sample(i: usize, max: Option<usize>) -> Sample {
+    let len = DEMO_SIZES[i % DEMO_SIZES.len()];
+    let data: Vec<u8> = match i % 3 {
+        0 => format!("seq={i} temp=21.{} hum=40%\r\n", i % 10)
+            .bytes()
+            .cycle()
+            .take(len)
+            .collect(),
+        1 => (0..len).map(|b| (b * 7 + i) as u8).collect(),
+        _ => format!("AT+READ={i}\r").bytes().cycle().take(len).collect(),
+    };
+    let dir = match i % 3 {
+        2 => DirectionTag::Outbound(demo_client(i / 3)),
+        _ => DirectionTag::Inbound,
+    };
+    Sample::capture(dir, &data, max)
+}
+
+/// Send a demo sample every `DEMO_PERIOD` until `stop`, through the same
+/// filter and drop accounting as real traffic
+pub fn spawn_inspector_demo(
+    tx: channel::Sender<Sample>,
+    filter: Arc<InspectorFilter>,
+    counters: Arc<Counters>,
+    max: Option<usize>,
+    stop: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut i = 0;
+        while !stop.load(Ordering::Relaxed) {
+            let sample = demo_sample(i, max);
+            if filter.wants(&sample.dir) {
+                offer_sample(&tx, sample, &counters.inspector_dropped);
+            }
+            i += 1;
+            thread::sleep(DEMO_PERIOD);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_repeatable_and_varied() {
+        let first: Vec<Sample> = (0..DEMO_SIZES.len() * 3)
+            .map(|i| demo_sample(i, None))
+            .collect();
+        for (i, s) in first.iter().enumerate() {
+            let again = demo_sample(i, None);
+            assert_eq!((&s.data, s.len), (&again.data, again.len));
+            assert_eq!(s.len, DEMO_SIZES[i % DEMO_SIZES.len()]);
+        }
+        assert!(first.iter().any(|s| s.dir == DirectionTag::Inbound));
+        let clients: Vec<_> = first
+            .iter()
+            .filter_map(|s| match s.dir {
+                DirectionTag::Outbound(addr) => Some(addr),
+                DirectionTag::Inbound => None,
+            })
+            .collect();
+        assert!(clients.contains(&demo_client(0)) && clients.contains(&demo_client(1)));
+        assert!(first.iter().any(|s| s.data.contains(&0)));
+    }
+
+    #[test]
+    fn sample_max_caps_the_data() {
+        let s = demo_sample(5, Some(64));
+        assert_eq!((s.data.len(), s.len), (64, 1500));
+    }
+}
@@ -0,0 +1,84 @@
+// Overview injection palette: a line typed in the TUI goes straight to the
+// device. Text is sent as typed plus a newline; a leading `0x` switches to hex
+// so binary commands can be entered as byte pairs ("0x 02 10,ff 0d0a").
+
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InjectError {
+    #[error("nothing to send")]
+    Empty,
+    #[error("invalid hex '{0}' (expected byte pairs such as 0d 0a)")]
+    BadHex(String),
+}
+
+/// Bytes for one palette line
+pub fn parse_injection(input: &str) -> Result<Vec<u8>, InjectError> {
+    let Some(hex) = input
+        .strip_prefix("0x")
+        .or_else(|| input.strip_prefix("0X"))
+    else {
+        if input.is_empty() {
+            return Err(InjectError::Empty);
+        }
+        let mut bytes = input.as_bytes().to_vec();
+        bytes.push(b'\n');
+        return Ok(bytes);
+    };
+    parse_hex(hex)
+}
+
+/// Byte pairs separated by spaces or commas, each optionally `0x`-prefixed
+pub fn parse_hex(hex: &str) -> Result<Vec<u8>, InjectError> {
+    let mut bytes = Vec::new();
+    for token in hex.split(|c: char| c.is_whitespace() || c == ',') {
+        if token.is_empty() {
+            continue;
+        }
+        let digits = token
+            .strip_prefix("0x")
+            .or_else(|| token.strip_prefix("0X"))
+            .unwrap_or(token);
+        if digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(InjectError::BadHex(token.to_string()));
+        }
+        for i in (0..digits.len()).step_by(2) {
+            let byte = u8::from_str_radix(&digits[i..i + 2], 16)
+                .map_err(|_| InjectError::BadHex(token.to_string()))?;
+            bytes.push(byte);
+        }
+    }
+    if bytes.is_empty() {
+        return Err(InjectError::Empty);
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_gets_a_newline() {
+        assert_eq!(parse_injection("AT").unwrap(), b"AT\n");
+        assert_eq!(parse_injection(""), Err(InjectError::Empty));
+    }
+
+    #[test]
+    fn hex_pairs_with_mixed_separators() {
+        assert_eq!(
+            parse_injection("0x 02 10,ff 0d0a").unwrap(),
+            [0x02, 0x10, 0xff, 0x0d, 0x0a]
+        );
+        assert_eq!(parse_injection("0x01, 0x7F").unwrap(), [0x01, 0x7f]);
+        assert_eq!(parse_injection("0x"), Err(InjectError::Empty));
+        assert_eq!(
+            parse_injection("0x 02 1"),
+            Err(InjectError::BadHex("1".into()))
+        );
+        assert_eq!(
+            parse_injection("0x zz"),
+            Err(InjectError::BadHex("zz".into()))
+        );
+    }
+}
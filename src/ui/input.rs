@@ -0,0 +1,129 @@
+// One-line text input for the chat UIs: cursor movement and in-place editing,
+// plus whole-string inserts for bracketed paste.
+
+/// Text being typed, with a cursor counted in chars
+#[derive(Debug, Default)]
+pub struct InputLine {
+    text: String,
+    cursor: usize,
+}
+
+impl InputLine {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        let at = self.byte_index(self.cursor);
+        self.text.insert(at, c);
+        self.cursor += 1;
+    }
+
+    /// A paste goes in as one edit; CRLF and CR line breaks become `\n`
+    pub fn insert_str(&mut self, s: &str) {
+        let s = s.replace("\r\n", "\n").replace('\r', "\n");
+        let at = self.byte_index(self.cursor);
+        self.text.insert_str(at, &s);
+        self.cursor += s.chars().count();
+    }
+
+    pub fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.text.remove(self.byte_index(self.cursor));
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            self.text.remove(self.byte_index(self.cursor));
+        }
+    }
+
+    pub fn left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.chars().count());
+    }
+
+    pub fn home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.cursor = self.text.chars().count();
+    }
+
+    /// The part of the line that fits in `width` columns with the cursor in
+    /// view (line breaks drawn as `↵`), and the cursor's column within it
+    pub fn view(&self, width: usize) -> (String, usize) {
+        let width = width.max(1);
+        // keep a cell free after the text for the cursor at the end
+        let skip = (self.cursor + 1).saturating_sub(width);
+        let visible = self
+            .text
+            .chars()
+            .skip(skip)
+            .take(width)
+            .map(|c| if c == '\n' { '↵' } else { c })
+            .collect();
+        (visible, self.cursor - skip)
+    }
+
+    fn byte_index(&self, chars: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(chars)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_happen_at_the_cursor() {
+        let mut input = InputLine::default();
+        input.insert_str("AT+CGM");
+        input.home();
+        input.right();
+        input.right();
+        input.insert('é');
+        assert_eq!(input.as_str(), "ATé+CGM");
+        input.backspace();
+        input.delete();
+        assert_eq!(input.as_str(), "ATCGM");
+        input.end();
+        input.insert('R');
+        assert_eq!(input.as_str(), "ATCGMR");
+        input.left();
+        input.left();
+        input.insert_str("x\r\ny\rz");
+        assert_eq!(input.as_str(), "ATCGx\ny\nzMR");
+        input.clear();
+        input.backspace();
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn view_follows_the_cursor() {
+        let mut input = InputLine::default();
+        input.insert_str("abc\ndef");
+        assert_eq!(input.view(20), ("abc↵def".to_string(), 7));
+        assert_eq!(input.view(4), ("def".to_string(), 3));
+        input.home();
+        assert_eq!(input.view(4), ("abc↵".to_string(), 0));
+    }
+}
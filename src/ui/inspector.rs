@@ -1,11 +1,15 @@
 use std::collections::VecDeque;
 use std::net::SocketAddr;
-// no time imports needed here
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Instant, SystemTime};
 
 use bytes::Bytes;
+use crossbeam_channel::{Sender, TrySendError};
 use ratatui::layout::Rect;
+use ratatui::style::{Modifier, Style};
 use ratatui::text::{Line, Span};
-use ratatui::widgets::{Paragraph, Wrap};
+use ratatui::widgets::{List, ListItem, Paragraph, Wrap};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum DirectionTag {
@@ -13,43 +17,323 @@ pub enum DirectionTag {
     Outbound(SocketAddr),
 }
 
+impl DirectionTag {
+    pub fn device(&self) -> DeviceId {
+        match self {
+            DirectionTag::Inbound => DeviceId::Serial,
+            DirectionTag::Outbound(a) => DeviceId::Client(*a),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum DeviceId {
+    /// Sidebar entry showing every device's traffic merged
+    All,
     Serial,
     Client(SocketAddr),
 }
 
+impl DeviceId {
+    fn sends(&self, dir: &DirectionTag) -> bool {
+        *self == DeviceId::All || *self == dir.device()
+    }
+}
+
+/// Which way the inspector's traffic may flow, independent of the device
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DirectionFilter {
+    #[default]
+    Both,
+    /// serial → clients
+    Inbound,
+    /// clients → serial
+    Outbound,
+}
+
+impl DirectionFilter {
+    pub fn next(self) -> Self {
+        match self {
+            DirectionFilter::Both => DirectionFilter::Inbound,
+            DirectionFilter::Inbound => DirectionFilter::Outbound,
+            DirectionFilter::Outbound => DirectionFilter::Both,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DirectionFilter::Both => "both",
+            DirectionFilter::Inbound => "in (serial→tcp)",
+            DirectionFilter::Outbound => "out (tcp→serial)",
+        }
+    }
+
+    pub fn wants(self, dir: &DirectionTag) -> bool {
+        match self {
+            DirectionFilter::Both => true,
+            DirectionFilter::Inbound => *dir == DirectionTag::Inbound,
+            DirectionFilter::Outbound => matches!(dir, DirectionTag::Outbound(_)),
+        }
+    }
+}
+
+/// Mirror of the inspector's selection that producer threads consult before
+/// sampling, so the bounded sample channel only carries what will be shown.
+#[derive(Default)]
+pub struct InspectorFilter {
+    enabled: AtomicBool,
+    device: RwLock<Option<DeviceId>>,
+    direction: RwLock<DirectionFilter>,
+}
+
+impl InspectorFilter {
+    pub fn wants(&self, dir: &DirectionTag) -> bool {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return false;
+        }
+        if !self
+            .direction
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .wants(dir)
+        {
+            return false;
+        }
+        match &*self.device.read().unwrap_or_else(|e| e.into_inner()) {
+            Some(dev) => dev.sends(dir),
+            None => true,
+        }
+    }
+
+    pub fn set_direction(&self, direction: DirectionFilter) {
+        *self.direction.write().unwrap_or_else(|e| e.into_inner()) = direction;
+    }
+
+    pub fn set(&self, enabled: bool, device: Option<&DeviceId>) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        let current = self.device.read().unwrap_or_else(|e| e.into_inner());
+        if current.as_ref() == device {
+            return;
+        }
+        drop(current);
+        *self.device.write().unwrap_or_else(|e| e.into_inner()) = device.cloned();
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum DumpFormat {
+    // classic hexdump rows: hex bytes followed by their ASCII
+    HexAscii,
     Hex,
     Ascii,
+    // lossless ASCII: control bytes rendered as C-style escapes
+    AsciiEscaped,
     Dec,
 }
 
+impl DumpFormat {
+    pub fn next(self) -> Self {
+        match self {
+            DumpFormat::HexAscii => DumpFormat::Hex,
+            DumpFormat::Hex => DumpFormat::Ascii,
+            DumpFormat::Ascii => DumpFormat::AsciiEscaped,
+            DumpFormat::AsciiEscaped => DumpFormat::Dec,
+            DumpFormat::Dec => DumpFormat::HexAscii,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InspectorView {
+    // long samples wrap (hex+ASCII spans several rows)
+    Wrapped,
+    // one row per sample, clipped at the right edge
+    Compact,
+}
+
+impl InspectorView {
+    pub fn toggle(self) -> Self {
+        match self {
+            InspectorView::Wrapped => InspectorView::Compact,
+            InspectorView::Compact => InspectorView::Wrapped,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InspectorView::Wrapped => "wrap",
+            InspectorView::Compact => "list",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Sample {
     pub dir: DirectionTag,
     pub data: Bytes,
+    // wall-clock arrival, used for exported timestamps
+    pub at: SystemTime,
+    // monotonic arrival, timed against the inspector's T0
+    pub seen: Instant,
+    // size of the original read; larger than data.len() when capped
+    pub len: usize,
+}
+
+impl Sample {
+    /// Copy at most `max` bytes of `data` (all of it when `max` is None).
+    pub fn capture(dir: DirectionTag, data: &[u8], max: Option<usize>) -> Self {
+        let kept = max.map_or(data.len(), |m| data.len().min(m));
+        Self {
+            dir,
+            data: Bytes::copy_from_slice(&data[..kept]),
+            at: SystemTime::now(),
+            seen: Instant::now(),
+            len: data.len(),
+        }
+    }
+
+    pub fn truncated(&self) -> usize {
+        self.len.saturating_sub(self.data.len())
+    }
+}
+
+/// Queue a sample for the inspector without blocking the I/O thread,
+/// counting it in `dropped` when the channel is full
+pub fn offer_sample(tx: &Sender<Sample>, sample: Sample, dropped: &AtomicU64) {
+    if let Err(TrySendError::Full(_)) = tx.try_send(sample) {
+        dropped.fetch_add(1, Ordering::Relaxed);
+    }
 }
 
 pub struct InspectorState {
     pub format: DumpFormat,
+    pub view: InspectorView,
     pub paused: bool,
     pub devices: Vec<DeviceId>,
     pub selected: usize,
+    pub direction: DirectionFilter,
     pub scroll: usize,
+    // false: long lines are clipped to the pane instead of wrapping
+    pub wrap: bool,
+    // columns hidden on the left of clipped lines
+    pub hscroll: usize,
     pub capture: VecDeque<Sample>,
+    // capture indices of up to two samples marked for diffing
+    pub marks: [Option<usize>; 2],
+    // one-line result of the last export, shown in the header
+    pub notice: Option<String>,
+    // left gutter with each sample's position in the filtered list and length
+    pub gutter: bool,
+    // zero point for relative sample times; the T0 key sets and resets it
+    pub t_zero: Option<Instant>,
+    // --inspector-hex-columns: bytes per row of a wrapped hex dump
+    pub hex_columns: Option<usize>,
 }
 
+const CAPTURE_MAX: usize = 4096;
+
 impl InspectorState {
     pub fn new() -> Self {
         Self {
-            format: DumpFormat::Hex,
+            format: DumpFormat::HexAscii,
+            view: InspectorView::Wrapped,
             paused: false,
-            devices: vec![DeviceId::Serial],
-            selected: 0,
+            devices: vec![DeviceId::All, DeviceId::Serial],
+            // serial rather than everything merged
+            selected: 1,
+            direction: DirectionFilter::Both,
             scroll: 0,
+            wrap: true,
+            hscroll: 0,
             capture: VecDeque::with_capacity(2048),
+            marks: [None, None],
+            notice: None,
+            gutter: false,
+            t_zero: None,
+            hex_columns: None,
+        }
+    }
+
+    pub fn push(&mut self, sample: Sample) {
+        self.capture.push_back(sample);
+        if self.capture.len() > CAPTURE_MAX {
+            self.capture.pop_front();
+            // keep marks pointing at the same samples; drop any that were evicted
+            for m in self.marks.iter_mut() {
+                *m = m.and_then(|i| i.checked_sub(1));
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.capture.clear();
+        self.scroll = 0;
+        self.hscroll = 0;
+        self.marks = [None, None];
+    }
+
+    /// Whether rows are clipped at the pane edge (and can scroll sideways)
+    pub fn clips(&self) -> bool {
+        !self.wrap || self.view == InspectorView::Compact
+    }
+
+    /// Back to the initial device list (everything, then serial selected)
+    pub fn reset_devices(&mut self) {
+        let fresh = Self::new();
+        self.devices = fresh.devices;
+        self.selected = fresh.selected;
+    }
+
+    /// Add a device seen in traffic; serial sits right after the merged entry
+    pub fn track_device(&mut self, device: DeviceId) {
+        if self.devices.contains(&device) {
+            return;
+        }
+        match device {
+            DeviceId::Serial => {
+                let at = self.devices.len().min(1);
+                self.devices.insert(at, device);
+                if self.selected >= at && self.devices.len() > 1 {
+                    self.selected += 1;
+                }
+            }
+            _ => self.devices.push(device),
+        }
+    }
+
+    /// Capture indices of samples passing the current device and direction filters
+    pub fn visible_indices(&self) -> Vec<usize> {
+        let filter = self.devices.get(self.selected);
+        self.capture
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| filter.map_or(true, |sel| sel.sends(&s.dir)))
+            .filter(|(_, s)| self.direction.wants(&s.dir))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Mark the sample at the bottom of the view; a third mark starts a new pair.
+    pub fn mark_current(&mut self) {
+        let visible = self.visible_indices();
+        let Some(&idx) = visible
+            .len()
+            .checked_sub(1 + self.scroll)
+            .and_then(|i| visible.get(i))
+        else {
+            return;
+        };
+        self.marks = match self.marks {
+            [Some(a), None] if a != idx => [Some(a), Some(idx)],
+            [Some(a), None] => [Some(a), None],
+            _ => [Some(idx), None],
+        };
+    }
+
+    pub fn marked_pair(&self) -> Option<(&Sample, &Sample)> {
+        match self.marks {
+            [Some(a), Some(b)] => Some((self.capture.get(a)?, self.capture.get(b)?)),
+            _ => None,
         }
     }
 }
@@ -72,40 +356,503 @@ pub fn dump_bytes(buf: &[u8], fmt: DumpFormat, max: usize) -> String {
             }
             s
         }
+        DumpFormat::AsciiEscaped => {
+            let mut s = String::new();
+            for &b in slice {
+                match b {
+                    b'\n' => s.push_str("\\n"),
+                    b'\r' => s.push_str("\\r"),
+                    b'\t' => s.push_str("\\t"),
+                    b'\\' => s.push_str("\\\\"),
+                    b if b.is_ascii_graphic() || b == b' ' => s.push(b as char),
+                    b => s.push_str(&format!("\\x{b:02x}")),
+                }
+            }
+            s
+        }
         DumpFormat::Dec => slice.iter().map(|b| format!("{b:03} ")).collect(),
+        DumpFormat::HexAscii => hex_ascii_rows(slice, HEX_ASCII_MAX_ROW).join("\n"),
     }
 }
 
-// Render wrapped text for inspector messages. Returns a Paragraph with Wrap enabled.
-pub fn inspector_paragraph(state: &InspectorState, area: Rect) -> Paragraph<'static> {
-    let filter = state.devices.get(state.selected);
-    // Build lines as strings first
-    let lines: Vec<String> = state
-        .capture
-        .iter()
-        .filter_map(|s| {
-            let dev = match s.dir {
-                DirectionTag::Inbound => DeviceId::Serial,
-                DirectionTag::Outbound(a) => DeviceId::Client(a),
-            };
-            if let Some(sel) = filter {
-                if &dev != sel {
-                    return None;
-                }
+const HEX_ASCII_MAX_ROW: usize = 16;
+
+/// Bytes per hex+ASCII row that fit in `cols` columns (each byte takes "xx "
+/// plus one ASCII cell, with room for the mark tag and the `|...|` gutter).
+pub fn hex_ascii_width(cols: usize) -> usize {
+    let per_row = cols.saturating_sub(4 + 3) / 4;
+    [16, 8, 4].into_iter().find(|&n| n <= per_row).unwrap_or(4)
+}
+
+/// `48 65 6c 6c 6f 0a  |Hello.|`, padded so the ASCII columns line up
+pub fn hex_ascii_rows(buf: &[u8], per_row: usize) -> Vec<String> {
+    buf.chunks(per_row.max(1))
+        .map(|row| {
+            let hex: String = row.iter().map(|b| format!("{b:02x} ")).collect();
+            let ascii: String = row
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            format!("{hex:<width$} |{ascii}|", width = per_row * 3)
+        })
+        .collect()
+}
+
+/// Hex in rows of `per_row` bytes, each padded to the full width so the
+/// columns of consecutive samples line up
+pub fn hex_rows(buf: &[u8], per_row: usize) -> Vec<String> {
+    let per_row = per_row.max(1);
+    buf.chunks(per_row)
+        .map(|row| {
+            let hex: String = row.iter().map(|b| format!("{b:02x} ")).collect();
+            format!("{hex:<width$}", width = per_row * 3)
+        })
+        .collect()
+}
+
+/// `seq` is 1-based within the filtered list; `len` is the original read size
+fn gutter(seq: usize, len: usize) -> String {
+    format!("{seq:>6} {len:>5}B  ")
+}
+
+/// Time from T0 to `seen`, e.g. "+12.345ms"; samples from before T0 are negative
+fn since_zero(zero: Instant, seen: Instant) -> String {
+    let (sign, d) = match seen.checked_duration_since(zero) {
+        Some(d) => ('+', d),
+        None => ('-', zero - seen),
+    };
+    let offset = format!("{sign}{:.3}ms", d.as_secs_f64() * 1000.0);
+    format!("{offset:>12}  ")
+}
+
+// Display rows for one sample; hex+ASCII samples span several rows
+fn sample_lines(state: &InspectorState, i: usize, seq: usize, cols: usize) -> Vec<String> {
+    let sample = &state.capture[i];
+    let mark = match state.marks {
+        [Some(a), _] if a == i => "[A] ",
+        [_, Some(b)] if b == i => "[B] ",
+        _ => "",
+    };
+    let mut tag = match state.gutter {
+        true => gutter(seq, sample.len),
+        false => String::new(),
+    };
+    if let Some(zero) = state.t_zero {
+        tag.push_str(&since_zero(zero, sample.seen));
+    }
+    tag.push_str(mark);
+    let cols = cols.saturating_sub(tag.chars().count());
+    let mut lines = match (state.format, state.view, state.hex_columns) {
+        // the whole sample as a single hexdump row
+        (DumpFormat::HexAscii, InspectorView::Compact, _) => {
+            hex_ascii_rows(&sample.data, sample.data.len())
+        }
+        (DumpFormat::HexAscii, InspectorView::Wrapped, _) => {
+            hex_ascii_rows(&sample.data, hex_ascii_width(cols))
+        }
+        (DumpFormat::Hex, InspectorView::Wrapped, Some(columns)) => hex_rows(&sample.data, columns),
+        (fmt, _, _) => vec![dump_bytes(&sample.data, fmt, 4096)],
+    };
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    if sample.truncated() > 0 {
+        if let Some(last) = lines.last_mut() {
+            last.push_str(&format!(" …(+{} bytes)", sample.truncated()));
+        }
+    }
+    let indent = " ".repeat(tag.chars().count());
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(n, l)| format!("{}{l}", if n == 0 { &tag } else { &indent }))
+        .collect()
+}
+
+/// Dump lines that fit in `rows` x `cols`, ending at the sample `scroll`
+/// places above the newest one.
+pub fn visible_lines(state: &InspectorState, rows: usize, cols: usize) -> Vec<String> {
+    let indices = state.visible_indices();
+    let end = indices.len().saturating_sub(state.scroll);
+    let mut out: Vec<String> = Vec::new();
+    for (pos, &i) in indices[..end].iter().enumerate().rev() {
+        if out.len() >= rows {
+            break;
+        }
+        for line in sample_lines(state, i, pos + 1, cols).into_iter().rev() {
+            if out.len() >= rows {
+                break;
             }
-            Some(dump_bytes(&s.data, state.format, 4096))
+            out.push(line);
+        }
+    }
+    out.reverse();
+    out
+}
+
+/// Columns moved per Left/Right press on clipped lines
+pub const HSCROLL_STEP: usize = 8;
+
+/// The `width` columns of `line` starting at `offset`. A `…` replaces the
+/// first or last visible column when text is hidden on that side.
+pub fn clip_line(line: &str, offset: usize, width: usize) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    if width == 0 || (offset == 0 && chars.len() <= width) {
+        return line.chars().take(width).collect();
+    }
+    let start = offset.min(chars.len());
+    let mut out: Vec<char> = chars[start..].iter().take(width).copied().collect();
+    if start + width < chars.len() {
+        out[width - 1] = '…';
+    }
+    if start > 0 {
+        match out.first_mut() {
+            Some(c) => *c = '…',
+            None => out.push('…'),
+        }
+    }
+    out.into_iter().collect()
+}
+
+/// Furthest useful `offset` for `clip_line`: the widest line's end is in view
+pub fn max_hscroll<'a>(lines: impl IntoIterator<Item = &'a str>, width: usize) -> usize {
+    lines
+        .into_iter()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0)
+        .saturating_sub(width)
+}
+
+// Render text for inspector messages: wrapped, or clipped at the pane edge when wrap is off.
+pub fn inspector_paragraph(state: &InspectorState, area: Rect) -> Paragraph<'static> {
+    let rows = area.height.saturating_sub(2) as usize;
+    let cols = area.width.saturating_sub(2) as usize;
+    let text_lines: Vec<Line> = visible_lines(state, rows, cols)
+        .into_iter()
+        .map(|s| match state.wrap {
+            true => Line::from(Span::raw(s)),
+            false => Line::from(Span::raw(clip_line(&s, state.hscroll, cols))),
         })
         .collect();
 
-    if state.scroll > 0 {
-        let _ = &lines;
+    match state.wrap {
+        true => Paragraph::new(text_lines).wrap(Wrap { trim: false }),
+        false => Paragraph::new(text_lines),
+    }
+}
+
+// One ListItem per sample for the compact view; nothing wraps, long rows are clipped.
+pub fn inspector_items(state: &InspectorState, area: Rect) -> List<'static> {
+    let rows = area.height.saturating_sub(2) as usize;
+    let cols = area.width.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = visible_lines(state, rows, cols)
+        .into_iter()
+        .map(|s| ListItem::new(clip_line(&s, state.hscroll, cols)))
+        .collect();
+    List::new(items)
+}
+
+/// Positions where the two buffers differ, including bytes present in only one
+pub fn diff_positions(a: &[u8], b: &[u8]) -> Vec<usize> {
+    (0..a.len().max(b.len()))
+        .filter(|&i| a.get(i) != b.get(i))
+        .collect()
+}
+
+// Byte-level hex diff of two marked samples; differing bytes in inverse video.
+pub fn diff_paragraph(a: &[u8], b: &[u8]) -> Paragraph<'static> {
+    let row = |this: &[u8], other: &[u8], tag: &'static str| -> Line<'static> {
+        let mut spans = vec![Span::raw(tag)];
+        for (i, byte) in this.iter().enumerate() {
+            let text = format!("{byte:02x}");
+            if other.get(i) == Some(byte) {
+                spans.push(Span::raw(text));
+            } else {
+                spans.push(Span::styled(
+                    text,
+                    Style::default().add_modifier(Modifier::REVERSED),
+                ));
+            }
+            spans.push(Span::raw(" "));
+        }
+        Line::from(spans)
+    };
+    let differing = diff_positions(a, b).len();
+    Paragraph::new(vec![
+        Line::from(format!(
+            "{differing} differing byte(s) | A: {} bytes, B: {} bytes",
+            a.len(),
+            b.len()
+        )),
+        row(a, b, "A: "),
+        row(b, a, "B: "),
+    ])
+    .wrap(Wrap { trim: false })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn full_channel_counts_drops() {
+        let (tx, rx) = crossbeam_channel::bounded(2);
+        let dropped = AtomicU64::new(0);
+        for i in 0..5u8 {
+            offer_sample(
+                &tx,
+                Sample::capture(DirectionTag::Inbound, &[i], None),
+                &dropped,
+            );
+        }
+        assert_eq!(dropped.load(Ordering::Relaxed), 3);
+        let kept: Vec<u8> = rx.try_iter().map(|s| s.data[0]).collect();
+        assert_eq!(kept, [0, 1]);
+    }
+
+    #[test]
+    fn ascii_escaped_is_lossless() {
+        let s = dump_bytes(b"ok\r\n\t\\\x00\xff", DumpFormat::AsciiEscaped, 64);
+        assert_eq!(s, "ok\\r\\n\\t\\\\\\x00\\xff");
+    }
+
+    #[test]
+    fn hex_ascii_rows_align() {
+        let rows = hex_ascii_rows(b"Hello\n0123456789abcdef", 16);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0],
+            "48 65 6c 6c 6f 0a 30 31 32 33 34 35 36 37 38 39  |Hello.0123456789|"
+        );
+        assert_eq!(rows[1], format!("{:<48} |abcdef|", "61 62 63 64 65 66 "));
+        assert_eq!(hex_ascii_width(80), 16);
+        assert_eq!(hex_ascii_width(50), 8);
+        assert_eq!(hex_ascii_width(10), 4);
+    }
+
+    #[test]
+    fn multi_row_samples_fill_viewport_from_bottom() {
+        let mut st = InspectorState::new();
+        st.push(sample(b"0123456789abcdefXYZ"));
+        st.push(sample(b"ok"));
+        // 2 rows of 16-byte hexdump + 1 row for "ok"
+        let lines = visible_lines(&st, 2, 80);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("|XYZ|"));
+        assert!(lines[1].ends_with("|ok|"));
+        st.scroll = 1;
+        assert!(visible_lines(&st, 5, 80)[0].ends_with("|0123456789abcdef|"));
+    }
+
+    #[test]
+    fn compact_view_keeps_one_row_per_sample() {
+        let mut st = InspectorState::new();
+        st.push(sample(b"0123456789abcdefXYZ"));
+        st.push(sample(b"ok"));
+        st.view = InspectorView::Compact;
+        let lines = visible_lines(&st, 5, 80);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("|0123456789abcdefXYZ|"));
+        assert!(lines[1].ends_with("|ok|"));
+        assert_eq!(st.view.toggle(), InspectorView::Wrapped);
     }
-    let start = lines
-        .len()
-        .saturating_sub(area.height.saturating_sub(2) as usize + state.scroll);
-    let visible = lines.into_iter().skip(start);
 
-    let text_lines: Vec<Line> = visible.map(|s| Line::from(Span::raw(s))).collect();
+    #[test]
+    fn gutter_numbers_the_filtered_samples() {
+        let a: SocketAddr = "127.0.0.1:16003".parse().unwrap();
+        let mut st = InspectorState::new();
+        st.push(sample(b"first"));
+        st.push(Sample::capture(DirectionTag::Outbound(a), b"AT\r", None));
+        st.push(Sample::capture(
+            DirectionTag::Inbound,
+            b"0123456789abcdefXYZ",
+            Some(17),
+        ));
+        assert!(visible_lines(&st, 5, 80)[0].starts_with("66 69"));
+
+        st.gutter = true;
+        st.format = DumpFormat::Ascii;
+        // the client's write is filtered out, so the device's reads are 1 and 2
+        let lines = visible_lines(&st, 5, 80);
+        assert_eq!(lines[0], "     1     5B  first");
+        assert!(lines[1].starts_with("     2    19B  0123456789abcdefX …(+2 bytes)"));
+
+        // continuation rows line up under the data, not the gutter
+        st.format = DumpFormat::HexAscii;
+        st.marks = [Some(2), None];
+        let lines = visible_lines(&st, 5, 80);
+        assert!(lines[1].starts_with("     2    19B  [A] 30 31"));
+        assert!(lines[2].starts_with(&format!("{:19}", "")));
+    }
+
+    #[test]
+    fn times_are_relative_to_t_zero() {
+        let mut st = InspectorState::new();
+        st.format = DumpFormat::Ascii;
+        let early = sample(b"AT");
+        let zero = early.seen + Duration::from_millis(5);
+        let mut reply = sample(b"OK");
+        reply.seen = zero + Duration::from_micros(12_345);
+        st.push(early);
+        st.push(reply);
+        assert_eq!(visible_lines(&st, 5, 80), ["AT", "OK"]);
+
+        st.t_zero = Some(zero);
+        st.marks = [Some(1), None];
+        assert_eq!(
+            visible_lines(&st, 5, 80),
+            ["    -5.000ms  AT", "   +12.345ms  [A] OK"]
+        );
+    }
+
+    #[test]
+    fn hex_columns_line_up() {
+        assert_eq!(hex_rows(b"ABCDE", 4), ["41 42 43 44 ", "45          "]);
+        assert!(hex_rows(b"", 4).is_empty());
+
+        let mut st = InspectorState::new();
+        st.format = DumpFormat::Hex;
+        st.push(sample(b"\x01\x02\x03"));
+        st.push(Sample::capture(
+            DirectionTag::Inbound,
+            b"\x0a\x0b\x0c\x0d\x0e",
+            Some(3),
+        ));
+        assert_eq!(
+            visible_lines(&st, 5, 80),
+            ["01 02 03 ", "0a 0b 0c  …(+2 bytes)"]
+        );
+        st.hex_columns = Some(4);
+        assert_eq!(
+            visible_lines(&st, 5, 80),
+            ["01 02 03    ", "0a 0b 0c     …(+2 bytes)"]
+        );
+        // one row per sample in the list view
+        st.view = InspectorView::Compact;
+        assert_eq!(visible_lines(&st, 5, 80)[0], "01 02 03 ");
+    }
+
+    #[test]
+    fn clipped_lines_mark_hidden_text() {
+        assert_eq!(clip_line("short", 0, 8), "short");
+        assert_eq!(clip_line("0123456789", 0, 6), "01234…");
+        assert_eq!(clip_line("0123456789", 2, 6), "…3456…");
+        assert_eq!(clip_line("0123456789", 4, 6), "…56789");
+        assert_eq!(clip_line("0123", 9, 6), "…");
+        assert_eq!(max_hscroll(["0123456789", "ab"], 6), 4);
+        assert_eq!(max_hscroll(["ab"], 6), 0);
+    }
 
-    Paragraph::new(text_lines).wrap(Wrap { trim: false })
+    #[test]
+    fn direction_filter_spans_all_devices() {
+        let a: SocketAddr = "127.0.0.1:16002".parse().unwrap();
+        let mut st = InspectorState::new();
+        st.push(sample(b"in"));
+        st.push(Sample::capture(DirectionTag::Outbound(a), b"out", None));
+        st.track_device(DeviceId::Client(a));
+        assert_eq!(
+            st.devices,
+            [DeviceId::All, DeviceId::Serial, DeviceId::Client(a)]
+        );
+        assert_eq!(st.visible_indices(), [0]);
+
+        st.selected = 0;
+        assert_eq!(st.visible_indices(), [0, 1]);
+        st.direction = DirectionFilter::Outbound;
+        assert_eq!(st.visible_indices(), [1]);
+        st.direction = st.direction.next();
+        assert_eq!(st.direction, DirectionFilter::Both);
+
+        let f = InspectorFilter::default();
+        f.set(true, Some(&DeviceId::All));
+        f.set_direction(DirectionFilter::Inbound);
+        assert!(f.wants(&DirectionTag::Inbound));
+        assert!(!f.wants(&DirectionTag::Outbound(a)));
+    }
+
+    #[test]
+    fn ascii_drops_line_endings() {
+        assert_eq!(dump_bytes(b"a\r\nb\x01", DumpFormat::Ascii, 64), "ab.");
+    }
+
+    #[test]
+    fn filter_follows_selection() {
+        let a: SocketAddr = "127.0.0.1:16000".parse().unwrap();
+        let b: SocketAddr = "127.0.0.1:16001".parse().unwrap();
+        let f = InspectorFilter::default();
+        // Disabled until the inspector is on screen
+        assert!(!f.wants(&DirectionTag::Inbound));
+
+        f.set(true, None);
+        assert!(f.wants(&DirectionTag::Inbound));
+        assert!(f.wants(&DirectionTag::Outbound(a)));
+
+        f.set(true, Some(&DeviceId::Client(a)));
+        assert!(!f.wants(&DirectionTag::Inbound));
+        assert!(f.wants(&DirectionTag::Outbound(a)));
+        assert!(!f.wants(&DirectionTag::Outbound(b)));
+    }
+
+    fn sample(data: &'static [u8]) -> Sample {
+        Sample::capture(DirectionTag::Inbound, data, None)
+    }
+
+    #[test]
+    fn capture_caps_copy_and_keeps_length() {
+        let s = Sample::capture(DirectionTag::Inbound, b"abcdef", Some(4));
+        assert_eq!(&s.data[..], b"abcd");
+        assert_eq!(s.len, 6);
+        assert_eq!(s.truncated(), 2);
+        let full = Sample::capture(DirectionTag::Inbound, b"abcdef", None);
+        assert_eq!(full.truncated(), 0);
+    }
+
+    #[test]
+    fn diff_positions_cover_length_mismatch() {
+        assert_eq!(diff_positions(b"abcd", b"abXd"), vec![2]);
+        assert_eq!(diff_positions(b"ab", b"abcd"), vec![2, 3]);
+        assert!(diff_positions(b"same", b"same").is_empty());
+    }
+
+    #[test]
+    fn marks_pair_and_follow_eviction() {
+        let mut st = InspectorState::new();
+        st.push(sample(b"one"));
+        st.push(sample(b"two"));
+        st.scroll = 1;
+        st.mark_current();
+        st.scroll = 0;
+        st.mark_current();
+        let (a, b) = st.marked_pair().unwrap();
+        assert_eq!(&a.data[..], b"one");
+        assert_eq!(&b.data[..], b"two");
+
+        for _ in 0..CAPTURE_MAX - 1 {
+            st.push(sample(b"x"));
+        }
+        // "one" was evicted, "two" moved to the front
+        assert_eq!(st.marks, [None, Some(0)]);
+        assert!(st.marked_pair().is_none());
+    }
+
+    #[test]
+    fn format_cycle_visits_all() {
+        let mut f = DumpFormat::Hex;
+        let mut seen = vec![f];
+        for _ in 0..4 {
+            f = f.next();
+            seen.push(f);
+        }
+        assert_eq!(f.next(), DumpFormat::Hex);
+        assert!(seen.contains(&DumpFormat::AsciiEscaped));
+        assert!(seen.contains(&DumpFormat::HexAscii));
+    }
 }
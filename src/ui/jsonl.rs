@@ -0,0 +1,113 @@
+// Newline-delimited JSON export of inspector captures, for jq and pandas. One
+// object per sample:
+//
+//   {"direction":"out","device":"127.0.0.1:51000","ts":1700000000.25,"len":3,"data":"41540d"}
+//
+// `direction` is "in" (serial -> clients) or "out" (client -> serial),
+// `device` is "serial" or the client address, `ts` is Unix seconds and `len`
+// is the size of the original read; `data` (hex) is shorter when capped.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::ui::inspector::{DirectionTag, Sample};
+
+#[derive(Serialize)]
+struct SampleRecord {
+    direction: &'static str,
+    device: String,
+    ts: f64,
+    len: usize,
+    data: String,
+}
+
+impl SampleRecord {
+    fn new(sample: &Sample) -> Self {
+        let (direction, device) = match sample.dir {
+            DirectionTag::Inbound => ("in", "serial".to_string()),
+            DirectionTag::Outbound(addr) => ("out", addr.to_string()),
+        };
+        let ts = sample
+            .at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |d| d.as_micros() as f64 / 1_000_000.0);
+        let data = sample.data.iter().map(|b| format!("{b:02x}")).collect();
+        Self {
+            direction,
+            device,
+            ts,
+            len: sample.len,
+            data,
+        }
+    }
+}
+
+pub fn write_jsonl<'a, W: Write>(
+    mut out: W,
+    samples: impl IntoIterator<Item = &'a Sample>,
+) -> io::Result<usize> {
+    let mut count = 0;
+    for sample in samples {
+        serde_json::to_writer(&mut out, &SampleRecord::new(sample))?;
+        out.write_all(b"\n")?;
+        count += 1;
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+/// Write the samples to `sergw-<unix secs>.jsonl` in the working directory.
+pub fn export_jsonl<'a>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+) -> io::Result<(PathBuf, usize)> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = PathBuf::from(format!("sergw-{secs}.jsonl"));
+    let count = write_jsonl(BufWriter::new(File::create(&path)?), samples)?;
+    Ok((path, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn one_object_per_sample() {
+        let at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_250);
+        let samples = [
+            Sample {
+                dir: DirectionTag::Outbound("127.0.0.1:51000".parse().unwrap()),
+                data: Bytes::from_static(b"AT\r"),
+                at,
+                seen: Instant::now(),
+                len: 3,
+            },
+            Sample {
+                dir: DirectionTag::Inbound,
+                data: Bytes::from_static(&[0x00, 0xff]),
+                at,
+                seen: Instant::now(),
+                len: 10,
+            },
+        ];
+        let mut out = Vec::new();
+        assert_eq!(write_jsonl(&mut out, &samples).unwrap(), 2);
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                r#"{"direction":"out","device":"127.0.0.1:51000","ts":1700000000.25,"len":3,"data":"41540d"}"#,
+                r#"{"direction":"in","device":"serial","ts":1700000000.25,"len":10,"data":"00ff"}"#,
+            ]
+        );
+    }
+}
@@ -0,0 +1,537 @@
+// Remappable TUI keys. Defaults match the built-in bindings; an optional file
+// overrides them per action:
+//
+//   [gateway]
+//   up = k, Up
+//   down = j, Down
+//   kick = K
+//   [chat]
+//   clear_input = Ctrl+u
+//
+// Binding an action replaces all of its default keys, and a key moves to the
+// last action it was bound to.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use thiserror::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    NextTab,
+    ToggleSerialReads,
+    /// Overview: scroll events up; inspector: previous device
+    Up,
+    /// Overview: scroll events down; inspector: next device
+    Down,
+    /// Jump back to the newest events/samples
+    Top,
+    PageUp,
+    PageDown,
+    /// Overview: previous connection; inspector: scroll clipped lines left
+    PrevConnection,
+    /// Overview: next connection; inspector: scroll clipped lines right
+    NextConnection,
+    Kick,
+    /// Dismiss the last serial error shown in the header
+    ClearError,
+    /// Throughput smoothing window: 1s, 5s, 15s
+    CycleSmoothing,
+    /// Overview: open the palette that sends a line (text or 0x hex) to the device
+    Inject,
+    Clear,
+    CycleFormat,
+    /// Inspector: both directions, serial→tcp only, tcp→serial only
+    CycleDirection,
+    /// Inspector: wrapped paragraph vs one row per sample
+    ToggleView,
+    /// Inspector and chat messages: wrap long lines or clip them to the pane
+    ToggleWrap,
+    /// Inspector: left gutter with each sample's sequence number and length
+    ToggleGutter,
+    TogglePause,
+    Mark,
+    Unmark,
+    /// Inspector: show sample times relative to now (again to re-zero)
+    ZeroTime,
+    Copy,
+    SavePcap,
+    /// Inspector: write the capture as newline-delimited JSON
+    SaveJsonl,
+    Send,
+    ClearInput,
+    /// Chat: the next key is sent to the device as a control byte (Ctrl+a, c → 0x03)
+    ControlPrefix,
+}
+
+const ACTION_NAMES: [(Action, &str); 30] = [
+    (Action::Quit, "quit"),
+    (Action::NextTab, "next_tab"),
+    (Action::ToggleSerialReads, "toggle_serial_reads"),
+    (Action::Up, "up"),
+    (Action::Down, "down"),
+    (Action::Top, "top"),
+    (Action::PageUp, "page_up"),
+    (Action::PageDown, "page_down"),
+    (Action::PrevConnection, "prev_connection"),
+    (Action::NextConnection, "next_connection"),
+    (Action::Kick, "kick"),
+    (Action::ClearError, "clear_error"),
+    (Action::CycleSmoothing, "cycle_smoothing"),
+    (Action::Inject, "inject"),
+    (Action::Clear, "clear"),
+    (Action::CycleFormat, "cycle_format"),
+    (Action::CycleDirection, "cycle_direction"),
+    (Action::ToggleView, "toggle_view"),
+    (Action::ToggleWrap, "toggle_wrap"),
+    (Action::ToggleGutter, "toggle_gutter"),
+    (Action::TogglePause, "toggle_pause"),
+    (Action::Mark, "mark"),
+    (Action::Unmark, "unmark"),
+    (Action::ZeroTime, "zero_time"),
+    (Action::Copy, "copy"),
+    (Action::SavePcap, "save_pcap"),
+    (Action::SaveJsonl, "save_jsonl"),
+    (Action::Send, "send"),
+    (Action::ClearInput, "clear_input"),
+    (Action::ControlPrefix, "control_prefix"),
+];
+
+impl Action {
+    fn from_name(name: &str) -> Option<Self> {
+        ACTION_NAMES
+            .iter()
+            .find(|(_, n)| *n == name)
+            .map(|(a, _)| *a)
+    }
+}
+
+/// A key plus the modifiers that matter for matching (Shift is implied by the character)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeySpec {
+    code: KeyCode,
+    mods: KeyModifiers,
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 16] = [
+    ("tab", KeyCode::Tab),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("backspace", KeyCode::Backspace),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("pgup", KeyCode::PageUp),
+    ("pgdn", KeyCode::PageDown),
+    ("space", KeyCode::Char(' ')),
+    ("comma", KeyCode::Char(',')),
+];
+
+impl KeySpec {
+    const fn new(code: KeyCode, mods: KeyModifiers) -> Self {
+        Self { code, mods }
+    }
+
+    const fn plain(code: KeyCode) -> Self {
+        Self::new(code, KeyModifiers::NONE)
+    }
+
+    fn from_event(ev: &KeyEvent) -> Self {
+        Self::new(
+            ev.code,
+            ev.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT),
+        )
+    }
+
+    /// `q`, `K`, `Ctrl+c`, `Alt+x`, `PageUp`, `F5`, ... (names are case-insensitive)
+    fn parse(text: &str) -> Option<Self> {
+        let mut mods = KeyModifiers::NONE;
+        let mut rest = text;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            if lower.starts_with("ctrl+") && rest.len() > 5 {
+                mods |= KeyModifiers::CONTROL;
+                rest = &rest[5..];
+            } else if lower.starts_with("alt+") && rest.len() > 4 {
+                mods |= KeyModifiers::ALT;
+                rest = &rest[4..];
+            } else {
+                break;
+            }
+        }
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => {
+                let lower = rest.to_ascii_lowercase();
+                match NAMED_KEYS.iter().find(|(n, _)| *n == lower) {
+                    Some((_, code)) => *code,
+                    None => KeyCode::F(lower.strip_prefix('f')?.parse().ok()?),
+                }
+            }
+        };
+        Some(Self::new(code, mods))
+    }
+}
+
+impl fmt::Display for KeySpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.mods.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.mods.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+/// Ordered key → action bindings; the first key bound to an action is the one
+/// shown in footers.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: Vec<(KeySpec, Action)>,
+}
+
+impl Keymap {
+    pub fn gateway_defaults() -> Self {
+        use Action::*;
+        let ch = |c| KeySpec::plain(KeyCode::Char(c));
+        Self {
+            bindings: vec![
+                (ch('q'), Quit),
+                (
+                    KeySpec::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+                    Quit,
+                ),
+                (KeySpec::plain(KeyCode::Tab), NextTab),
+                (ch('r'), ToggleSerialReads),
+                (KeySpec::plain(KeyCode::Up), Up),
+                (KeySpec::plain(KeyCode::Down), Down),
+                (KeySpec::plain(KeyCode::Home), Top),
+                (KeySpec::plain(KeyCode::PageUp), PageUp),
+                (KeySpec::plain(KeyCode::PageDown), PageDown),
+                (KeySpec::plain(KeyCode::Left), PrevConnection),
+                (KeySpec::plain(KeyCode::Right), NextConnection),
+                (ch('k'), Kick),
+                (ch('e'), ClearError),
+                (ch('s'), CycleSmoothing),
+                (ch('i'), Inject),
+                (ch('c'), Clear),
+                (ch('t'), CycleFormat),
+                (ch('d'), CycleDirection),
+                (ch('v'), ToggleView),
+                (ch('n'), ToggleWrap),
+                (ch('g'), ToggleGutter),
+                (ch('p'), TogglePause),
+                (ch('m'), Mark),
+                (ch('x'), Unmark),
+                (ch('0'), ZeroTime),
+                (ch('y'), Copy),
+                (ch('w'), SavePcap),
+                (ch('o'), SaveJsonl),
+            ],
+        }
+    }
+
+    /// Chat keys stay off plain characters so typing is never intercepted
+    pub fn chat_defaults() -> Self {
+        Self {
+            bindings: vec![
+                (
+                    KeySpec::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+                    Action::Quit,
+                ),
+                (KeySpec::plain(KeyCode::Enter), Action::Send),
+                (KeySpec::plain(KeyCode::Esc), Action::ClearInput),
+                (
+                    KeySpec::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+                    Action::ToggleWrap,
+                ),
+                (
+                    KeySpec::new(KeyCode::Char('t'), KeyModifiers::CONTROL),
+                    Action::CycleSmoothing,
+                ),
+                (
+                    KeySpec::new(KeyCode::Char('a'), KeyModifiers::CONTROL),
+                    Action::ControlPrefix,
+                ),
+            ],
+        }
+    }
+
+    pub fn action(&self, ev: &KeyEvent) -> Option<Action> {
+        let key = KeySpec::from_event(ev);
+        self.bindings
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, a)| *a)
+    }
+
+    /// Footer label for an action: its first key, or `-` when unbound
+    pub fn label(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map_or_else(|| "-".to_string(), |(k, _)| k.to_string())
+    }
+
+    fn rebind(&mut self, action: Action, keys: Vec<KeySpec>) {
+        self.bindings
+            .retain(|(k, a)| *a != action && !keys.contains(k));
+        self.bindings.extend(keys.into_iter().map(|k| (k, action)));
+    }
+}
+
+/// The control byte for the key pressed after the chat's control prefix:
+/// `c` or Ctrl+c → 0x03, `@` → 0x00, `[` → ESC, `?` → DEL. Other keys give `None`.
+pub fn control_byte(ev: &KeyEvent) -> Option<u8> {
+    let KeyCode::Char(c) = ev.code else {
+        return None;
+    };
+    match c.to_ascii_uppercase() {
+        '?' => Some(0x7f),
+        c @ '@'..='_' => Some(c as u8 & 0x1f),
+        _ => None,
+    }
+}
+
+/// `^C` for 0x03, `^?` for DEL
+pub fn caret_notation(byte: u8) -> String {
+    format!("^{}", (byte ^ 0x40) as char)
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum KeymapError {
+    #[error("line {line}: expected `action = key[, key...]` or a [gateway]/[chat] section")]
+    Syntax { line: usize },
+    #[error("line {line}: unknown section [{name}] (expected gateway or chat)")]
+    UnknownSection { line: usize, name: String },
+    #[error("line {line}: unknown action '{name}'")]
+    UnknownAction { line: usize, name: String },
+    #[error("line {line}: unknown key '{key}'")]
+    UnknownKey { line: usize, key: String },
+}
+
+/// Bindings for the gateway TUI and the chat client
+#[derive(Clone, Debug)]
+pub struct Keymaps {
+    pub gateway: Keymap,
+    pub chat: Keymap,
+}
+
+impl Default for Keymaps {
+    fn default() -> Self {
+        Self {
+            gateway: Keymap::gateway_defaults(),
+            chat: Keymap::chat_defaults(),
+        }
+    }
+}
+
+impl Keymaps {
+    /// `path` must exist when given; otherwise the default location is used if present
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(p) => p.to_path_buf(),
+            None => match default_path().filter(|p| p.exists()) {
+                Some(p) => p,
+                None => return Ok(Self::default()),
+            },
+        };
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Reading keymap {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("Parsing keymap {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, KeymapError> {
+        let mut maps = Self::default();
+        let mut chat = false;
+        for (i, raw) in text.lines().enumerate() {
+            let line = i + 1;
+            let entry = raw.split('#').next().unwrap_or("").trim();
+            if entry.is_empty() {
+                continue;
+            }
+            if let Some(name) = entry.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+                chat = match name.trim() {
+                    "gateway" => false,
+                    "chat" => true,
+                    other => {
+                        return Err(KeymapError::UnknownSection {
+                            line,
+                            name: other.to_string(),
+                        })
+                    }
+                };
+                continue;
+            }
+            let (name, keys) = entry.split_once('=').ok_or(KeymapError::Syntax { line })?;
+            let name = name.trim();
+            let action = Action::from_name(name).ok_or_else(|| KeymapError::UnknownAction {
+                line,
+                name: name.to_string(),
+            })?;
+            let keys = keys
+                .split(',')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(|k| {
+                    KeySpec::parse(k).ok_or_else(|| KeymapError::UnknownKey {
+                        line,
+                        key: k.to_string(),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let map = if chat {
+                &mut maps.chat
+            } else {
+                &mut maps.gateway
+            };
+            map.rebind(action, keys);
+        }
+        Ok(maps)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/sergw/keys.conf`, falling back to `~/.config/sergw/keys.conf`
+fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("sergw").join("keys.conf"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, mods: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, mods)
+    }
+
+    #[test]
+    fn defaults_match_builtin_keys() {
+        let m = Keymaps::default();
+        let g = &m.gateway;
+        assert_eq!(
+            g.action(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            g.action(&key(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+            Some(Action::Quit)
+        );
+        assert_eq!(
+            g.action(&key(KeyCode::Char('c'), KeyModifiers::NONE)),
+            Some(Action::Clear)
+        );
+        assert_eq!(g.action(&key(KeyCode::Char('z'), KeyModifiers::NONE)), None);
+        assert_eq!(g.label(Action::Up), "↑");
+        assert_eq!(
+            m.chat.action(&key(KeyCode::Char('q'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(m.chat.label(Action::Quit), "Ctrl+c");
+        assert_eq!(m.chat.label(Action::ControlPrefix), "Ctrl+a");
+    }
+
+    #[test]
+    fn control_bytes_after_the_prefix() {
+        let byte = |c, mods| control_byte(&key(KeyCode::Char(c), mods));
+        assert_eq!(byte('c', KeyModifiers::NONE), Some(0x03));
+        assert_eq!(byte('C', KeyModifiers::NONE), Some(0x03));
+        assert_eq!(byte('a', KeyModifiers::CONTROL), Some(0x01));
+        assert_eq!(byte('@', KeyModifiers::NONE), Some(0x00));
+        assert_eq!(byte('[', KeyModifiers::NONE), Some(0x1b));
+        assert_eq!(byte('?', KeyModifiers::NONE), Some(0x7f));
+        assert_eq!(byte('1', KeyModifiers::NONE), None);
+        assert_eq!(control_byte(&key(KeyCode::Esc, KeyModifiers::NONE)), None);
+        assert_eq!(caret_notation(0x03), "^C");
+        assert_eq!(caret_notation(0x7f), "^?");
+    }
+
+    #[test]
+    fn vim_style_overrides() {
+        let m = Keymaps::parse(
+            "# vim-ish\n[gateway]\nup = k, Up\ndown = j, Down  # comment\nkick = K\n[chat]\nclear_input = Ctrl+u\n",
+        )
+        .unwrap();
+        let g = &m.gateway;
+        assert_eq!(
+            g.action(&key(KeyCode::Char('k'), KeyModifiers::NONE)),
+            Some(Action::Up)
+        );
+        // Shift arrives as a modifier on the uppercase char; it is not part of the match
+        assert_eq!(
+            g.action(&key(KeyCode::Char('K'), KeyModifiers::SHIFT)),
+            Some(Action::Kick)
+        );
+        assert_eq!(g.label(Action::Down), "j");
+        assert_eq!(
+            m.chat
+                .action(&key(KeyCode::Char('u'), KeyModifiers::CONTROL)),
+            Some(Action::ClearInput)
+        );
+        assert_eq!(m.chat.action(&key(KeyCode::Esc, KeyModifiers::NONE)), None);
+    }
+
+    #[test]
+    fn unbinding_and_errors() {
+        let m = Keymaps::parse("save_pcap =\n").unwrap();
+        assert_eq!(m.gateway.label(Action::SavePcap), "-");
+        assert_eq!(
+            Keymaps::parse("\n\nfly = f").unwrap_err(),
+            KeymapError::UnknownAction {
+                line: 3,
+                name: "fly".into()
+            }
+        );
+        assert_eq!(
+            Keymaps::parse("quit = Hyper+q").unwrap_err(),
+            KeymapError::UnknownKey {
+                line: 1,
+                key: "Hyper+q".into()
+            }
+        );
+        assert!(matches!(
+            Keymaps::parse("[mock]").unwrap_err(),
+            KeymapError::UnknownSection { .. }
+        ));
+        assert_eq!(
+            Keymaps::parse("quit").unwrap_err(),
+            KeymapError::Syntax { line: 1 }
+        );
+    }
+
+    #[test]
+    fn key_names_round_trip() {
+        for text in ["q", "Ctrl+c", "Alt+x", "PgUp", "F5", "Space", "Tab"] {
+            let spec = KeySpec::parse(text).unwrap();
+            assert_eq!(KeySpec::parse(&spec.to_string()), Some(spec), "{text}");
+        }
+        assert_eq!(
+            KeySpec::parse("pagedown"),
+            Some(KeySpec::plain(KeyCode::PageDown))
+        );
+    }
+}
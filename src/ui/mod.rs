@@ -1,2 +1,11 @@
+pub mod clipboard;
+pub mod demo;
+pub mod inject;
+pub mod input;
 pub mod inspector;
+pub mod jsonl;
+pub mod keymap;
 pub mod overview;
+#[cfg(feature = "pcap")]
+pub mod pcap;
+pub mod terminal;
@@ -1,31 +1,284 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::net::SocketAddr;
 use std::sync::{
-    atomic::{AtomicBool, AtomicU64, Ordering},
-    Arc,
+    atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    Arc, Mutex,
 };
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
-use crossbeam_channel::Receiver;
+use bytes::Bytes;
+use crossbeam_channel::{Receiver, Sender};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{self, Event, KeyCode},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    style::Print,
+    terminal::{disable_raw_mode, LeaveAlternateScreen, SetTitle},
 };
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs},
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, List, ListItem, Paragraph, Sparkline, Tabs},
     Terminal,
 };
 
-use crate::metrics::ThroughputAverager;
+use crate::cli::ThroughputPerspective;
+use crate::metrics::{next_smoothing, ThroughputAverager, DEFAULT_SMOOTHING};
+use crate::serial::lineerr::LineErrors;
 use crate::state::SharedState;
-use crate::ui::inspector::{DeviceId, DumpFormat, InspectorState};
+use crate::ui::inject::parse_injection;
+use crate::ui::inspector::{
+    max_hscroll, DeviceId, InspectorFilter, InspectorState, InspectorView, HSCROLL_STEP,
+};
+use crate::ui::keymap::{Action, Keymap};
 
 #[derive(Default)]
 pub struct Counters {
     pub bytes_in: AtomicU64,
     pub bytes_out: AtomicU64,
+    pub serial_connected: AtomicBool,
+    pub modem: ModemLines,
+    // break/framing/parity/overrun counts since the port was first polled;
+    // None while the driver keeps no counts (pty, many USB adapters)
+    pub line_errors: Mutex<Option<LineErrors>>,
+    // client frames discarded by --serial-write-policy latest
+    pub serial_writes_dropped: AtomicU64,
+    // bytes the serial writer sent for --poll-bytes (not counted in bytes_in)
+    pub poll_bytes: AtomicU64,
+    // set from the TUI: the serial reader stops calling read() so the device backs up
+    pub serial_read_paused: AtomicBool,
+    // samples that found the inspector channel full (--inspector-buffer)
+    pub inspector_dropped: AtomicU64,
+    // line rate the device is (re)opened at; starts as --baud
+    pub baud: AtomicU32,
+    // rate asked for by --stdin-commands, applied by the serial reader (0: none)
+    pub baud_request: AtomicU32,
+    // session totals for the shutdown summary
+    pub peak_clients: AtomicU64,
+    pub serial_reconnects: AtomicU64,
+    pub last_error: LastError,
+}
+
+/// The most recent serial read/write error, shown in the TUI header until a
+/// read or write succeeds or it is dismissed
+#[derive(Default)]
+pub struct LastError {
+    // lets the I/O threads skip the lock when there is nothing to clear
+    set: AtomicBool,
+    error: Mutex<Option<(String, SystemTime)>>,
+}
+
+impl LastError {
+    pub fn record(&self, error: impl fmt::Display) {
+        let mut slot = self.error.lock().unwrap_or_else(|e| e.into_inner());
+        *slot = Some((error.to_string(), SystemTime::now()));
+        self.set.store(true, Ordering::Relaxed);
+    }
+
+    pub fn clear(&self) {
+        if self.set.swap(false, Ordering::Relaxed) {
+            *self.error.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        }
+    }
+
+    /// "read: Broken pipe @ 12:03:45 UTC"
+    pub fn summary(&self) -> Option<String> {
+        if !self.set.load(Ordering::Relaxed) {
+            return None;
+        }
+        let slot = self.error.lock().unwrap_or_else(|e| e.into_inner());
+        let (error, at) = slot.as_ref()?;
+        Some(format!("{error} @ {}", utc_clock(*at)))
+    }
+}
+
+/// Time of day in UTC, `HH:MM:SS`
+fn utc_clock(at: SystemTime) -> String {
+    let secs = at.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()) % 86_400;
+    format!(
+        "{:02}:{:02}:{:02} UTC",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Last polled modem status lines; `valid` is false when the port can't report them
+#[derive(Default)]
+pub struct ModemLines {
+    pub valid: AtomicBool,
+    pub cts: AtomicBool,
+    pub dsr: AtomicBool,
+    pub cd: AtomicBool,
+    pub ri: AtomicBool,
+}
+
+impl ModemLines {
+    pub fn poll(&self, port: &mut dyn serialport::SerialPort) {
+        let read = (|| -> serialport::Result<[bool; 4]> {
+            Ok([
+                port.read_clear_to_send()?,
+                port.read_data_set_ready()?,
+                port.read_carrier_detect()?,
+                port.read_ring_indicator()?,
+            ])
+        })();
+        match read {
+            Ok([cts, dsr, cd, ri]) => {
+                self.cts.store(cts, Ordering::Relaxed);
+                self.dsr.store(dsr, Ordering::Relaxed);
+                self.cd.store(cd, Ordering::Relaxed);
+                self.ri.store(ri, Ordering::Relaxed);
+                self.valid.store(true, Ordering::Relaxed);
+            }
+            Err(_) => self.valid.store(false, Ordering::Relaxed),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        if !self.valid.load(Ordering::Relaxed) {
+            return "n/a (port does not report modem lines)".to_string();
+        }
+        let flag = |b: &AtomicBool| {
+            if b.load(Ordering::Relaxed) {
+                "on"
+            } else {
+                "off"
+            }
+        };
+        format!(
+            "CTS: {}  DSR: {}  CD: {}  RI: {}",
+            flag(&self.cts),
+            flag(&self.dsr),
+            flag(&self.cd),
+            flag(&self.ri)
+        )
+    }
+}
+
+/// Presentation settings for `run_tui` taken from the command line
+pub struct TuiOptions {
+    pub keymap: Keymap,
+    pub perspective: ThroughputPerspective,
+    /// The serial writer's queue, for lines sent from the injection palette
+    pub inject: Sender<Bytes>,
+    /// Device name for the terminal title (`--terminal-title`)
+    pub title: Option<String>,
+    pub device: DeviceLabel,
+    /// `--inspector-hex-columns`
+    pub hex_columns: Option<usize>,
+    /// Addresses the TCP listeners were bound to, with any port 0 resolved
+    pub listening: Vec<SocketAddr>,
+}
+
+/// What sergw attached to, for the line above the overview
+#[derive(Clone, Debug)]
+pub struct DeviceLabel {
+    /// Serial path or upstream URL
+    pub name: String,
+    /// False for a plain TCP upstream, which has no line rate
+    pub has_baud: bool,
+    /// USB VID:PID and product, when the port is a USB adapter
+    pub adapter: Option<String>,
+}
+
+impl DeviceLabel {
+    /// "Device: /dev/ttyUSB0 @ 115200 baud | USB 10c4:ea60 CP2102"; the baud is
+    /// passed in because --stdin-commands can change it
+    pub fn line(&self, baud: u32) -> String {
+        let mut line = format!("Device: {}", self.name);
+        if self.has_baud {
+            line.push_str(&format!(" @ {baud} baud"));
+        }
+        if let Some(adapter) = &self.adapter {
+            line.push_str(&format!(" | {adapter}"));
+        }
+        line
+    }
+}
+
+/// "Listening: 127.0.0.1:41234, [::1]:41234", after the device line
+fn listening_line(addrs: &[SocketAddr]) -> String {
+    let addrs: Vec<String> = addrs.iter().map(SocketAddr::to_string).collect();
+    format!("Listening: {}", addrs.join(", "))
+}
+
+/// Bytes per second with a decimal (1000-based) unit
+fn fmt_rate(bps: u64) -> String {
+    match bps {
+        0..=999 => format!("{bps} B/s"),
+        1_000..=999_999 => format!("{:.1} KB/s", bps as f64 / 1e3),
+        _ => format!("{:.1} MB/s", bps as f64 / 1e6),
+    }
+}
+
+/// Throughput panel lines; "inbound" is whatever flows into the side named by `perspective`
+fn throughput_lines(perspective: ThroughputPerspective, to_tcp: u64, to_serial: u64) -> String {
+    let to_tcp = format!("serial→tcp: {}", fmt_rate(to_tcp));
+    let to_serial = format!("tcp→serial: {}", fmt_rate(to_serial));
+    let (inbound, outbound) = match perspective {
+        ThroughputPerspective::Tcp => (to_tcp, to_serial),
+        ThroughputPerspective::Serial => (to_serial, to_tcp),
+    };
+    format!("Inbound  {inbound}\nOutbound {outbound}")
+}
+
+// xterm "save/restore window title" (CSI 22;0 t / CSI 23;0 t); ignored by
+// terminals without a title stack
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+// Throughput samples kept for the overview sparklines (one per UI tick)
+const HISTORY_LEN: usize = 60;
+
+// Widest rate row ("Outbound tcp→serial: 999.9 KB/s") plus a gap
+const RATE_TEXT_WIDTH: u16 = 34;
+
+fn push_history(history: &mut VecDeque<u64>, rate: u64) {
+    history.push_back(rate);
+    if history.len() > HISTORY_LEN {
+        history.pop_front();
+    }
+}
+
+/// The newest samples that fit in `width` cells, oldest first
+fn history_tail(history: &VecDeque<u64>, width: u16) -> Vec<u64> {
+    let skip = history.len().saturating_sub(width as usize);
+    history.iter().skip(skip).copied().collect()
+}
+
+/// Terminal title for `--terminal-title`, e.g. "sergw ttyUSB0 · 3 clients"
+fn terminal_title(device: &str, clients: usize, connected: bool) -> String {
+    let plural = if clients == 1 { "" } else { "s" };
+    let mut title = format!("sergw {device} · {clients} client{plural}");
+    if !connected {
+        title.push_str(" · reconnecting");
+    }
+    title
+}
+
+const ATTEMPT_MARK: &str = " (attempt ";
+
+/// Append an event, folding repeated "<stem> (attempt N)…" progress messages
+/// into the existing rolling line for the same stem.
+fn push_event(logs: &mut Vec<String>, ev: String) {
+    if let Some(pos) = ev.find(ATTEMPT_MARK) {
+        let stem = &ev[..pos];
+        // only look through the trailing run of progress lines
+        let rolling = logs
+            .iter()
+            .rev()
+            .take_while(|l| l.contains(ATTEMPT_MARK))
+            .position(|l| l.starts_with(stem) && l[stem.len()..].starts_with(ATTEMPT_MARK));
+        if let Some(back) = rolling {
+            let idx = logs.len() - 1 - back;
+            logs[idx] = ev;
+            return;
+        }
+    }
+    logs.push(ev);
 }
 
 pub fn run_tui(
@@ -33,33 +286,121 @@ pub fn run_tui(
     counters: Arc<Counters>,
     events: Receiver<String>,
     insp_rx: Receiver<crate::ui::inspector::Sample>,
+    insp_filter: Arc<InspectorFilter>,
     stop: Arc<AtomicBool>,
+    options: TuiOptions,
 ) -> Result<()> {
-    enable_raw_mode()?;
+    let TuiOptions {
+        keymap,
+        perspective,
+        inject,
+        title,
+        device,
+        hex_columns,
+        listening,
+    } = options;
+    let listening = listening_line(&listening);
+    crate::ui::terminal::enter(false)?;
     let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    if title.is_some() {
+        // xterm title stack: keep the current title so exit can put it back
+        execute!(stdout, Print(PUSH_TITLE))?;
+    }
+    let mut shown_title = String::new();
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     let mut logs: Vec<String> = Vec::new();
     let mut log_scroll: usize = 0;
+    let mut conn_selected: usize = 0;
+    // text being typed into the injection palette while it is open
+    let mut palette: Option<String> = None;
     let mut active_tab: usize = 0; // 0: Overview, 1: Inspector
     let mut _prev_tab: usize = active_tab;
     let mut insp = InspectorState::new();
+    insp.hex_columns = hex_columns;
+    let mut insp_rows: usize = 0;
+    let mut insp_cols: usize = 0;
+    let mut clipboard = crate::ui::clipboard::Clipboard::default();
     let mut last_in = 0u64;
     let mut last_out = 0u64;
-    let mut avg_in = ThroughputAverager::new(5.0);
-    let mut avg_out = ThroughputAverager::new(5.0);
+    let mut avg_in = ThroughputAverager::new(DEFAULT_SMOOTHING);
+    let mut avg_out = ThroughputAverager::new(DEFAULT_SMOOTHING);
     let mut last_time = Instant::now();
+    let mut to_tcp_history: VecDeque<u64> = VecDeque::with_capacity(HISTORY_LEN + 1);
+    let mut to_serial_history: VecDeque<u64> = VecDeque::with_capacity(HISTORY_LEN + 1);
+    let key = |a| keymap.label(a);
+    let overview_footer = format!(
+        "{}: inspector | {}: quit | {}/{}/{}: scroll events | {}/{}: select connection | {}: kick | {}: inject | {}: smoothing | {}: pause/resume serial reads | {}: clear events",
+        key(Action::NextTab),
+        key(Action::Quit),
+        key(Action::Up),
+        key(Action::Down),
+        key(Action::Top),
+        key(Action::PrevConnection),
+        key(Action::NextConnection),
+        key(Action::Kick),
+        key(Action::Inject),
+        key(Action::CycleSmoothing),
+        key(Action::ToggleSerialReads),
+        key(Action::Clear),
+    );
+    let export_hint = if cfg!(feature = "pcap") {
+        format!(
+            " | {}: save pcap | {}: save jsonl",
+            key(Action::SavePcap),
+            key(Action::SaveJsonl)
+        )
+    } else {
+        format!(" | {}: save jsonl", key(Action::SaveJsonl))
+    };
+    let inspector_footer = format!(
+        "{}: overview | {}: quit | {}: toggle type | {}: direction | {}: list/wrap | {}: wrap lines | {}: seq/length gutter | {}/{}: scroll clipped lines | {}: pause/resume | {}/{}: select device | {}/{}/{}: scroll | {}: mark | {}: unmark | {}: zero time | {}: copy view | {}: pause/resume serial reads | {}: clear{export_hint}",
+        key(Action::NextTab),
+        key(Action::Quit),
+        key(Action::CycleFormat),
+        key(Action::CycleDirection),
+        key(Action::ToggleView),
+        key(Action::ToggleWrap),
+        key(Action::ToggleGutter),
+        key(Action::PrevConnection),
+        key(Action::NextConnection),
+        key(Action::TogglePause),
+        key(Action::Up),
+        key(Action::Down),
+        key(Action::PageUp),
+        key(Action::PageDown),
+        key(Action::Top),
+        key(Action::Mark),
+        key(Action::Unmark),
+        key(Action::ZeroTime),
+        key(Action::Copy),
+        key(Action::ToggleSerialReads),
+        key(Action::Clear),
+    );
 
     while !stop.load(Ordering::Relaxed) {
         while let Ok(ev) = events.try_recv() {
-            logs.push(ev);
+            push_event(&mut logs, ev);
             if logs.len() > 100 {
                 logs.remove(0);
             }
         }
 
+        conn_selected = conn_selected.min(shared.tcp_connections.len().saturating_sub(1));
+
+        if let Some(device) = &title {
+            let current = terminal_title(
+                device,
+                shared.tcp_connections.len(),
+                counters.serial_connected.load(Ordering::Relaxed),
+            );
+            if current != shown_title {
+                execute!(terminal.backend_mut(), SetTitle(&current))?;
+                shown_title = current;
+            }
+        }
+
         let now = Instant::now();
         let dt = now.duration_since(last_time).as_secs_f64().max(0.001);
         let bi = counters.bytes_in.load(Ordering::Relaxed);
@@ -69,194 +410,469 @@ pub fn run_tui(
         last_in = bi;
         last_out = bo;
         last_time = now;
+        push_history(&mut to_tcp_history, tout);
+        push_history(&mut to_serial_history, tin);
+
+        if active_tab == 1 {
+            // Producers only sample the selected device, so discover clients from live connections
+            for e in shared.tcp_connections.iter() {
+                insp.track_device(DeviceId::Client(*e.key()));
+            }
+        }
+        insp_filter.set(
+            active_tab == 1 && !insp.paused,
+            insp.devices.get(insp.selected),
+        );
+        insp_filter.set_direction(insp.direction);
 
         // Pull inspector samples; skip if paused
         while let Ok(s) = insp_rx.try_recv() {
             if !insp.paused {
-                // Track devices
-                match s.dir {
-                    crate::ui::inspector::DirectionTag::Inbound => {
-                        if !insp.devices.iter().any(|d| matches!(d, DeviceId::Serial)) {
-                            insp.devices.insert(0, DeviceId::Serial);
-                        }
-                    }
-                    crate::ui::inspector::DirectionTag::Outbound(addr) => {
-                        if !insp
-                            .devices
-                            .iter()
-                            .any(|d| matches!(d, DeviceId::Client(a) if *a == addr))
-                        {
-                            insp.devices.push(DeviceId::Client(addr));
-                        }
-                    }
-                }
-                insp.capture.push_back(s);
-                if insp.capture.len() > 4096 {
-                    insp.capture.pop_front();
-                }
+                insp.track_device(s.dir.device());
+                insp.push(s);
             }
         }
 
+        let read_paused = counters.serial_read_paused.load(Ordering::Relaxed);
         terminal.draw(|f| {
             // Top-level: header tabs, main, footer
             let outer = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Length(1),  // Tabs header
-                    Constraint::Min(0),     // Main
-                    Constraint::Length(1),  // Footer
-                ].as_ref())
+                .constraints(
+                    [
+                        Constraint::Length(1), // Tabs header
+                        Constraint::Min(0),    // Main
+                        Constraint::Length(1), // Footer
+                    ]
+                    .as_ref(),
+                )
                 .split(f.size());
 
-            // Tabs header
+            // Tabs header, then the last serial error and a read-pause banner when active
+            let header = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(
+                    [
+                        Constraint::Length(24),
+                        Constraint::Min(0),
+                        Constraint::Length(20),
+                    ]
+                    .as_ref(),
+                )
+                .split(outer[0]);
             let titles = ["Overview", "Inspector"].iter().map(|t| (*t).to_string());
             let tabs = Tabs::new(titles).select(active_tab);
-            f.render_widget(tabs, outer[0]);
+            f.render_widget(tabs, header[0]);
+            if let Some(error) = counters.last_error.summary() {
+                let text = format!(
+                    "last serial error: {error} ({} to dismiss)",
+                    key(Action::ClearError)
+                );
+                let line = Paragraph::new(text).style(Style::default().fg(Color::Red));
+                f.render_widget(line, header[1]);
+            }
+            if read_paused {
+                let banner = Paragraph::new(" SERIAL READ PAUSED ").style(
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                );
+                f.render_widget(banner, header[2]);
+            }
 
             if active_tab == 0 {
-                // Overview: connections, throughput, events
-                let main = outer[1];
+                // Overview: the device line, then connections, throughput, events
+                let rows = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref())
+                    .split(outer[1]);
+                let baud = counters.baud.load(Ordering::Relaxed);
+                let device_text = format!("{} | {listening}", device.line(baud));
+                f.render_widget(Paragraph::new(device_text), rows[0]);
+                let main = rows[1];
+                let mut throughput_text = throughput_lines(perspective, tout, tin);
+                if let Some(cap) = shared.tcp_rate() {
+                    throughput_text.push_str(&format!(
+                        "\nTCP cap: {cap} B/s | dropped: {} frames",
+                        shared.dropped_frames()
+                    ));
+                }
+                let tap_dropped = shared.tap_dropped();
+                if tap_dropped > 0 {
+                    throughput_text.push_str(&format!("\nTap dropped: {tap_dropped} frames"));
+                }
+                let polled = counters.poll_bytes.load(Ordering::Relaxed);
+                if polled > 0 {
+                    throughput_text.push_str(&format!("\nPolls sent: {polled} B"));
+                }
+                let superseded = counters.serial_writes_dropped.load(Ordering::Relaxed);
+                if superseded > 0 {
+                    throughput_text
+                        .push_str(&format!("\nSerial writes superseded: {superseded} frames"));
+                }
                 let sub = Layout::default()
                     .direction(Direction::Vertical)
-                    .constraints([
-                        Constraint::Length(5), // Connections
-                        Constraint::Length(4), // Throughput
-                        Constraint::Min(0),    // Events
-                    ].as_ref())
+                    .constraints(
+                        [
+                            Constraint::Length(5),                                          // Connections
+                            Constraint::Length(throughput_text.lines().count() as u16 + 2), // Throughput
+                            Constraint::Length(3), // Modem lines
+                            Constraint::Min(0),    // Events
+                        ]
+                        .as_ref(),
+                    )
                     .split(main);
 
                 let items: Vec<ListItem> = shared
-                    .tcp_connections
+                    .sorted_addrs()
                     .iter()
-                    .map(|e| ListItem::new(e.key().to_string()))
+                    .enumerate()
+                    .map(|(i, a)| {
+                        let prefix = if i == conn_selected { "> " } else { "  " };
+                        ListItem::new(format!("{prefix}{}", shared.display_name(a)))
+                    })
                     .collect();
-                let list = List::new(items).block(Block::default().title("Connections").borders(Borders::ALL));
+                let list = List::new(items)
+                    .block(Block::default().title("Connections").borders(Borders::ALL));
                 f.render_widget(list, sub[0]);
 
-                let throughput = Paragraph::new(format!("Inbound: {tout} B/s\nOutbound: {tin} B/s"))
-                    .block(Block::default().title("Throughput").borders(Borders::ALL));
-                f.render_widget(throughput, sub[1]);
+                // The whole text full width, with a trend line beside each of the two rate rows
+                let block = Block::default()
+                    .title(format!("Throughput ({}s avg)", avg_in.tau_secs()))
+                    .borders(Borders::ALL);
+                let inner = block.inner(sub[1]);
+                f.render_widget(block, sub[1]);
+                f.render_widget(Paragraph::new(throughput_text), inner);
+                // Fixed offset so the graphs don't shift as the numbers change width
+                let cols = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Length(RATE_TEXT_WIDTH), Constraint::Min(0)].as_ref())
+                    .split(inner);
+                let (inbound, outbound) = match perspective {
+                    ThroughputPerspective::Tcp => (&to_tcp_history, &to_serial_history),
+                    ThroughputPerspective::Serial => (&to_serial_history, &to_tcp_history),
+                };
+                for (row, (history, color)) in [(inbound, Color::Green), (outbound, Color::Cyan)]
+                    .into_iter()
+                    .enumerate()
+                {
+                    let mut area = cols[1];
+                    if area.height <= row as u16 {
+                        break;
+                    }
+                    area.y += row as u16;
+                    area.height = 1;
+                    let data = history_tail(history, area.width);
+                    let spark = Sparkline::default()
+                        .data(&data)
+                        .style(Style::default().fg(color));
+                    f.render_widget(spark, area);
+                }
+
+                let mut modem_text = counters.modem.summary();
+                if let Some(errors) = *counters
+                    .line_errors
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                {
+                    modem_text.push_str(&format!("  |  Errors: {}", errors.summary()));
+                }
+                let modem = Paragraph::new(modem_text)
+                    .block(Block::default().title("Modem lines").borders(Borders::ALL));
+                f.render_widget(modem, sub[2]);
 
-                let viewport = sub[2].height.saturating_sub(2) as usize;
+                let viewport = sub[3].height.saturating_sub(2) as usize;
                 let start = logs.len().saturating_sub(viewport + log_scroll);
-                let log_items: Vec<ListItem> = logs.iter().skip(start).map(|l| ListItem::new(l.clone())).collect();
-                let log_list = List::new(log_items).block(Block::default().title("Events").borders(Borders::ALL));
-                f.render_widget(log_list, sub[2]);
+                let log_items: Vec<ListItem> = logs
+                    .iter()
+                    .skip(start)
+                    .map(|l| ListItem::new(l.clone()))
+                    .collect();
+                let log_list = List::new(log_items)
+                    .block(Block::default().title("Events").borders(Borders::ALL));
+                f.render_widget(log_list, sub[3]);
             } else {
                 // Inspector tab: header summary + dump list
                 let main = outer[1];
                 // Sidebar + main list
                 let columns = Layout::default()
                     .direction(Direction::Horizontal)
-                    .constraints([
-                        Constraint::Length(24), // sidebar
-                        Constraint::Min(0),     // inspector content
-                    ].as_ref())
+                    .constraints(
+                        [
+                            Constraint::Length(24), // sidebar
+                            Constraint::Min(0),     // inspector content
+                        ]
+                        .as_ref(),
+                    )
                     .split(main);
 
                 // Sidebar devices
-                let dev_labels: Vec<String> = insp.devices.iter().map(|d| match d {
-                    DeviceId::Serial => "serial".to_string(),
-                    DeviceId::Client(a) => format!("{a}"),
-                }).collect();
-                let dev_items: Vec<ListItem> = dev_labels.iter().enumerate().map(|(i, s)| {
-                    let prefix = if i == insp.selected { "> " } else {"  "};
-                    ListItem::new(format!("{prefix}{s}"))
-                }).collect();
-                let dev_list = List::new(dev_items).block(Block::default().title("Devices").borders(Borders::ALL));
+                let dev_labels: Vec<String> = insp
+                    .devices
+                    .iter()
+                    .map(|d| match d {
+                        DeviceId::All => "all devices".to_string(),
+                        DeviceId::Serial => "serial".to_string(),
+                        DeviceId::Client(a) => shared.label(a).unwrap_or_else(|| a.to_string()),
+                    })
+                    .collect();
+                let dev_items: Vec<ListItem> = dev_labels
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| {
+                        let prefix = if i == insp.selected { "> " } else { "  " };
+                        ListItem::new(format!("{prefix}{s}"))
+                    })
+                    .collect();
+                let dev_list = List::new(dev_items)
+                    .block(Block::default().title("Devices").borders(Borders::ALL));
                 f.render_widget(dev_list, columns[0]);
 
                 let sub = Layout::default()
                     .direction(Direction::Vertical)
-                    .constraints([
-                        Constraint::Length(1), // header
-                        Constraint::Min(0),    // list
-                    ].as_ref())
+                    .constraints(
+                        [
+                            Constraint::Length(1), // header
+                            Constraint::Min(0),    // list
+                        ]
+                        .as_ref(),
+                    )
                     .split(columns[1]);
 
-                let header = Paragraph::new(format!(
-                    "fmt: {:?} | status: {}",
+                let mut header_text = format!(
+                    "fmt: {:?} | dir: {} | view: {} | lines: {} | status: {}",
                     insp.format,
+                    insp.direction.label(),
+                    insp.view.label(),
+                    if insp.clips() { "clipped" } else { "wrapped" },
                     if insp.paused { "paused" } else { "resumed" }
-                ));
+                );
+                let dropped = counters.inspector_dropped.load(Ordering::Relaxed);
+                if dropped > 0 {
+                    header_text.push_str(&format!(" | {dropped} samples dropped"));
+                }
+                if let Some(notice) = &insp.notice {
+                    header_text.push_str(&format!(" | {notice}"));
+                }
+                let header = Paragraph::new(header_text);
                 f.render_widget(header, sub[0]);
 
-                let para = crate::ui::inspector::inspector_paragraph(&insp, sub[1]);
+                // Split off a diff pane once two samples are marked
+                let (messages_area, diff_area) = match insp.marked_pair() {
+                    Some(_) => {
+                        let parts = Layout::default()
+                            .direction(Direction::Vertical)
+                            .constraints([Constraint::Min(0), Constraint::Length(8)].as_ref())
+                            .split(sub[1]);
+                        (parts[0], Some(parts[1]))
+                    }
+                    None => (sub[1], None),
+                };
+
+                insp_rows = messages_area.height.saturating_sub(2) as usize;
+                insp_cols = messages_area.width.saturating_sub(2) as usize;
                 let block = Block::default().title("Messages").borders(Borders::ALL);
-                f.render_widget(para.block(block), sub[1]);
+                match insp.view {
+                    InspectorView::Wrapped => {
+                        let para = crate::ui::inspector::inspector_paragraph(&insp, messages_area);
+                        f.render_widget(para.block(block), messages_area);
+                    }
+                    InspectorView::Compact => {
+                        let list = crate::ui::inspector::inspector_items(&insp, messages_area);
+                        f.render_widget(list.block(block), messages_area);
+                    }
+                }
+
+                if let (Some(area), Some((a, b))) = (diff_area, insp.marked_pair()) {
+                    let diff = crate::ui::inspector::diff_paragraph(&a.data, &b.data)
+                        .block(Block::default().title("Diff A/B").borders(Borders::ALL));
+                    f.render_widget(diff, area);
+                }
             }
 
             // Sticky footer with keybinds
-            let footer = if active_tab == 0 {
-                Paragraph::new("Tab: inspector | q: quit | ↑/↓/Home: scroll events | c: clear events")
+            let footer = if let Some(text) = &palette {
+                Paragraph::new(format!(
+                    "Inject (0x.. for hex bytes, Enter: send, Esc: cancel)> {text}"
+                ))
+                .style(Style::default().fg(Color::Yellow))
+            } else if active_tab == 0 {
+                Paragraph::new(overview_footer.as_str())
             } else {
-                Paragraph::new("Tab: overview | q: quit | t: toggle type | p: pause/resume | ↑/↓: select device | Home: top | c: clear")
+                Paragraph::new(inspector_footer.as_str())
             };
             f.render_widget(footer, outer[2]);
         })?;
 
         if event::poll(Duration::from_millis(200))? {
-            if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Char('q')
-                    || (key.code == KeyCode::Char('c')
-                        && key.modifiers.contains(KeyModifiers::CONTROL))
-                {
+            let key = match event::read()? {
+                Event::Key(ev) => Some(ev),
+                _ => None,
+            };
+            // An open palette takes every key until Enter or Esc
+            if let (Some(ev), Some(text)) = (key, palette.as_mut()) {
+                match ev.code {
+                    KeyCode::Enter => {
+                        let msg = match parse_injection(text) {
+                            Ok(bytes) => {
+                                let n = bytes.len();
+                                match inject.try_send(Bytes::from(bytes)) {
+                                    Ok(()) => format!("Injected {n} byte(s)"),
+                                    Err(_) => "Inject failed: serial write queue full".to_string(),
+                                }
+                            }
+                            Err(e) => format!("Inject failed: {e}"),
+                        };
+                        tracing::info!("{msg}");
+                        logs.push(msg);
+                        palette = None;
+                    }
+                    KeyCode::Esc => palette = None,
+                    KeyCode::Backspace => {
+                        text.pop();
+                    }
+                    KeyCode::Char(c) => text.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+            let action = key.and_then(|ev| keymap.action(&ev));
+            if let Some(action) = action {
+                if action == Action::Quit {
                     stop.store(true, Ordering::Relaxed);
-                } else if key.code == KeyCode::Tab {
+                } else if action == Action::ToggleSerialReads {
+                    let paused = !counters.serial_read_paused.load(Ordering::Relaxed);
+                    counters.serial_read_paused.store(paused, Ordering::Relaxed);
+                    let msg = if paused {
+                        "Serial reads paused"
+                    } else {
+                        "Serial reads resumed"
+                    };
+                    tracing::info!("{msg}");
+                    logs.push(msg.to_string());
+                } else if action == Action::ClearError {
+                    counters.last_error.clear();
+                } else if action == Action::NextTab {
                     _prev_tab = active_tab;
                     active_tab = (active_tab + 1) % 2;
                     if active_tab == 0 {
                         // leaving inspector: clear state
-                        insp.capture.clear();
-                        insp.devices = vec![DeviceId::Serial];
-                        insp.selected = 0;
-                        insp.scroll = 0;
+                        insp.clear();
+                        insp.reset_devices();
                         insp.paused = false;
                     }
                 } else if active_tab == 0 {
-                    match key.code {
-                        KeyCode::Up => {
+                    match action {
+                        Action::Up => {
                             log_scroll = log_scroll.saturating_add(1);
                         }
-                        KeyCode::Down => {
+                        Action::Down => {
                             log_scroll = log_scroll.saturating_sub(1);
                         }
-                        KeyCode::Home => {
+                        Action::Top => {
                             log_scroll = 0;
                         }
-                        KeyCode::Char('c') => {
+                        Action::Clear => {
                             logs.clear();
                             log_scroll = 0;
                         }
+                        Action::CycleSmoothing => {
+                            let tau = next_smoothing(avg_in.tau_secs());
+                            avg_in = ThroughputAverager::new(tau);
+                            avg_out = ThroughputAverager::new(tau);
+                        }
+                        Action::PrevConnection => {
+                            conn_selected = conn_selected.saturating_sub(1);
+                        }
+                        Action::NextConnection => {
+                            conn_selected += 1;
+                        }
+                        Action::Inject => palette = Some(String::new()),
+                        Action::Kick => {
+                            if let Some(addr) = shared.sorted_addrs().get(conn_selected) {
+                                if shared.kick(addr) {
+                                    tracing::info!(%addr, "Kicked connection");
+                                    logs.push(format!("Kicked: {addr}"));
+                                }
+                            }
+                        }
                         _ => {}
                     }
                 } else {
-                    match key.code {
-                        KeyCode::Char('t') => {
-                            insp.format = match insp.format {
-                                DumpFormat::Hex => DumpFormat::Ascii,
-                                DumpFormat::Ascii => DumpFormat::Dec,
-                                DumpFormat::Dec => DumpFormat::Hex,
-                            };
+                    match action {
+                        Action::CycleFormat => {
+                            insp.format = insp.format.next();
+                        }
+                        Action::CycleDirection => {
+                            insp.direction = insp.direction.next();
+                            insp.scroll = 0;
+                        }
+                        Action::ToggleView => {
+                            insp.view = insp.view.toggle();
+                            insp.hscroll = 0;
+                        }
+                        Action::ToggleWrap => {
+                            insp.wrap = !insp.wrap;
+                            insp.hscroll = 0;
                         }
-                        KeyCode::Char('p') => {
+                        Action::ToggleGutter => insp.gutter = !insp.gutter,
+                        Action::PrevConnection if insp.clips() => {
+                            insp.hscroll = insp.hscroll.saturating_sub(HSCROLL_STEP);
+                        }
+                        Action::NextConnection if insp.clips() => {
+                            let lines =
+                                crate::ui::inspector::visible_lines(&insp, insp_rows, insp_cols);
+                            let max = max_hscroll(lines.iter().map(String::as_str), insp_cols);
+                            insp.hscroll = (insp.hscroll + HSCROLL_STEP).min(max);
+                        }
+                        Action::TogglePause => {
                             insp.paused = !insp.paused;
                         }
-                        KeyCode::Char('c') => {
-                            insp.capture.clear();
-                            insp.scroll = 0;
+                        Action::Clear => {
+                            insp.clear();
                         }
-                        KeyCode::Up => {
-                            if insp.selected > 0 {
-                                insp.selected -= 1;
-                            }
+                        Action::Mark => insp.mark_current(),
+                        Action::Unmark => insp.marks = [None, None],
+                        Action::ZeroTime => insp.t_zero = Some(Instant::now()),
+                        Action::Copy => {
+                            let lines =
+                                crate::ui::inspector::visible_lines(&insp, insp_rows, insp_cols);
+                            insp.notice = Some(clipboard.copy(&lines.join("\n")));
                         }
-                        KeyCode::Down => {
-                            if insp.selected + 1 < insp.devices.len() {
-                                insp.selected += 1;
-                            }
+                        #[cfg(feature = "pcap")]
+                        Action::SavePcap => {
+                            insp.notice =
+                                Some(match crate::ui::pcap::export_capture(&insp.capture) {
+                                    Ok((path, n)) => {
+                                        format!("saved {n} samples to {}", path.display())
+                                    }
+                                    Err(e) => format!("pcap export failed: {e}"),
+                                });
+                        }
+                        Action::SaveJsonl => {
+                            insp.notice =
+                                Some(match crate::ui::jsonl::export_jsonl(&insp.capture) {
+                                    Ok((path, n)) => {
+                                        format!("saved {n} samples to {}", path.display())
+                                    }
+                                    Err(e) => format!("jsonl export failed: {e}"),
+                                });
+                        }
+                        Action::PageUp => {
+                            let max = insp.visible_indices().len().saturating_sub(1);
+                            insp.scroll = (insp.scroll + 1).min(max);
+                        }
+                        Action::PageDown => {
+                            insp.scroll = insp.scroll.saturating_sub(1);
+                        }
+                        Action::Up => {
+                            insp.selected = insp.selected.saturating_sub(1);
                         }
-                        KeyCode::Home => insp.scroll = 0,
+                        Action::Down if insp.selected + 1 < insp.devices.len() => {
+                            insp.selected += 1;
+                        }
+                        Action::Top => insp.scroll = 0,
                         _ => {}
                     }
                 }
@@ -266,6 +882,138 @@ pub fn run_tui(
 
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    if title.is_some() {
+        execute!(terminal.backend_mut(), Print(POP_TITLE))?;
+    }
     terminal.show_cursor()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_line() {
+        let mut device = DeviceLabel {
+            name: "/dev/ttyUSB0".into(),
+            has_baud: true,
+            adapter: Some("USB 10c4:ea60 Silicon Labs CP2102".into()),
+        };
+        assert_eq!(
+            device.line(9600),
+            "Device: /dev/ttyUSB0 @ 9600 baud | USB 10c4:ea60 Silicon Labs CP2102"
+        );
+        device.name = "tcp://10.0.0.1:5656".into();
+        device.has_baud = false;
+        device.adapter = None;
+        assert_eq!(device.line(9600), "Device: tcp://10.0.0.1:5656");
+        let bound = [
+            "127.0.0.1:41234".parse().unwrap(),
+            "[::1]:41234".parse().unwrap(),
+        ];
+        assert_eq!(
+            listening_line(&bound),
+            "Listening: 127.0.0.1:41234, [::1]:41234"
+        );
+    }
+
+    #[test]
+    fn last_error_until_cleared() {
+        let last = LastError::default();
+        assert_eq!(last.summary(), None);
+        last.record("read: Broken pipe");
+        let shown = last.summary().unwrap();
+        assert!(shown.starts_with("read: Broken pipe @ "), "{shown}");
+        assert!(shown.ends_with(" UTC"));
+        last.clear();
+        assert_eq!(last.summary(), None);
+        assert_eq!(
+            utc_clock(UNIX_EPOCH + Duration::from_secs(86_400 * 3 + 12 * 3600 + 3 * 60 + 45)),
+            "12:03:45 UTC"
+        );
+    }
+
+    #[test]
+    fn reconnect_attempts_roll_into_one_line() {
+        let mut logs = vec!["Connected: 127.0.0.1:5000".to_string()];
+        push_event(
+            &mut logs,
+            "Serial: reconnecting reader… (attempt 1): gone".into(),
+        );
+        push_event(
+            &mut logs,
+            "Serial: reconnecting writer… (attempt 1): gone".into(),
+        );
+        push_event(
+            &mut logs,
+            "Serial: reconnecting reader… (attempt 2): gone".into(),
+        );
+        push_event(
+            &mut logs,
+            "Serial: reconnecting writer… (attempt 2): gone".into(),
+        );
+        assert_eq!(
+            logs,
+            vec![
+                "Connected: 127.0.0.1:5000",
+                "Serial: reconnecting reader… (attempt 2): gone",
+                "Serial: reconnecting writer… (attempt 2): gone",
+            ]
+        );
+
+        // a regular event ends the rolling run
+        push_event(
+            &mut logs,
+            "Serial: reconnected (reader) after 3 attempt(s)".into(),
+        );
+        push_event(
+            &mut logs,
+            "Serial: reconnecting reader… (attempt 1): gone".into(),
+        );
+        assert_eq!(logs.len(), 5);
+    }
+
+    #[test]
+    fn title_names_device_and_clients() {
+        assert_eq!(
+            terminal_title("ttyUSB0", 3, true),
+            "sergw ttyUSB0 · 3 clients"
+        );
+        assert_eq!(
+            terminal_title("ttyUSB0", 1, true),
+            "sergw ttyUSB0 · 1 client"
+        );
+        assert_eq!(
+            terminal_title("ttyACM1", 0, false),
+            "sergw ttyACM1 · 0 clients · reconnecting"
+        );
+    }
+
+    #[test]
+    fn history_keeps_newest_samples() {
+        let mut history = VecDeque::new();
+        for rate in 0..(HISTORY_LEN as u64 + 5) {
+            push_history(&mut history, rate);
+        }
+        assert_eq!(history.len(), HISTORY_LEN);
+        assert_eq!(history.front(), Some(&5));
+        assert_eq!(history_tail(&history, 3), vec![62, 63, 64]);
+        assert_eq!(history_tail(&history, 200).len(), HISTORY_LEN);
+    }
+
+    #[test]
+    fn throughput_labels_follow_perspective() {
+        assert_eq!(fmt_rate(999), "999 B/s");
+        assert_eq!(fmt_rate(1_234), "1.2 KB/s");
+        assert_eq!(fmt_rate(2_500_000), "2.5 MB/s");
+        assert_eq!(
+            throughput_lines(ThroughputPerspective::Tcp, 1_200, 5),
+            "Inbound  serial→tcp: 1.2 KB/s\nOutbound tcp→serial: 5 B/s"
+        );
+        assert_eq!(
+            throughput_lines(ThroughputPerspective::Serial, 1_200, 5),
+            "Inbound  tcp→serial: 5 B/s\nOutbound serial→tcp: 1.2 KB/s"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+// Wireshark export of inspector captures. Packets use LINKTYPE_USER0 with a
+// fixed 20-byte pseudo header in front of the payload:
+//
+//   u8  direction  0 = serial -> clients, 1 = client -> serial
+//   u8  family     0 = none (serial), 4 = IPv4, 6 = IPv6
+//   u16 port       client port, big-endian
+//   [u8; 16]       client address (IPv4 in the first 4 bytes)
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::ui::inspector::{DirectionTag, Sample};
+
+const LINKTYPE_USER0: u32 = 147;
+const SNAPLEN: u32 = 65_535;
+pub const PSEUDO_HEADER_LEN: usize = 20;
+
+fn pseudo_header(dir: &DirectionTag) -> [u8; PSEUDO_HEADER_LEN] {
+    let mut h = [0u8; PSEUDO_HEADER_LEN];
+    if let DirectionTag::Outbound(addr) = dir {
+        h[0] = 1;
+        h[2..4].copy_from_slice(&addr.port().to_be_bytes());
+        match addr.ip() {
+            IpAddr::V4(ip) => {
+                h[1] = 4;
+                h[4..8].copy_from_slice(&ip.octets());
+            }
+            IpAddr::V6(ip) => {
+                h[1] = 6;
+                h[4..20].copy_from_slice(&ip.octets());
+            }
+        }
+    }
+    h
+}
+
+pub fn write_pcap<'a, W: Write>(
+    mut out: W,
+    samples: impl IntoIterator<Item = &'a Sample>,
+) -> io::Result<usize> {
+    // Global header: magic, v2.4, UTC offset, sigfigs, snaplen, link type
+    out.write_all(&0xa1b2_c3d4u32.to_le_bytes())?;
+    out.write_all(&2u16.to_le_bytes())?;
+    out.write_all(&4u16.to_le_bytes())?;
+    out.write_all(&0i32.to_le_bytes())?;
+    out.write_all(&0u32.to_le_bytes())?;
+    out.write_all(&SNAPLEN.to_le_bytes())?;
+    out.write_all(&LINKTYPE_USER0.to_le_bytes())?;
+
+    let mut count = 0;
+    for sample in samples {
+        let ts = sample.at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let len = (PSEUDO_HEADER_LEN + sample.len) as u32;
+        let incl = ((PSEUDO_HEADER_LEN + sample.data.len()) as u32).min(SNAPLEN);
+        out.write_all(&(ts.as_secs() as u32).to_le_bytes())?;
+        out.write_all(&ts.subsec_micros().to_le_bytes())?;
+        out.write_all(&incl.to_le_bytes())?;
+        out.write_all(&len.to_le_bytes())?;
+        out.write_all(&pseudo_header(&sample.dir))?;
+        out.write_all(&sample.data[..incl as usize - PSEUDO_HEADER_LEN])?;
+        count += 1;
+    }
+    out.flush()?;
+    Ok(count)
+}
+
+/// Write the samples to `sergw-<unix secs>.pcap` in the working directory.
+pub fn export_capture<'a>(
+    samples: impl IntoIterator<Item = &'a Sample>,
+) -> io::Result<(PathBuf, usize)> {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = PathBuf::from(format!("sergw-{secs}.pcap"));
+    let count = write_pcap(BufWriter::new(File::create(&path)?), samples)?;
+    Ok((path, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn writes_header_and_tagged_packets() {
+        let at = UNIX_EPOCH + Duration::from_micros(1_500_000);
+        let samples = [
+            Sample {
+                dir: DirectionTag::Inbound,
+                data: Bytes::from_static(b"hi"),
+                at,
+                seen: Instant::now(),
+                len: 2,
+            },
+            Sample {
+                dir: DirectionTag::Outbound("10.0.0.2:4000".parse().unwrap()),
+                data: Bytes::from_static(b"AT"),
+                at,
+                seen: Instant::now(),
+                len: 5,
+            },
+        ];
+        let mut out = Vec::new();
+        assert_eq!(write_pcap(&mut out, &samples).unwrap(), 2);
+
+        assert_eq!(&out[..4], &[0xd4, 0xc3, 0xb2, 0xa1]);
+        assert_eq!(&out[20..24], &LINKTYPE_USER0.to_le_bytes());
+        let rec = 16 + PSEUDO_HEADER_LEN + 2;
+        assert_eq!(out.len(), 24 + 2 * rec);
+
+        let first = &out[24..24 + rec];
+        assert_eq!(&first[0..4], &1u32.to_le_bytes());
+        assert_eq!(&first[4..8], &500_000u32.to_le_bytes());
+        assert_eq!(&first[8..12], &22u32.to_le_bytes());
+        assert_eq!(first[16], 0);
+        assert_eq!(&first[36..], b"hi");
+
+        let second = &out[24 + rec..];
+        // capped sample: captured length 22, original length 25
+        assert_eq!(&second[8..12], &22u32.to_le_bytes());
+        assert_eq!(&second[12..16], &25u32.to_le_bytes());
+        assert_eq!(&second[16..20], &[1, 4, 0x0f, 0xa0]);
+        assert_eq!(&second[20..24], &[10, 0, 0, 2]);
+        assert_eq!(&second[36..], b"AT");
+    }
+}
@@ -0,0 +1,113 @@
+// Terminal setup shared by the TUIs. Over SSH with a slow PTY, raw mode or
+// the alternate screen sometimes fails on the first try, so setup is retried
+// a few times, undoing whatever part of it succeeded before each new attempt.
+
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use crossterm::{
+    event::{DisableBracketedPaste, EnableBracketedPaste},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+const SETUP_ATTEMPTS: u32 = 3;
+const SETUP_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Raw mode and the alternate screen, plus bracketed paste when `paste`
+pub fn enter(paste: bool) -> Result<()> {
+    retry(
+        SETUP_ATTEMPTS,
+        SETUP_RETRY_DELAY,
+        || enter_once(paste),
+        || undo(paste),
+    )
+    .with_context(|| {
+        format!(
+            "Setting up the terminal (raw mode, alternate screen) failed {SETUP_ATTEMPTS} times"
+        )
+    })
+}
+
+fn enter_once(paste: bool) -> io::Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    if paste {
+        execute!(stdout, EnableBracketedPaste)?;
+    }
+    Ok(())
+}
+
+// Each step is harmless when the matching setup step never happened
+fn undo(paste: bool) {
+    let mut stdout = io::stdout();
+    if paste {
+        let _ = execute!(stdout, DisableBracketedPaste);
+    }
+    let _ = execute!(stdout, LeaveAlternateScreen);
+    let _ = disable_raw_mode();
+}
+
+/// Run `op` up to `attempts` times, calling `undo` and waiting `delay` after
+/// each failure; the last error is returned if none succeeds
+fn retry<T>(
+    attempts: u32,
+    delay: Duration,
+    mut op: impl FnMut() -> io::Result<T>,
+    mut undo: impl FnMut(),
+) -> io::Result<T> {
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                undo();
+                if attempt >= attempts {
+                    return Err(e);
+                }
+                attempt += 1;
+                thread::sleep(delay);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_and_cleans_up_between_attempts() {
+        let (mut calls, mut undone) = (0, 0);
+        let result = retry(
+            3,
+            Duration::ZERO,
+            || {
+                calls += 1;
+                match calls {
+                    3 => Ok(calls),
+                    _ => Err(io::Error::new(io::ErrorKind::WouldBlock, "EAGAIN")),
+                }
+            },
+            || undone += 1,
+        );
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(undone, 2);
+
+        let (mut calls, mut undone) = (0, 0);
+        let result: io::Result<()> = retry(
+            2,
+            Duration::ZERO,
+            || {
+                calls += 1;
+                Err(io::Error::new(io::ErrorKind::Other, "no tty"))
+            },
+            || undone += 1,
+        );
+        assert_eq!(result.unwrap_err().to_string(), "no tty");
+        assert_eq!((calls, undone), (2, 2));
+    }
+}